@@ -0,0 +1,61 @@
+//! Runs small Lox programs that stress variable access, calls and closures.
+//!
+//! `cargo bench` compares each run with the previous one, so running it
+//! before and after a change to the interpreter shows the difference.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use lox_one::interpreter::Interpreter;
+
+const PROGRAMS: [(&str, &str); 4] = [
+    (
+        "fib",
+        "fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+         fib(18);",
+    ),
+    (
+        "loop",
+        "var sum = 0;
+         for (var i = 0; i < 20000; i = i + 1) {
+             var square = i * i;
+             sum = sum + square;
+         }",
+    ),
+    (
+        "closures",
+        "fun counter() {
+             var count = 0;
+             return () => { count = count + 1; return count; };
+         }
+         var next = counter();
+         for (var i = 0; i < 10000; i = i + 1) next();",
+    ),
+    (
+        "nested_scopes",
+        "var total = 0;
+         for (var i = 0; i < 2000; i = i + 1) {
+             var a = i;
+             {
+                 var b = a + 1;
+                 {
+                     var c = b + a;
+                     total = total + a + b + c;
+                 }
+             }
+         }",
+    ),
+];
+
+fn programs(c: &mut Criterion) {
+    for (name, source) in PROGRAMS {
+        c.bench_function(name, |b| {
+            b.iter(|| {
+                let mut interpreter = Interpreter::new();
+                interpreter.interpret_str(source).unwrap();
+            })
+        });
+    }
+}
+
+criterion_group!(benches, programs);
+criterion_main!(benches);
@@ -1,9 +1,28 @@
-use std::io::{self, Write};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
 
-use clap::Parser;
+use clap::{Args, Parser, ValueEnum};
 use colored::Colorize;
 
-use lox_one::{error::LoxError, interpreter::Interpreter};
+use lox_one::{
+    checker::check_source,
+    coverage::{Coverage, Recorder},
+    debugger::{terminal::Terminal, Debugger},
+    dump::{program_to_json, program_to_sexpr, tokens_to_json},
+    error::LoxError,
+    formatter::format_source,
+    interpreter::{error::RuntimeError, heap::GcConfig, Interpreter},
+    linter::lint_source,
+    optimizer::optimize,
+    parser::Parser as LoxParser,
+    profiler::Profiler,
+    scanner::Scanner,
+    testing::{discover, run_tests},
+    tracer::{TraceOptions, Tracer},
+};
+
+use crate::repl::run_repl;
 
 #[derive(Parser)]
 #[command(name = "lox_one")]
@@ -15,58 +34,415 @@ pub enum CliArgs {
     #[command(about = "Runs the lox_one REPL.")]
     Repl,
     #[command(about = "Runs the given Lox program file.")]
-    Run { src_path: String },
+    Run(RunArgs),
+    #[command(about = "Runs the test_* functions of the *_test.lox files in the given paths.")]
+    Test {
+        #[arg(default_value = ".")]
+        paths: Vec<String>,
+    },
+    #[command(about = "Runs the given Lox program file in a debugger.")]
+    Debug {
+        src_path: String,
+        /// Lines to add breakpoints at before the program starts.
+        #[arg(short, long = "break")]
+        breakpoints: Vec<usize>,
+    },
+    #[command(about = "Runs the Lox language server over stdio.")]
+    Lsp,
+    #[command(about = "Runs the Lox debug adapter over stdio.")]
+    Dap,
+    #[command(about = "Reports likely mistakes in the given Lox program files.")]
+    Lint { src_paths: Vec<String> },
+    #[command(about = "Type checks the given Lox program files using their annotations.")]
+    Check { src_paths: Vec<String> },
+    #[command(about = "Formats the given Lox program files in place.")]
+    Fmt {
+        src_paths: Vec<String>,
+        /// Don't write anything, exit with an error if any file isn't formatted.
+        #[arg(long)]
+        check: bool,
+    },
+    #[command(about = "Prints the syntax tree of the given Lox program file.")]
+    Parse {
+        src_path: String,
+        #[arg(long, value_enum, default_value_t = TreeFormat::Json)]
+        format: TreeFormat,
+    },
+    #[command(about = "Prints the tokens of the given Lox program file.")]
+    Tokens {
+        src_path: String,
+        #[arg(long, value_enum, default_value_t = TokenFormat::Text)]
+        format: TokenFormat,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum TreeFormat {
+    /// The versioned schema documented in `lox_one::dump`.
+    Json,
+    /// S-expressions, one top level statement per line.
+    Sexpr,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum TokenFormat {
+    /// A table, for people.
+    Text,
+    /// The versioned schema documented in `lox_one::dump`.
+    Json,
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    src_path: String,
+    /// Logs every statement, call and assignment as JSON lines, to stderr by default, which
+    /// leaves out lint warnings.
+    #[arg(long, conflicts_with = "profile")]
+    trace: bool,
+    /// Writes the trace to this file.
+    #[arg(long, requires = "trace")]
+    trace_output: Option<String>,
+    /// Only traces what happens while these functions run.
+    #[arg(long = "trace-function", requires = "trace")]
+    trace_functions: Vec<String>,
+    /// Only traces calls up to this depth, 0 is the script itself.
+    #[arg(long, requires = "trace")]
+    trace_depth: Option<usize>,
+    /// Reports the time spent in each function and how often each line ran to stderr.
+    #[arg(long)]
+    profile: bool,
+    /// Writes the profiled call stacks to this file, folded for flamegraph tools.
+    #[arg(long, requires = "profile")]
+    profile_folded: Option<String>,
+    /// Writes the lines and branches that ran to this LCOV file, with a summary on stderr.
+    #[arg(long, conflicts_with_all = ["trace", "profile"])]
+    coverage: Option<String>,
+    /// Folds constant expressions and drops code that can't run before running the program.
+    #[arg(long, conflicts_with = "coverage")]
+    optimize: bool,
+    /// Reports how many scopes were allocated and freed by the garbage collector to stderr.
+    #[arg(long)]
+    gc_stats: bool,
+    /// Collects garbage once the scopes that survived the last collection and those created since reach this many.
+    #[arg(long, default_value_t = GcConfig::default().threshold)]
+    gc_threshold: usize,
+    /// Waits for this many times the scopes that survived a collection before the next one.
+    #[arg(long, default_value_t = GcConfig::default().growth)]
+    gc_growth: usize,
+    /// Collects garbage every time a scope is created, to test the collector.
+    #[arg(long)]
+    gc_stress: bool,
 }
 
 pub fn execute_args(args: &CliArgs) {
     use CliArgs::*;
     match args {
         Repl => run_repl(false),
-        Run { src_path } => run_file(src_path),
+        Run(args) => run_file(args),
+        Debug {
+            src_path,
+            breakpoints,
+        } => debug_file(src_path, breakpoints),
+        Test { paths } => test_paths(paths),
+        Lint { src_paths } => lint_files(src_paths),
+        Check { src_paths } => check_files(src_paths),
+        Fmt { src_paths, check } => format_files(src_paths, *check),
+        Parse { src_path, format } => parse_file(src_path, *format),
+        Tokens { src_path, format } => scan_file(src_path, *format),
+        // Stdout carries the protocol, so errors go to stderr.
+        Lsp => lox_one::lsp::run_stdio().unwrap_or_else(|e| {
+            eprintln!("{}", LoxError::IO(e));
+            std::process::exit(1);
+        }),
+        Dap => lox_one::dap::run_stdio().unwrap_or_else(|e| {
+            eprintln!("{}", LoxError::IO(e));
+            std::process::exit(1);
+        }),
     }
 }
 
-fn run_file(src_path: &str) {
-    let src = std::fs::read_to_string(src_path).expect(&format!("Error finding file {src_path}"));
-    Interpreter::new().interpret_str(&src).unwrap_or_else(|e| {
+fn run_file(args: &RunArgs) {
+    let src_path = &args.src_path;
+    let src = std::fs::read_to_string(src_path)
+        .unwrap_or_else(|_| panic!("Error finding file {src_path}"));
+
+    // Errors are left for the interpreter to report. A trace on stderr is read by tools,
+    // warnings would get mixed into it.
+    let trace_on_stderr = args.trace && args.trace_output.is_none();
+    if !trace_on_stderr {
+        for warning in lint_source(&src).unwrap_or_default() {
+            eprintln!("{src_path}: {}", warning.to_string().yellow());
+        }
+    }
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_gc_config(GcConfig {
+        threshold: args.gc_threshold,
+        growth: args.gc_growth,
+        stress: args.gc_stress,
+    });
+    if args.trace {
+        let output: Box<dyn Write> = match &args.trace_output {
+            Some(path) => Box::new(BufWriter::new(create_file(path))),
+            None => Box::new(std::io::stderr()),
+        };
+        let options = TraceOptions {
+            functions: args.trace_functions.clone(),
+            max_depth: args.trace_depth,
+        };
+        interpreter.set_hook(Box::new(Tracer::new(output, options)));
+    }
+    let profile = args.profile.then(|| {
+        let profiler = Profiler::new();
+        let profile = profiler.profile();
+        interpreter.set_hook(Box::new(profiler));
+        profile
+    });
+
+    // A program that doesn't parse has no coverage, running it reports the errors.
+    let coverage = args.coverage.as_ref().map(|_| {
+        let recorder = Recorder::new(Coverage::from_source(&src).unwrap_or_default());
+        let coverage = recorder.coverage();
+        interpreter.set_hook(Box::new(recorder));
+        coverage
+    });
+
+    let result = if args.optimize {
+        LoxParser::parse_str(&src).and_then(|mut statements| {
+            optimize(&mut statements);
+            interpreter
+                .interpret(&statements, false, false)
+                .map_err(LoxError::Runtime)
+        })
+    } else {
+        interpreter.interpret_str(&src)
+    };
+    // Dropping the tracer flushes the trace before a failure is reported.
+    drop(interpreter.take_hook());
+
+    if let Some(profile) = profile {
+        let mut profile = profile.borrow_mut();
+        profile.finish();
+        eprint!("{}", profile.report());
+
+        if let Some(path) = &args.profile_folded {
+            create_file(path)
+                .write_all(profile.folded().as_bytes())
+                .unwrap_or_else(|e| println!("Couldn't write {path}: {e}"));
+        }
+    }
+
+    if let (Some(coverage), Some(path)) = (coverage, &args.coverage) {
+        let coverage = coverage.borrow();
+        eprintln!("{}", coverage.summary(src_path));
+
+        create_file(path)
+            .write_all(coverage.lcov(src_path).as_bytes())
+            .unwrap_or_else(|e| println!("Couldn't write {path}: {e}"));
+    }
+
+    if args.gc_stats {
+        eprintln!("gc: {}", interpreter.gc_stats());
+    }
+
+    result.unwrap_or_else(|e| {
         println!("{e}");
         panic!()
     });
 }
 
-fn run_repl(_verbose: bool) {
+fn create_file(path: &str) -> File {
+    File::create(path).unwrap_or_else(|e| {
+        println!("Couldn't create {path}: {e}");
+        std::process::exit(1);
+    })
+}
+
+fn debug_file(src_path: &str, breakpoints: &[usize]) {
+    let src = std::fs::read_to_string(src_path)
+        .unwrap_or_else(|_| panic!("Error finding file {src_path}"));
+
+    println!("Debugging {src_path}, enter 'help' for the commands.");
+    let terminal = Terminal::new(&src, std::io::stdin().lock(), std::io::stdout());
     let mut interpreter = Interpreter::new();
-    println!("Lox Interpreter Version 0.1.0");
-    println!("Enter 'exit' or ':q' to quit.");
+    interpreter.set_hook(Box::new(Debugger::new(
+        terminal,
+        breakpoints.iter().copied(),
+    )));
+
+    match interpreter.interpret_str(&src) {
+        Ok(_) => println!("{}", "The program finished.".green().bold()),
+        Err(LoxError::Runtime(RuntimeError::Stopped(_))) => {
+            println!("{}", "The program was stopped.".yellow().bold())
+        }
+        Err(e) => {
+            println!("{e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn test_paths(paths: &[String]) {
+    let (mut passed, mut failed) = (0, 0);
+
+    for path in paths {
+        let files = discover(Path::new(path)).unwrap_or_else(|e| {
+            println!("{path}: {}", LoxError::IO(e));
+            std::process::exit(1);
+        });
+
+        for file in files {
+            let src_path = file.display();
+            let src = std::fs::read_to_string(&file)
+                .unwrap_or_else(|_| panic!("Error finding file {src_path}"));
+            println!("{src_path}");
+
+            let results = match run_tests(&src) {
+                Ok(results) => results,
+                Err(e) => {
+                    println!("{e}");
+                    failed += 1;
+                    continue;
+                }
+            };
 
-    'a: loop {
-        print!("{} ", "λ> ".cyan().bold());
-        io::stdout().flush().unwrap();
+            for result in results {
+                let Some(error) = &result.error else {
+                    println!("  {} {}", "ok".green().bold(), result.name);
+                    passed += 1;
+                    continue;
+                };
 
-        let mut input = String::new();
-        if let Err(e) = io::stdin().read_line(&mut input) {
-            println!("{}", LoxError::IO(e));
-            break 'a;
+                println!("  {} {}", "FAILED".red().bold(), result.name);
+                for line in error.to_string().lines() {
+                    println!("    {line}");
+                }
+                if !result.output.is_empty() {
+                    println!("    Output:");
+                    for line in result.output.lines() {
+                        println!("      {line}");
+                    }
+                }
+                failed += 1;
+            }
         }
+    }
+
+    println!("\n{passed} passed, {failed} failed");
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
 
-        if input.starts_with(":q") || input.starts_with("exit") {
-            println!(
-                "{}",
-                "Goodbye and thanks for all the fish ><> ><>".green().bold(),
-            );
-            break 'a;
+fn parse_file(src_path: &str, format: TreeFormat) {
+    let src = std::fs::read_to_string(src_path)
+        .unwrap_or_else(|_| panic!("Error finding file {src_path}"));
+    let statements = LoxParser::parse_str(&src).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+
+    match format {
+        TreeFormat::Json => println!("{:#}", program_to_json(&statements)),
+        TreeFormat::Sexpr => print!("{}", program_to_sexpr(&statements)),
+    }
+}
+
+fn scan_file(src_path: &str, format: TokenFormat) {
+    let src = std::fs::read_to_string(src_path)
+        .unwrap_or_else(|_| panic!("Error finding file {src_path}"));
+    let display = matches!(format, TokenFormat::Text);
+    let tokens = Scanner::tokens_from_str(&src, display).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+
+    if let TokenFormat::Json = format {
+        println!("{:#}", tokens_to_json(&tokens));
+    }
+}
+
+fn lint_files(src_paths: &[String]) {
+    let mut failed = false;
+
+    for src_path in src_paths {
+        let src = std::fs::read_to_string(src_path)
+            .unwrap_or_else(|_| panic!("Error finding file {src_path}"));
+
+        match lint_source(&src) {
+            Ok(warnings) => {
+                for warning in &warnings {
+                    println!("{src_path}: {}", warning.to_string().yellow());
+                }
+                failed = failed || !warnings.is_empty();
+            }
+            Err(e) => {
+                println!("{src_path}:\n{e}");
+                failed = true;
+            }
         }
+    }
 
-        match interpreter.interpret_str(&input) {
-            Ok(Some(v)) => {
-                println!("{} {}", "Result:".blink().bold(), v);
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+fn check_files(src_paths: &[String]) {
+    let mut failed = false;
+
+    for src_path in src_paths {
+        let src = std::fs::read_to_string(src_path)
+            .unwrap_or_else(|_| panic!("Error finding file {src_path}"));
+
+        match check_source(&src) {
+            Ok(errors) => {
+                for error in &errors {
+                    println!("{src_path}: {} {error}", "Type Error:".red().bold());
+                }
+                failed = failed || !errors.is_empty();
             }
-            Ok(_) => continue,
             Err(e) => {
-                println!("{}", e);
+                println!("{src_path}:\n{e}");
+                failed = true;
             }
-        };
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
     }
 }
 
-fn main() {}
+fn format_files(src_paths: &[String], check: bool) {
+    let mut failed = false;
+
+    for src_path in src_paths {
+        let src = std::fs::read_to_string(src_path)
+            .unwrap_or_else(|_| panic!("Error finding file {src_path}"));
+        let formatted = match format_source(&src) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                println!("{src_path}:\n{e}");
+                failed = true;
+                continue;
+            }
+        };
+
+        if formatted == src {
+            continue;
+        }
+
+        if check {
+            println!("{} {src_path}", "Not formatted:".red().bold());
+            failed = true;
+        } else {
+            std::fs::write(src_path, formatted)
+                .unwrap_or_else(|_| panic!("Error writing file {src_path}"));
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -1,4 +1,5 @@
 mod cli;
+mod repl;
 
 use clap::Parser;
 use cli::{execute_args, CliArgs};
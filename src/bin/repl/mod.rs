@@ -0,0 +1,372 @@
+#[cfg(test)]
+mod tests;
+
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use colored::Colorize;
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{error::ReadlineError, CompletionType, Config, EditMode, Editor, Helper};
+
+use lox_one::{
+    checker::infer_type,
+    error::{LoxError, LoxResult},
+    interpreter::Interpreter,
+    lox_value::LoxValue,
+    parser::Parser,
+    scanner::Scanner,
+    types::Type,
+};
+
+const HISTORY_FILE: &str = ".lox_history";
+
+const COMMANDS: [&str; 10] = [
+    ":help", ":env", ":load", ":reset", ":tokens", ":ast", ":time", ":type", ":verbose", ":q",
+];
+
+const HELP: &str = "\
+Commands:
+  :help           Shows this message
+  :env            Lists the global variables and their values
+  :load <file>    Runs a Lox file in the current session
+  :reset          Forgets everything defined so far
+  :tokens <code>  Shows the tokens the code is scanned into
+  :ast <code>     Shows the syntax tree the code is parsed into
+  :time <code>    Runs the code and shows how long it took
+  :type <expr>    Shows the type of an expression, without running it
+  :verbose        Toggles showing the tokens and syntax tree of every input
+  :q, exit        Quits the REPL";
+
+struct Repl {
+    interpreter: Interpreter,
+    verbose: bool,
+}
+
+/// Completes keywords, meta-commands and the names defined in the session and hints
+/// at the parameters of a function once its call is opened.
+struct ReplHelper {
+    keywords: Vec<&'static str>,
+    /// The names visible in the interpreter with the parameters of the functions,
+    /// refreshed before every input.
+    names: Vec<(String, Option<String>)>,
+}
+
+pub fn run_repl(verbose: bool) {
+    let mut repl = Repl {
+        interpreter: Interpreter::new(),
+        verbose,
+    };
+    println!("Lox Interpreter Version 0.1.0");
+    println!("Enter ':help' for the commands, 'exit' or ':q' to quit, Ctrl-C clears the input.");
+
+    let config = Config::builder()
+        .edit_mode(EditMode::Emacs)
+        .completion_type(CompletionType::List)
+        .auto_add_history(false)
+        .max_history_size(1000)
+        .and_then(|builder| builder.history_ignore_dups(true))
+        .map(|builder| builder.build())
+        .unwrap_or_default();
+    let mut editor: Editor<ReplHelper, DefaultHistory> = Editor::with_config(config)
+        .unwrap_or_else(|e| {
+            println!("{} {}", "Error starting the REPL:".red().bold(), e);
+            panic!()
+        });
+    editor.set_helper(Some(ReplHelper::new()));
+
+    let history = history_path();
+    if let Some(path) = &history {
+        // There's no history the first time the REPL is used.
+        editor.load_history(path).ok();
+    }
+
+    loop {
+        if let Some(helper) = editor.helper_mut() {
+            helper.update(&repl.interpreter);
+        }
+
+        let Some(input) = read_input(&mut editor) else {
+            break;
+        };
+
+        let trimmed = input.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        editor.add_history_entry(trimmed).ok();
+
+        if trimmed == ":q" || trimmed == "exit" {
+            break;
+        }
+
+        match trimmed.strip_prefix(':') {
+            Some(command) => repl.command(command),
+            None => repl.run(&input),
+        }
+    }
+
+    if let Some(path) = &history {
+        if let Err(e) = editor.save_history(path) {
+            println!("{} {}", "Error saving the REPL history:".red().bold(), e);
+        }
+    }
+
+    println!(
+        "{}",
+        "Goodbye and thanks for all the fish ><> ><>".green().bold(),
+    );
+}
+
+impl Repl {
+    fn run(&mut self, source: &str) {
+        if self.verbose {
+            self.tokens(source);
+            self.ast(source);
+        }
+
+        match self.interpreter.interpret_str(source) {
+            Ok(Some(v)) => {
+                println!("{} {}", "Result:".blink().bold(), v);
+            }
+            Ok(_) => (),
+            Err(e) => {
+                println!("{}", e);
+            }
+        };
+    }
+
+    /// Runs a meta-command, `command` is the input without its leading ':'.
+    fn command(&mut self, command: &str) {
+        let (name, argument) = split_command(command);
+
+        match (name, argument) {
+            ("help", _) => println!("{HELP}"),
+            ("env", _) => self.env(),
+            ("reset", _) => {
+                self.interpreter = Interpreter::new();
+                println!("Cleared the environment.");
+            }
+            ("verbose", _) => {
+                self.verbose = !self.verbose;
+                let state = if self.verbose { "on" } else { "off" };
+                println!("Verbose mode is {state}.");
+            }
+            ("load" | "tokens" | "ast" | "time" | "type", "") => {
+                println!("{} ':{name}' needs an argument.", "Error:".red().bold());
+            }
+            ("load", path) => match std::fs::read_to_string(path) {
+                Ok(source) => self.run(&source),
+                Err(e) => println!("{}", LoxError::IO(e)),
+            },
+            ("tokens", source) => self.tokens(source),
+            ("ast", source) => self.ast(source),
+            ("time", source) => {
+                let start = Instant::now();
+                self.run(source);
+                println!("{} {:?}", "Time:".bold(), start.elapsed());
+            }
+            ("type", source) => match self.type_of(source) {
+                Ok(ty) => println!("{} {}", "Type:".bold(), ty),
+                Err(e) => println!("{}", e),
+            },
+            _ => println!(
+                "{} Unknown command ':{name}', enter ':help' for the commands.",
+                "Error:".red().bold()
+            ),
+        }
+    }
+
+    /// Infers the type of an expression with the types of the values defined so far.
+    fn type_of(&self, source: &str) -> LoxResult<Type> {
+        let globals: Vec<(String, Type)> = self
+            .interpreter
+            .visible_bindings()
+            .into_iter()
+            .map(|(name, value)| (name, value.type_of()))
+            .collect();
+
+        infer_type(source, &globals)
+    }
+
+    fn env(&self) {
+        let mut names = self.interpreter.globals.names();
+        names.sort();
+
+        for name in names {
+            if let Some(value) = self.interpreter.globals.get(&name) {
+                println!("{name} = {value}");
+            }
+        }
+    }
+
+    fn tokens(&self, source: &str) {
+        if let Err(e) = Scanner::tokens_from_str(source, true) {
+            println!("{}", LoxError::Scan(e));
+        }
+    }
+
+    fn ast(&self, source: &str) {
+        match Parser::parse_str(source) {
+            Ok(statements) => println!("{:#?}", statements),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+impl ReplHelper {
+    fn new() -> ReplHelper {
+        let mut keywords: Vec<&'static str> = Scanner::new("").keywords.into_keys().collect();
+        keywords.sort();
+
+        ReplHelper {
+            keywords,
+            names: Vec::new(),
+        }
+    }
+
+    fn update(&mut self, interpreter: &Interpreter) {
+        self.names = interpreter
+            .visible_bindings()
+            .into_iter()
+            .map(|(name, value)| match value {
+                LoxValue::Function(fun) => (name, Some(fun.params_repr())),
+                _ => (name, None),
+            })
+            .collect();
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let before = &line[..pos];
+
+        // Meta-commands are only completed at the start of the line.
+        if before.starts_with(':') && !before.contains(char::is_whitespace) {
+            let commands = COMMANDS
+                .iter()
+                .filter(|command| command.starts_with(before))
+                .map(|command| command.to_string())
+                .collect();
+            return Ok((0, commands));
+        }
+
+        let start = word_start(before);
+        let word = &before[start..];
+        if word.is_empty() {
+            return Ok((pos, Vec::new()));
+        }
+
+        let mut candidates: Vec<String> = self
+            .keywords
+            .iter()
+            .copied()
+            .chain(self.names.iter().map(|(name, _)| name.as_str()))
+            .filter(|candidate| candidate.starts_with(word))
+            .map(|candidate| candidate.to_string())
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+
+    /// Shows the parameters of a function right after the '(' of a call to it.
+    fn hint(&self, line: &str, pos: usize, _ctx: &rustyline::Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+
+        let callee = line.strip_suffix('(')?;
+        let callee = &callee[word_start(callee)..];
+
+        self.names
+            .iter()
+            .find(|(name, _)| name == callee)
+            .and_then(|(_, params)| params.as_ref())
+            .map(|params| format!("{params})"))
+    }
+}
+
+impl Highlighter for ReplHelper {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(hint.dimmed().to_string())
+    }
+}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// Reads lines until they make up a complete input, `None` once the input ends.
+fn read_input(editor: &mut Editor<ReplHelper, DefaultHistory>) -> Option<String> {
+    let mut input = String::new();
+
+    loop {
+        let prompt = if input.is_empty() {
+            format!("{} ", "λ> ".cyan().bold())
+        } else {
+            format!("{} ", ".. ".cyan().bold())
+        };
+
+        match editor.readline(&prompt) {
+            Ok(line) => {
+                input.push_str(&line);
+                input.push('\n');
+
+                if !Scanner::is_incomplete(&input) {
+                    return Some(input);
+                }
+            }
+            // Ctrl-C drops what has been typed so far and starts again.
+            Err(ReadlineError::Interrupted) => input.clear(),
+            Err(ReadlineError::Eof) => return None,
+            Err(ReadlineError::Io(e)) => {
+                println!("{}", LoxError::IO(e));
+                return None;
+            }
+            Err(e) => {
+                println!("{} {}", "Error reading input:".red().bold(), e);
+                return None;
+            }
+        }
+    }
+}
+
+/// Splits a meta-command into its name and its argument, which is empty if there's none.
+fn split_command(command: &str) -> (&str, &str) {
+    match command.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (command, ""),
+    }
+}
+
+/// Returns where the identifier the text ends with starts.
+fn word_start(text: &str) -> usize {
+    text.char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+        .last()
+        .map_or(text.len(), |(index, _)| index)
+}
+
+/// The history is kept in `~/.lox_history`, there's none if the home directory isn't known.
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
@@ -0,0 +1,100 @@
+use super::*;
+
+fn repl_with(source: &str) -> Repl {
+    let mut interpreter = Interpreter::new();
+    interpreter.interpret_str(source).unwrap();
+
+    Repl {
+        interpreter,
+        verbose: false,
+    }
+}
+
+#[test]
+fn splits_commands_from_their_argument() {
+    assert_eq!(split_command("env"), ("env", ""));
+    assert_eq!(split_command("type 1 + 2"), ("type", "1 + 2"));
+    assert_eq!(split_command("load\t  file.lox  "), ("load", "file.lox"));
+    assert_eq!(split_command("ast "), ("ast", ""));
+}
+
+#[test]
+fn infers_types_with_the_session_values() {
+    let repl = repl_with("var n = 1; var s = \"a\"; fun id(a) { return a; }");
+
+    assert_eq!(repl.type_of("n + 1").unwrap().to_string(), "number");
+    assert_eq!(repl.type_of("s * n").unwrap().to_string(), "string");
+    assert!(repl.type_of("id").unwrap().to_string().starts_with("fun("));
+    assert!(repl.type_of("var x = 1").is_err());
+}
+
+#[test]
+fn infers_types_without_running_the_expression() {
+    let repl = repl_with("var calls = 0; fun bump() { calls = calls + 1; return calls; }");
+
+    repl.type_of("bump()").unwrap();
+
+    assert!(matches!(
+        repl.interpreter.globals.get("calls"),
+        Some(LoxValue::Number(n)) if n == 0.0
+    ));
+}
+
+fn helper_for(source: &str) -> ReplHelper {
+    let mut helper = ReplHelper::new();
+    ReplHelper::update(&mut helper, &repl_with(source).interpreter);
+
+    helper
+}
+
+fn complete(helper: &ReplHelper, line: &str) -> (usize, Vec<String>) {
+    let history = DefaultHistory::new();
+    helper
+        .complete(line, line.len(), &rustyline::Context::new(&history))
+        .unwrap()
+}
+
+fn hint(helper: &ReplHelper, line: &str) -> Option<String> {
+    let history = DefaultHistory::new();
+    helper.hint(line, line.len(), &rustyline::Context::new(&history))
+}
+
+#[test]
+fn finds_the_start_of_the_last_word() {
+    assert_eq!(word_start("print foo_1"), 6);
+    assert_eq!(word_start("f(ré"), 2);
+    assert_eq!(word_start("1 + "), 4);
+    assert_eq!(word_start(""), 0);
+}
+
+#[test]
+fn completes_meta_commands_at_the_start_of_the_line() {
+    let helper = ReplHelper::new();
+
+    assert_eq!(
+        complete(&helper, ":t"),
+        (0, vec![":tokens".into(), ":time".into(), ":type".into()])
+    );
+    let (_, candidates) = complete(&helper, ":load :t");
+    assert!(candidates.iter().all(|name| !name.starts_with(':')));
+}
+
+#[test]
+fn completes_keywords_and_names() {
+    let helper = helper_for("var value = 1; fun validate(input) { return input; }");
+
+    assert_eq!(
+        complete(&helper, "print va"),
+        (6, vec!["validate".into(), "value".into(), "var".into()])
+    );
+    assert_eq!(complete(&helper, "print "), (6, Vec::new()));
+}
+
+#[test]
+fn hints_at_parameters_after_an_open_call() {
+    let helper = helper_for("var value = 1; fun add(a, b) { return a + b; }");
+
+    assert_eq!(hint(&helper, "print add("), Some("a, b)".to_string()));
+    assert_eq!(hint(&helper, "value("), None);
+    assert_eq!(hint(&helper, "print add"), None);
+}
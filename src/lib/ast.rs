@@ -1,13 +1,23 @@
 use crate::function::FunDecl;
+use crate::interpreter::environment::Slot;
 use crate::lox_value::LoxValue;
 
 use crate::token::{Position, Token};
+use crate::types::Type;
 
+use std::cell::Cell;
 use std::fmt::{self, Debug};
+use std::rc::Rc;
+
+/// Where the resolver found the variable a name refers to, `None` until it's resolved.
+///
+/// It's kept in the node rather than in the interpreter so that programs run one
+/// after another, like the inputs of the REPL, can't mix up their variables.
+pub type ResolvedSlot = Cell<Option<Slot>>;
 
 #[derive(Debug, Clone)]
 pub enum Expr {
-    Identifier(String, Position),
+    Identifier(Rc<str>, Position, ResolvedSlot),
     Value {
         value: LoxValue,
         position: Position,
@@ -31,29 +41,53 @@ pub enum Expr {
         position: Position,
     },
     Assignment {
-        name: String,
+        name: Rc<str>,
         value: Box<Expr>,
         position: Position,
+        slot: ResolvedSlot,
     },
     Call {
         callee: Box<Expr>,
         arguments: Vec<Expr>,
         position: Position,
     },
+    Lambda {
+        fun_declaration: Rc<FunDecl>,
+        position: Position,
+    },
+    /// A string with embedded `${...}` expressions, `parts` alternate between
+    /// the literal segments and the interpolated expressions.
+    Interpolation {
+        parts: Vec<Expr>,
+        position: Position,
+    },
 }
 
 impl Expr {
     pub fn get_position(&self) -> Position {
         use Expr::*;
         match self {
-            Identifier(_, p) => p.to_owned(),
-            Value { position, .. } => position.clone(),
-            Grouping(_, position) => position.clone(),
-            Unary { position, .. } => position.clone(),
-            Binary { position, .. } => position.clone(),
-            Ternary { position, .. } => position.clone(),
-            Assignment { position, .. } => position.clone(),
-            Call { position, .. } => position.clone(),
+            Identifier(_, p, _) => p.to_owned(),
+            Value { position, .. } => *position,
+            Grouping(_, position) => *position,
+            Unary { position, .. } => *position,
+            Binary { position, .. } => *position,
+            Ternary { position, .. } => *position,
+            Assignment { position, .. } => *position,
+            Call { position, .. } => *position,
+            Lambda { position, .. } => *position,
+            Interpolation { position, .. } => *position,
+        }
+    }
+
+    /// Returns the position of the leftmost token of the expression.
+    pub fn start_position(&self) -> Position {
+        use Expr::*;
+        match self {
+            Binary { lhs, .. } => lhs.start_position(),
+            Ternary { condition, .. } => condition.start_position(),
+            Call { callee, .. } => callee.start_position(),
+            expr => expr.get_position(),
         }
     }
 }
@@ -82,22 +116,19 @@ impl fmt::Display for Expr {
                 result_2,
                 position: _,
             } => write!(f, "{} ? {} : {}", condition, result_1, result_2),
-            Identifier(name, _position) => write!(f, "{}", name),
-            Assignment {
-                name,
-                value,
-                position: _,
-            } => write!(f, "{} = {}", name, value),
+            Identifier(name, ..) => write!(f, "{}", name),
+            Assignment { name, value, .. } => write!(f, "{} = {}", name, value),
             Call {
                 callee,
                 arguments,
                 position: _,
             } => {
-                let args = if arguments.len() == 0 {
+                let args = if arguments.is_empty() {
                     String::new()
                 } else {
                     arguments
                         .iter()
+                        .skip(1)
                         .fold(format!("{}", arguments[0]), |acc, arg| {
                             format!("{}, {}", acc, arg)
                         })
@@ -105,6 +136,28 @@ impl fmt::Display for Expr {
 
                 write!(f, "{}({})", callee, args)
             }
+            Lambda {
+                fun_declaration,
+                position: _,
+            } => write!(
+                f,
+                "fun ({}) {}",
+                fun_declaration.params_repr(),
+                fun_declaration.body
+            ),
+            Interpolation { parts, position: _ } => {
+                write!(f, "\"")?;
+                for part in parts {
+                    match part {
+                        Value {
+                            value: LoxValue::String(segment),
+                            ..
+                        } => write!(f, "{}", segment)?,
+                        expr => write!(f, "${{{}}}", expr)?,
+                    }
+                }
+                write!(f, "\"")
+            }
         }
     }
 }
@@ -114,11 +167,14 @@ pub enum Stmt {
     PrintStmt(Expr),
     ExprStmt(Expr),
     Var {
-        name: String,
+        name: Rc<str>,
+        annotation: Option<Type>,
         initializer: Expr,
-        postion: Position,
+        position: Position,
     },
-    Block(Vec<Stmt>),
+    /// A block and the position of its '{', blocks the parser makes up
+    /// use the position of the statement they wrap.
+    Block(Vec<Stmt>, Position),
     IfStmt {
         condition: Expr,
         then_branch: Box<Stmt>,
@@ -130,10 +186,17 @@ pub enum Stmt {
         body: Box<Stmt>,
         position: Position,
     },
+    ForStmt {
+        initializer: Option<Box<Stmt>>,
+        condition: Option<Expr>,
+        increment: Option<Expr>,
+        body: Box<Stmt>,
+        position: Position,
+    },
     BreakStmt(Position),
     ContinueStmt(Position),
     FunStmt {
-        fun_declaration: FunDecl,
+        fun_declaration: Rc<FunDecl>,
         position: Position,
     },
     ReturnStmt {
@@ -142,6 +205,23 @@ pub enum Stmt {
     },
 }
 
+impl Stmt {
+    pub fn get_position(&self) -> Position {
+        use Stmt::*;
+        match self {
+            PrintStmt(expr) | ExprStmt(expr) => expr.start_position(),
+            Block(_, position) => *position,
+            BreakStmt(position) | ContinueStmt(position) => *position,
+            Var { position, .. }
+            | IfStmt { position, .. }
+            | WhileStmt { position, .. }
+            | ForStmt { position, .. }
+            | FunStmt { position, .. }
+            | ReturnStmt { position, .. } => *position,
+        }
+    }
+}
+
 impl fmt::Display for Stmt {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use Stmt::*;
@@ -151,10 +231,14 @@ impl fmt::Display for Stmt {
             ExprStmt(expr) => write!(f, "{};", expr),
             Var {
                 name,
+                annotation,
                 initializer,
-                postion: _,
-            } => write!(f, "var {} = {}", name, initializer),
-            Block(declarations) => {
+                position: _,
+            } => match annotation {
+                Some(annotation) => write!(f, "var {}: {} = {};", name, annotation, initializer),
+                None => write!(f, "var {} = {};", name, initializer),
+            },
+            Block(declarations, _position) => {
                 let repr = declarations.iter().fold(String::from("{\n"), |acc, stmt| {
                     format!("{}    {}\n", acc, stmt)
                 });
@@ -175,21 +259,39 @@ impl fmt::Display for Stmt {
                 body,
                 position: _,
             } => write!(f, "while ({}) {}", condition, body),
+            ForStmt {
+                initializer,
+                condition,
+                increment,
+                body,
+                position: _,
+            } => {
+                match initializer {
+                    Some(initializer) => write!(f, "for ({} ", initializer)?,
+                    None => write!(f, "for (; ")?,
+                }
+                match condition {
+                    Some(condition) => write!(f, "{}; ", condition)?,
+                    None => write!(f, "; ")?,
+                }
+                match increment {
+                    Some(increment) => write!(f, "{}) {}", increment, body),
+                    None => write!(f, ") {}", body),
+                }
+            }
             BreakStmt(_position) => write!(f, "break ;"),
             ContinueStmt(_position) => write!(f, "continue ;"),
             FunStmt {
-                fun_declaration: FunDecl { name, params, body },
+                fun_declaration,
                 position: _,
             } => {
-                let params_repr = if params.len() == 0 {
-                    String::new()
-                } else {
-                    params.iter().fold(format!("{}", params[0]), |acc, arg| {
-                        format!("{}, {}", acc, arg)
-                    })
-                };
-
-                write!(f, "fun {}({}) {:?}", name, params_repr, body)
+                write!(
+                    f,
+                    "fun {}({}) {}",
+                    fun_declaration.name,
+                    fun_declaration.params_repr(),
+                    fun_declaration.body
+                )
             }
             ReturnStmt { expr, position: _ } => {
                 if let Some(expr) = expr {
@@ -201,3 +303,325 @@ impl fmt::Display for Stmt {
         }
     }
 }
+
+/// Walks a syntax tree by reference, every method defaults to visiting the
+/// children of its node, so a pass only overrides the nodes it cares about and
+/// calls the matching `walk_*` function to keep going into their children.
+///
+/// The methods return nothing, so this suits passes that record what they find
+/// as they go, like the resolver, the linter and coverage. Passes that compute a
+/// value for each node keep their own `match`: the interpreter evaluates to
+/// values, the type checker to types, and the formatter and the dumps to text
+/// that depends on where the node is nested.
+pub trait Visitor {
+    fn visit_stmts(&mut self, stmts: &[Stmt]) {
+        walk_stmts(self, stmts)
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt)
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr)
+    }
+
+    /// Visits a function declared with `fun` or a lambda.
+    fn visit_function(&mut self, declaration: &FunDecl) {
+        walk_function(self, declaration)
+    }
+}
+
+pub fn walk_stmts<V: Visitor + ?Sized>(visitor: &mut V, stmts: &[Stmt]) {
+    for stmt in stmts {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    use Stmt::*;
+
+    match stmt {
+        PrintStmt(expr) | ExprStmt(expr) => visitor.visit_expr(expr),
+        Var { initializer, .. } => visitor.visit_expr(initializer),
+        Block(stmts, _) => visitor.visit_stmts(stmts),
+        IfStmt {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(then_branch);
+            if let Some(else_branch) = else_branch {
+                visitor.visit_stmt(else_branch);
+            }
+        }
+        WhileStmt {
+            condition, body, ..
+        } => {
+            visitor.visit_expr(condition);
+            visitor.visit_stmt(body);
+        }
+        // In the order they run in.
+        ForStmt {
+            initializer,
+            condition,
+            increment,
+            body,
+            ..
+        } => {
+            if let Some(initializer) = initializer {
+                visitor.visit_stmt(initializer);
+            }
+            if let Some(condition) = condition {
+                visitor.visit_expr(condition);
+            }
+            visitor.visit_stmt(body);
+            if let Some(increment) = increment {
+                visitor.visit_expr(increment);
+            }
+        }
+        BreakStmt(_) | ContinueStmt(_) => (),
+        FunStmt {
+            fun_declaration, ..
+        } => visitor.visit_function(fun_declaration),
+        ReturnStmt { expr, .. } => {
+            if let Some(expr) = expr {
+                visitor.visit_expr(expr);
+            }
+        }
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) {
+    use Expr::*;
+
+    match expr {
+        Identifier(..) | Value { .. } => (),
+        Grouping(expr, _) | Unary { rhs: expr, .. } | Assignment { value: expr, .. } => {
+            visitor.visit_expr(expr)
+        }
+        Binary { lhs, rhs, .. } => {
+            visitor.visit_expr(lhs);
+            visitor.visit_expr(rhs);
+        }
+        Ternary {
+            condition,
+            result_1,
+            result_2,
+            ..
+        } => {
+            visitor.visit_expr(condition);
+            visitor.visit_expr(result_1);
+            visitor.visit_expr(result_2);
+        }
+        Call {
+            callee, arguments, ..
+        } => {
+            visitor.visit_expr(callee);
+            for argument in arguments {
+                visitor.visit_expr(argument);
+            }
+        }
+        Lambda {
+            fun_declaration, ..
+        } => visitor.visit_function(fun_declaration),
+        Interpolation { parts, .. } => {
+            for part in parts {
+                visitor.visit_expr(part);
+            }
+        }
+    }
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(visitor: &mut V, declaration: &FunDecl) {
+    visitor.visit_stmt(&declaration.body)
+}
+
+/// Walks a syntax tree by mutable reference, like `Visitor`, for passes that
+/// rewrite the nodes in place.
+pub trait VisitorMut {
+    fn visit_stmts_mut(&mut self, stmts: &mut Vec<Stmt>) {
+        walk_stmts_mut(self, stmts)
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt)
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr)
+    }
+
+    fn visit_function_mut(&mut self, declaration: &mut FunDecl) {
+        walk_function_mut(self, declaration)
+    }
+}
+
+pub fn walk_stmts_mut<V: VisitorMut + ?Sized>(visitor: &mut V, stmts: &mut Vec<Stmt>) {
+    for stmt in stmts {
+        visitor.visit_stmt_mut(stmt);
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut Stmt) {
+    use Stmt::*;
+
+    match stmt {
+        PrintStmt(expr) | ExprStmt(expr) => visitor.visit_expr_mut(expr),
+        Var { initializer, .. } => visitor.visit_expr_mut(initializer),
+        Block(stmts, _) => visitor.visit_stmts_mut(stmts),
+        IfStmt {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_stmt_mut(then_branch);
+            if let Some(else_branch) = else_branch {
+                visitor.visit_stmt_mut(else_branch);
+            }
+        }
+        WhileStmt {
+            condition, body, ..
+        } => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_stmt_mut(body);
+        }
+        ForStmt {
+            initializer,
+            condition,
+            increment,
+            body,
+            ..
+        } => {
+            if let Some(initializer) = initializer {
+                visitor.visit_stmt_mut(initializer);
+            }
+            if let Some(condition) = condition {
+                visitor.visit_expr_mut(condition);
+            }
+            visitor.visit_stmt_mut(body);
+            if let Some(increment) = increment {
+                visitor.visit_expr_mut(increment);
+            }
+        }
+        BreakStmt(_) | ContinueStmt(_) => (),
+        FunStmt {
+            fun_declaration, ..
+        } => visitor.visit_function_mut(function_mut(fun_declaration)),
+        ReturnStmt { expr, .. } => {
+            if let Some(expr) = expr {
+                visitor.visit_expr_mut(expr);
+            }
+        }
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+    use Expr::*;
+
+    match expr {
+        Identifier(..) | Value { .. } => (),
+        Grouping(expr, _) | Unary { rhs: expr, .. } | Assignment { value: expr, .. } => {
+            visitor.visit_expr_mut(expr)
+        }
+        Binary { lhs, rhs, .. } => {
+            visitor.visit_expr_mut(lhs);
+            visitor.visit_expr_mut(rhs);
+        }
+        Ternary {
+            condition,
+            result_1,
+            result_2,
+            ..
+        } => {
+            visitor.visit_expr_mut(condition);
+            visitor.visit_expr_mut(result_1);
+            visitor.visit_expr_mut(result_2);
+        }
+        Call {
+            callee, arguments, ..
+        } => {
+            visitor.visit_expr_mut(callee);
+            for argument in arguments {
+                visitor.visit_expr_mut(argument);
+            }
+        }
+        Lambda {
+            fun_declaration, ..
+        } => visitor.visit_function_mut(function_mut(fun_declaration)),
+        Interpolation { parts, .. } => {
+            for part in parts {
+                visitor.visit_expr_mut(part);
+            }
+        }
+    }
+}
+
+pub fn walk_function_mut<V: VisitorMut + ?Sized>(visitor: &mut V, declaration: &mut FunDecl) {
+    visitor.visit_stmt_mut(&mut declaration.body)
+}
+
+/// Gives a pass the declaration to change, functions that already ran keep the one
+/// they were created with.
+fn function_mut(declaration: &mut Rc<FunDecl>) -> &mut FunDecl {
+    Rc::make_mut(declaration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    /// Collects the names read by a program, in the order they're visited.
+    #[derive(Default)]
+    struct Names(Vec<String>);
+
+    impl Visitor for Names {
+        fn visit_expr(&mut self, expr: &Expr) {
+            if let Expr::Identifier(name, ..) = expr {
+                self.0.push(name.to_string());
+            }
+            walk_expr(self, expr)
+        }
+    }
+
+    struct Rename;
+
+    impl VisitorMut for Rename {
+        fn visit_expr_mut(&mut self, expr: &mut Expr) {
+            if let Expr::Identifier(name, ..) | Expr::Assignment { name, .. } = expr {
+                *name = name.to_uppercase().into();
+            }
+            walk_expr_mut(self, expr)
+        }
+    }
+
+    const SRC: &str = "\
+fun f(a) { return a ? b : (x) => c(x); }
+for (var i = d; i < e; i = g) { print \"${h}\"; }
+if (j) k = -l; else while (m and n) o();";
+
+    #[test]
+    fn visits_every_node() {
+        let statements = Parser::parse_str(SRC).unwrap();
+        let mut names = Names::default();
+        names.visit_stmts(&statements);
+
+        assert_eq!(names.0.join(" "), "a b c x d i e h g j l m n o");
+    }
+
+    #[test]
+    fn rewrites_nodes_in_place() {
+        let mut statements = Parser::parse_str(SRC).unwrap();
+        Rename.visit_stmts_mut(&mut statements);
+
+        let mut names = Names::default();
+        names.visit_stmts(&statements);
+        assert_eq!(names.0.join(" "), "A B C X D I E H G J L M N O");
+        assert!(statements[2].to_string().contains("K = "));
+    }
+}
@@ -2,6 +2,7 @@ use crate::interpreter::Interpreter;
 use crate::lox_value::LoxValue;
 
 use crate::interpreter::error::RuntimeResult;
+use crate::token::Position;
 
 /// This trait is shared among all types that can be called
 /// like functions and class instantiations.
@@ -10,6 +11,12 @@ pub trait Callable {
     fn name(&self) -> String;
     /// Returns the number of arguments taken by the callable.
     fn arity(&self) -> usize;
-    /// Takes a `&mut Interpreter` and calls the function on it.
-    fn call(&self, interpreter: &mut Interpreter, args: &[LoxValue]) -> RuntimeResult<LoxValue>;
+    /// Takes a `&mut Interpreter` and calls the function on it, `position` is
+    /// where it's called from, e.g. for the errors of native functions.
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        args: &[LoxValue],
+        position: Position,
+    ) -> RuntimeResult<LoxValue>;
 }
@@ -0,0 +1,48 @@
+use crate::{token::Position, types::Type};
+
+use thiserror::Error;
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum TypeError {
+    #[error("Expected a value of type '{expected}' but found '{found}', {position}.")]
+    Mismatch {
+        expected: Type,
+        found: Type,
+        position: Position,
+    },
+    #[error("Operator '{op}' can't be used with '{lhs}' and '{rhs}', {position}.")]
+    InvalidOperands {
+        op: String,
+        lhs: Type,
+        rhs: Type,
+        position: Position,
+    },
+    #[error("Operator '{op}' can't be used with '{operand}', {position}.")]
+    InvalidOperand {
+        op: String,
+        operand: Type,
+        position: Position,
+    },
+    #[error("A value of type '{found}' can't be called, {position}.")]
+    NotCallable { found: Type, position: Position },
+    #[error("Expected {expected} arguments but found {found}, {position}.")]
+    WrongArity {
+        expected: usize,
+        found: usize,
+        position: Position,
+    },
+}
+
+impl TypeError {
+    pub fn position(&self) -> Position {
+        use TypeError::*;
+
+        match self {
+            Mismatch { position, .. }
+            | InvalidOperands { position, .. }
+            | InvalidOperand { position, .. }
+            | NotCallable { position, .. }
+            | WrongArity { position, .. } => *position,
+        }
+    }
+}
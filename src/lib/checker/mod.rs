@@ -0,0 +1,521 @@
+//! A static type checker for programs with optional annotations.
+//!
+//! Annotated variables, parameters and return types are checked against the
+//! values given to them, unannotated variables take the type of their
+//! initializer and everything else is `any`, which is never reported. Values
+//! of a nil-able type (`number?`) are narrowed to the type without `nil` by
+//! conditions like `if (x)` or `if (x != nil)`.
+//!
+//! The interpreter ignores annotations, checking is only done by `lox_one check`
+//! and the REPL's `:type`.
+
+pub mod error;
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+
+use crate::ast::{Expr, Stmt};
+use crate::error::{LoxError, LoxResult};
+use crate::function::FunDecl;
+use crate::interpreter::error::RuntimeError;
+use crate::interpreter::resolver::Resolver;
+use crate::interpreter::Interpreter;
+use crate::lox_value::LoxValue;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::token::{Position, Token};
+use crate::token_type::TokenType;
+use crate::types::Type;
+
+use error::TypeError;
+
+/// Type checks a whole program, the source must scan, parse and resolve without errors.
+pub fn check_source(source: &str) -> LoxResult<Vec<TypeError>> {
+    let mut scanner = Scanner::new(source);
+    scanner.scan_tokens().map_err(LoxError::Scan)?;
+
+    let statements = Parser::new(scanner.tokens)
+        .program()
+        .map_err(LoxError::Parser)?;
+
+    let mut interpreter = Interpreter::new();
+    Resolver::new(&mut interpreter)
+        .resolve_program(&statements)
+        .map_err(LoxError::Runtime)?;
+
+    let mut checker = Checker::new();
+    checker.program(&statements);
+
+    let mut errors = checker.errors;
+    errors.sort_by_key(|error| error.position());
+
+    Ok(errors)
+}
+
+/// Infers the type of an expression without running it. `globals` are the types of the
+/// variables it can use besides the natives, e.g. those defined in a REPL session.
+pub fn infer_type(source: &str, globals: &[(String, Type)]) -> LoxResult<Type> {
+    let statements = Parser::parse_str(&format!("{source};"))?;
+
+    let expr = match statements.as_slice() {
+        [Stmt::ExprStmt(expr)] => expr,
+        statements => {
+            return Err(LoxError::Runtime(RuntimeError::Generic(
+                "Expected a single expression".to_string(),
+                statements
+                    .first()
+                    .map_or(Position::new(1, 1), |stmt| stmt.get_position()),
+            )))
+        }
+    };
+
+    let mut checker = Checker::new();
+    for (name, ty) in globals {
+        if checker.lookup(name).is_none() {
+            checker.define(name, ty.clone());
+        }
+    }
+
+    Ok(checker.expr(expr))
+}
+
+#[derive(Debug, Clone)]
+struct Binding {
+    /// The annotated or inferred type, what assignments are checked against.
+    declared: Type,
+    /// The type where the variable is used, narrower than `declared` after a nil check.
+    current: Type,
+}
+
+struct Checker {
+    scopes: Vec<HashMap<String, Binding>>,
+    /// The declared return types of the functions being checked, innermost last.
+    returns: Vec<Option<Type>>,
+    errors: Vec<TypeError>,
+}
+
+impl Checker {
+    fn new() -> Checker {
+        let mut globals = HashMap::new();
+        // The natives, both take nothing and return a number.
+        for name in ["clock", "gc"] {
+            let native = Type::Function {
+                params: Vec::new(),
+                ret: Box::new(Type::Number),
+            };
+            globals.insert(
+                name.to_string(),
+                Binding {
+                    declared: native.clone(),
+                    current: native,
+                },
+            );
+        }
+
+        Checker {
+            scopes: vec![globals],
+            returns: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn program(&mut self, statements: &[Stmt]) {
+        // Functions declared at the top level can be called before their declaration.
+        for statement in statements {
+            if let Stmt::FunStmt {
+                fun_declaration, ..
+            } = statement
+            {
+                let ty = fun_declaration.signature();
+                self.define(&fun_declaration.name, ty);
+            }
+        }
+
+        self.stmts(statements);
+    }
+
+    fn stmts(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            self.stmt(statement);
+        }
+    }
+
+    fn stmt(&mut self, statement: &Stmt) {
+        use Stmt::*;
+
+        match statement {
+            PrintStmt(expr) | ExprStmt(expr) => {
+                self.expr(expr);
+            }
+            Var {
+                name,
+                annotation,
+                initializer,
+                position,
+            } => {
+                let found = self.expr(initializer);
+
+                // 'var a: number;' has a nil initializer placed at 'a', it isn't checked.
+                let implicit = matches!(
+                    initializer,
+                    Expr::Value { value: LoxValue::Nil, position: p } if p == position
+                );
+
+                let declared = match annotation {
+                    Some(annotation) => {
+                        if !implicit {
+                            self.expect(annotation, &found, initializer);
+                        }
+                        annotation.clone()
+                    }
+                    None if found == Type::Nil => Type::Any,
+                    None => found,
+                };
+                self.define(name, declared);
+            }
+            Block(statements, _) => {
+                self.scoped(Vec::new(), |checker| checker.stmts(statements));
+            }
+            IfStmt {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.expr(condition);
+
+                let narrowed = self.narrowings(condition, true);
+                self.scoped(narrowed, |checker| checker.stmt(then_branch));
+
+                if let Some(else_branch) = else_branch {
+                    let narrowed = self.narrowings(condition, false);
+                    self.scoped(narrowed, |checker| checker.stmt(else_branch));
+                }
+            }
+            WhileStmt {
+                condition, body, ..
+            } => {
+                self.expr(condition);
+
+                let narrowed = self.narrowings(condition, true);
+                self.scoped(narrowed, |checker| checker.stmt(body));
+            }
+            ForStmt {
+                initializer,
+                condition,
+                increment,
+                body,
+                ..
+            } => self.scoped(Vec::new(), |checker| {
+                if let Some(initializer) = initializer {
+                    checker.stmt(initializer);
+                }
+
+                let narrowed = match condition {
+                    Some(condition) => {
+                        checker.expr(condition);
+                        checker.narrowings(condition, true)
+                    }
+                    None => Vec::new(),
+                };
+                checker.scoped(narrowed, |checker| checker.stmt(body));
+
+                if let Some(increment) = increment {
+                    checker.expr(increment);
+                }
+            }),
+            BreakStmt(_) | ContinueStmt(_) => (),
+            FunStmt {
+                fun_declaration, ..
+            } => {
+                // Declared before the body is checked so the function can call itself.
+                self.define(&fun_declaration.name, fun_declaration.signature());
+                self.function(fun_declaration);
+            }
+            ReturnStmt { expr, position } => {
+                let found = match expr {
+                    Some(expr) => self.expr(expr),
+                    None => Type::Nil,
+                };
+
+                if let Some(Some(expected)) = self.returns.last() {
+                    if !found.is_assignable_to(expected) {
+                        self.errors.push(TypeError::Mismatch {
+                            expected: expected.clone(),
+                            found,
+                            position: expr
+                                .as_ref()
+                                .map_or(*position, |expr| expr.start_position()),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks the body of a function, its parameters are in scope with their annotated types.
+    fn function(&mut self, declaration: &FunDecl) {
+        let params = declaration
+            .params
+            .iter()
+            .map(|param| {
+                let ty = param.annotation.clone().unwrap_or(Type::Any);
+                (param.name.to_string(), ty)
+            })
+            .collect();
+
+        self.returns.push(declaration.return_type.clone());
+        self.scoped(params, |checker| checker.stmt(&declaration.body));
+        self.returns.pop();
+    }
+
+    /// Returns the type of an expression, reporting any errors in it.
+    fn expr(&mut self, expr: &Expr) -> Type {
+        use Expr::*;
+
+        match expr {
+            Identifier(name, ..) => self
+                .lookup(name)
+                .map_or(Type::Any, |binding| binding.current.clone()),
+            Value { value, .. } => match value {
+                LoxValue::Boolean(_) => Type::Bool,
+                LoxValue::Number(_) => Type::Number,
+                LoxValue::String(_) => Type::String,
+                LoxValue::Nil => Type::Nil,
+                LoxValue::Function(_) | LoxValue::Identifier(_) => Type::Any,
+            },
+            Grouping(expr, _) => self.expr(expr),
+            Unary { op, rhs, .. } => {
+                let operand = self.expr(rhs);
+                let expected = match op.token_type {
+                    TokenType::Minus => Type::Number,
+                    _ => Type::Bool,
+                };
+
+                if !operand.is_assignable_to(&expected) {
+                    self.errors.push(TypeError::InvalidOperand {
+                        op: op.lexeme.clone(),
+                        operand,
+                        position: op.position,
+                    });
+                }
+                expected
+            }
+            Binary { lhs, op, rhs, .. } => {
+                // The right side of 'and' only runs if the left is truthy, of 'or' if it isn't.
+                let narrowed = match op.token_type {
+                    TokenType::And => self.narrowings(lhs, true),
+                    TokenType::Or => self.narrowings(lhs, false),
+                    _ => Vec::new(),
+                };
+
+                let lhs = self.expr(lhs);
+                let rhs = self.scoped(narrowed, |checker| checker.expr(rhs));
+                self.binary(op, lhs, rhs)
+            }
+            Ternary {
+                condition,
+                result_1,
+                result_2,
+                ..
+            } => {
+                self.expr(condition);
+
+                let narrowed = self.narrowings(condition, true);
+                let then_type = self.scoped(narrowed, |checker| checker.expr(result_1));
+                let narrowed = self.narrowings(condition, false);
+                let else_type = self.scoped(narrowed, |checker| checker.expr(result_2));
+
+                join(then_type, else_type)
+            }
+            Assignment { name, value, .. } => {
+                let found = self.expr(value);
+
+                if let Some(declared) = self.lookup(name).map(|binding| binding.declared.clone()) {
+                    self.expect(&declared, &found, value);
+                }
+                found
+            }
+            Call {
+                callee,
+                arguments,
+                position,
+            } => {
+                let callee = self.expr(callee);
+                let found: Vec<Type> = arguments.iter().map(|arg| self.expr(arg)).collect();
+
+                match callee {
+                    Type::Any => Type::Any,
+                    Type::Function { params, ret } => {
+                        if params.len() != found.len() {
+                            self.errors.push(TypeError::WrongArity {
+                                expected: params.len(),
+                                found: found.len(),
+                                position: *position,
+                            });
+                        }
+
+                        for ((param, found), arg) in params.iter().zip(found).zip(arguments) {
+                            self.expect(param, &found, arg);
+                        }
+                        *ret
+                    }
+                    found => {
+                        self.errors.push(TypeError::NotCallable {
+                            found,
+                            position: *position,
+                        });
+                        Type::Any
+                    }
+                }
+            }
+            Lambda {
+                fun_declaration, ..
+            } => {
+                self.function(fun_declaration);
+                fun_declaration.signature()
+            }
+            Interpolation { parts, .. } => {
+                for part in parts {
+                    self.expr(part);
+                }
+                Type::String
+            }
+        }
+    }
+
+    /// The type of a binary operation, following what the interpreter accepts.
+    fn binary(&mut self, op: &Token, lhs: Type, rhs: Type) -> Type {
+        use TokenType::*;
+        use Type::{Any, Bool, Number, String};
+
+        let numbers = lhs.is_assignable_to(&Number) && rhs.is_assignable_to(&Number);
+
+        match (&op.token_type, &lhs, &rhs) {
+            (EqualEqual | BangEqual | And | Or, _, _) => Bool,
+            (Greater | GreaterEqual | Less | LessEqual, _, _) if numbers => Bool,
+
+            (Plus, String, _) => String,
+            (Star, String, _) if rhs.is_assignable_to(&Number) => String,
+            (Plus | Star, Any, _) | (Plus | Star, _, Any) => Any,
+            (Plus | Minus | Star | Slash, _, _) if numbers => Number,
+
+            _ => {
+                self.errors.push(TypeError::InvalidOperands {
+                    op: op.lexeme.clone(),
+                    lhs,
+                    rhs,
+                    position: op.position,
+                });
+                Any
+            }
+        }
+    }
+
+    /// Reports a mismatch if a value of type `found` can't be used where `expected` is.
+    fn expect(&mut self, expected: &Type, found: &Type, expr: &Expr) {
+        if !found.is_assignable_to(expected) {
+            self.errors.push(TypeError::Mismatch {
+                expected: expected.clone(),
+                found: found.clone(),
+                position: expr.start_position(),
+            });
+        }
+    }
+
+    /// Returns the variables known not to be nil when `condition` is `truthy`.
+    fn narrowings(&self, condition: &Expr, truthy: bool) -> Vec<(String, Type)> {
+        use Expr::*;
+
+        let non_nil = |name: &str| -> Vec<(String, Type)> {
+            match self.lookup(name) {
+                Some(binding) => vec![(name.to_string(), binding.current.non_nil())],
+                None => Vec::new(),
+            }
+        };
+
+        match condition {
+            Grouping(expr, _) => self.narrowings(expr, truthy),
+            Identifier(name, ..) if truthy => non_nil(name),
+            Unary { op, rhs, .. } if op.token_type == TokenType::Bang => {
+                self.narrowings(rhs, !truthy)
+            }
+            Binary { lhs, op, rhs, .. } => match (&op.token_type, &**lhs, &**rhs) {
+                (TokenType::And, _, _) if truthy => {
+                    let mut narrowed = self.narrowings(lhs, true);
+                    narrowed.extend(self.narrowings(rhs, true));
+                    narrowed
+                }
+                (TokenType::BangEqual | TokenType::EqualEqual, Identifier(name, ..), other)
+                | (TokenType::BangEqual | TokenType::EqualEqual, other, Identifier(name, ..))
+                    if matches!(
+                        other,
+                        Value {
+                            value: LoxValue::Nil,
+                            ..
+                        }
+                    ) =>
+                {
+                    let is_not_nil = op.token_type == TokenType::BangEqual;
+                    if is_not_nil == truthy {
+                        non_nil(name)
+                    } else {
+                        Vec::new()
+                    }
+                }
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    /// Runs `check` in a new scope where the given variables have the given types.
+    fn scoped<T>(
+        &mut self,
+        bindings: Vec<(String, Type)>,
+        check: impl FnOnce(&mut Checker) -> T,
+    ) -> T {
+        let mut scope = HashMap::new();
+        for (name, current) in bindings {
+            // Narrowed variables keep the type they were declared with.
+            let declared = self
+                .lookup(&name)
+                .map_or(current.clone(), |binding| binding.declared.clone());
+            scope.insert(name, Binding { declared, current });
+        }
+
+        self.scopes.push(scope);
+        let result = check(self);
+        self.scopes.pop();
+
+        result
+    }
+
+    fn define(&mut self, name: &str, declared: Type) {
+        let binding = Binding {
+            current: declared.clone(),
+            declared,
+        };
+
+        self.scopes
+            .last_mut()
+            .expect("There's always a global scope")
+            .insert(name.to_string(), binding);
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Binding> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+}
+
+/// The type of a value that is either of type `a` or of type `b`.
+fn join(a: Type, b: Type) -> Type {
+    match (a, b) {
+        (a, b) if a == b => a,
+        (Type::Nil, ty) | (ty, Type::Nil) => ty.nilable(),
+        (Type::Nilable(a), b) | (b, Type::Nilable(a)) if *a == b => Type::Nilable(a),
+        _ => Type::Any,
+    }
+}
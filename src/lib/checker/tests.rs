@@ -0,0 +1,127 @@
+use super::*;
+
+fn check(src: &str) -> Vec<String> {
+    check_source(src)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .iter()
+        .map(|error| error.to_string())
+        .collect()
+}
+
+#[test]
+fn accepts_annotated_programs() {
+    let src = crate::utils::read_file("examples/types.lox");
+    assert_eq!(check(&src), Vec::<String>::new());
+}
+
+#[test]
+fn reports_annotation_mismatches() {
+    assert_eq!(
+        check("var a: number = \"one\";\nvar b: string? = nil;\nvar c: bool;\nc = 1;"),
+        [
+            "Expected a value of type 'number' but found 'string', line 1, column 17.",
+            "Expected a value of type 'bool' but found 'number', line 4, column 5."
+        ]
+    );
+}
+
+#[test]
+fn infers_types_of_unannotated_variables() {
+    assert_eq!(
+        check("var a = 1;\nvar b = \"b\";\nprint b - a;\nvar c;\nprint c - a;\na = b;"),
+        [
+            "Operator '-' can't be used with 'string' and 'number', line 3, column 9.",
+            "Expected a value of type 'number' but found 'string', line 6, column 5."
+        ]
+    );
+}
+
+#[test]
+fn follows_the_runtime_operator_rules() {
+    let src = "print \"a\" + 1;\nprint \"a\" * 3;\nprint 1 + \"a\";\nprint -\"a\";\nprint !1;\nprint 1 < 2 == \"a\";";
+
+    assert_eq!(
+        check(src),
+        [
+            "Operator '+' can't be used with 'number' and 'string', line 3, column 9.",
+            "Operator '-' can't be used with 'string', line 4, column 7.",
+            "Operator '!' can't be used with 'number', line 5, column 7."
+        ]
+    );
+}
+
+#[test]
+fn checks_calls() {
+    let src = "fun add(a: number, b: number): number { return a + b; }\nadd(1);\nadd(1, \"2\");\nvar n = 1;\nn();\nclock(1);\nadd(1, 2)();";
+
+    assert_eq!(
+        check(src),
+        [
+            "Expected 2 arguments but found 1, line 2, column 5.",
+            "Expected a value of type 'number' but found 'string', line 3, column 8.",
+            "A value of type 'number' can't be called, line 5, column 3.",
+            "Expected 0 arguments but found 1, line 6, column 7.",
+            "A value of type 'number' can't be called, line 7, column 11."
+        ]
+    );
+}
+
+#[test]
+fn checks_return_types() {
+    let src =
+        "fun f(): number { return \"f\"; }\nfun g(): number? { return; }\nvar h = (): string => 1;";
+
+    assert_eq!(
+        check(src),
+        [
+            "Expected a value of type 'number' but found 'string', line 1, column 26.",
+            "Expected a value of type 'string' but found 'number', line 3, column 23."
+        ]
+    );
+}
+
+#[test]
+fn narrows_nilable_types() {
+    let src = "var a: number? = nil;\nprint a + 1;\nif (a != nil) print a + 1;\nif (a) print a + 1; else print a + 1;\nif (a == nil) print a; else print a + 1;\nvar f: (fun(): number)? = nil;\nf();\nprint f != nil and f() > 1;";
+
+    assert_eq!(
+        check(src),
+        [
+            "Operator '+' can't be used with 'number?' and 'number', line 2, column 9.",
+            "Operator '+' can't be used with 'number?' and 'number', line 4, column 34.",
+            "A value of type '(fun(): number)?' can't be called, line 7, column 3."
+        ]
+    );
+}
+
+#[test]
+fn checks_function_types() {
+    let src = "var f: fun(number): number = (x: string) => 1;\nvar g: fun(number?): any = (x) => x;\nvar h: fun(number): number = fun (x: number?): number { return 1; };";
+
+    assert_eq!(
+        check(src),
+        ["Expected a value of type 'fun(number): number' but found 'fun(string): any', line 1, column 30."]
+    );
+}
+
+#[test]
+fn infers_types_of_expressions() {
+    let globals = [
+        ("n".to_string(), Type::Number),
+        ("clock".to_string(), Type::Any),
+    ];
+    let infer = |src| {
+        infer_type(src, &globals)
+            .unwrap_or_else(|e| panic!("{e}"))
+            .to_string()
+    };
+
+    assert_eq!(infer("n + 1"), "number");
+    assert_eq!(infer("\"${n}\""), "string");
+    // The natives keep their own types, and calls aren't run.
+    assert_eq!(infer("clock()"), "number");
+    assert_eq!(infer("(x: number): string => \"\""), "fun(number): string");
+    assert_eq!(infer("undefined_name"), "any");
+
+    assert!(infer_type("print 1", &globals).is_err());
+}
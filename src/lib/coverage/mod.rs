@@ -0,0 +1,191 @@
+//! Line and branch coverage of a program, written as LCOV.
+//!
+//! `Coverage` starts out knowing every line with a statement and every branch
+//! point of a program, the `Recorder` hook then counts what runs. A branch
+//! point is an `if`, a ternary or an `and` / `or`, its branch 0 is taken when
+//! the condition or left side is truthy and branch 1 when it's falsy.
+
+#[cfg(test)]
+mod tests;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::rc::Rc;
+
+use crate::ast::{walk_expr, walk_stmt, Expr, Stmt, Visitor};
+use crate::error::LoxResult;
+use crate::interpreter::error::RuntimeResult;
+use crate::interpreter::hook::Hook;
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::token::Position;
+use crate::token_type::TokenType;
+
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    /// How many statements ran on each line with statements.
+    lines: BTreeMap<usize, u64>,
+    /// How often each branch point's condition was truthy and falsy.
+    branches: BTreeMap<Position, [u64; 2]>,
+}
+
+impl Coverage {
+    /// Creates a coverage of the given statements with nothing run yet.
+    pub fn new(statements: &[Stmt]) -> Coverage {
+        let mut coverage = Coverage::default();
+        coverage.visit_stmts(statements);
+
+        coverage
+    }
+
+    /// Parses a program and creates a coverage of it.
+    pub fn from_source(source: &str) -> LoxResult<Coverage> {
+        Ok(Coverage::new(&Parser::parse_str(source)?))
+    }
+
+    /// Returns how many lines with statements ran and how many there are.
+    pub fn lines_hit(&self) -> (usize, usize) {
+        let hit = self.lines.values().filter(|hits| **hits > 0).count();
+        (hit, self.lines.len())
+    }
+
+    /// Returns how many branches were taken and how many there are.
+    pub fn branches_hit(&self) -> (usize, usize) {
+        let hit = self
+            .branches
+            .values()
+            .flatten()
+            .filter(|taken| **taken > 0)
+            .count();
+        (hit, self.branches.len() * 2)
+    }
+
+    /// Returns a line summarising the coverage of the program at `path`.
+    pub fn summary(&self, path: &str) -> String {
+        let (lines_hit, lines) = self.lines_hit();
+        let (branches_hit, branches) = self.branches_hit();
+
+        format!(
+            "{path}: lines {lines_hit}/{lines} ({}), branches {branches_hit}/{branches} ({})",
+            percentage(lines_hit, lines),
+            percentage(branches_hit, branches)
+        )
+    }
+
+    /// Returns the coverage as an LCOV record for the program at `path`.
+    pub fn lcov(&self, path: &str) -> String {
+        let mut lcov = format!("TN:\nSF:{path}\n");
+
+        // Blocks number the branch points of a line from 0.
+        let mut block = 0;
+        let mut last_line = 0;
+        for (position, taken) in &self.branches {
+            block = if position.line == last_line {
+                block + 1
+            } else {
+                0
+            };
+            last_line = position.line;
+
+            for (branch, count) in taken.iter().enumerate() {
+                let count = match taken {
+                    [0, 0] => "-".to_string(),
+                    _ => count.to_string(),
+                };
+                writeln!(lcov, "BRDA:{},{block},{branch},{count}", position.line)
+                    .expect("Writing to a String can't fail");
+            }
+        }
+        let (branches_hit, branches) = self.branches_hit();
+        writeln!(lcov, "BRF:{branches}\nBRH:{branches_hit}")
+            .expect("Writing to a String can't fail");
+
+        for (line, hits) in &self.lines {
+            writeln!(lcov, "DA:{line},{hits}").expect("Writing to a String can't fail");
+        }
+        let (lines_hit, lines) = self.lines_hit();
+        writeln!(lcov, "LF:{lines}\nLH:{lines_hit}\nend_of_record")
+            .expect("Writing to a String can't fail");
+
+        lcov
+    }
+}
+
+fn percentage(hit: usize, total: usize) -> String {
+    if total == 0 {
+        return "100.0%".to_string();
+    }
+
+    format!("{:.1}%", hit as f64 * 100.0 / total as f64)
+}
+
+/// Finds the lines and branch points of a program.
+impl Visitor for Coverage {
+    fn visit_stmt(&mut self, statement: &Stmt) {
+        // Blocks aren't counted, the statements in them are.
+        if !matches!(statement, Stmt::Block(..)) {
+            self.lines.insert(statement.get_position().line, 0);
+        }
+        if let Stmt::IfStmt { position, .. } = statement {
+            self.branches.insert(*position, [0, 0]);
+        }
+
+        walk_stmt(self, statement)
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Binary { op, position, .. }
+                if matches!(op.token_type, TokenType::And | TokenType::Or) =>
+            {
+                self.branches.insert(*position, [0, 0]);
+            }
+            Expr::Ternary { position, .. } => {
+                self.branches.insert(*position, [0, 0]);
+            }
+            _ => (),
+        }
+
+        walk_expr(self, expr)
+    }
+}
+
+/// Counts the lines and branches of a program that run into its `Coverage`.
+pub struct Recorder {
+    coverage: Rc<RefCell<Coverage>>,
+}
+
+impl Recorder {
+    pub fn new(coverage: Coverage) -> Recorder {
+        Recorder {
+            coverage: Rc::new(RefCell::new(coverage)),
+        }
+    }
+
+    /// Returns the coverage being recorded.
+    pub fn coverage(&self) -> Rc<RefCell<Coverage>> {
+        self.coverage.clone()
+    }
+}
+
+impl Hook for Recorder {
+    fn statement(&mut self, _interpreter: &mut Interpreter, statement: &Stmt) -> RuntimeResult<()> {
+        let line = statement.get_position().line;
+
+        // Only the lines the program was created with are counted.
+        if !matches!(statement, Stmt::Block(..)) {
+            if let Some(hits) = self.coverage.borrow_mut().lines.get_mut(&line) {
+                *hits += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn branch(&mut self, _interpreter: &mut Interpreter, position: Position, condition: bool) {
+        if let Some(taken) = self.coverage.borrow_mut().branches.get_mut(&position) {
+            taken[usize::from(!condition)] += 1;
+        }
+    }
+}
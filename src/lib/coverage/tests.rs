@@ -0,0 +1,88 @@
+use super::*;
+use crate::utils::run_with_hook;
+
+const SRC: &str = "\
+fun sign(n) {
+    if (n < 0) {
+        return -1;
+    }
+    return n == 0 ? 0 : 1;
+}
+fun unused() {
+    return 1;
+}
+var x = sign(5);
+var ok = x > 0 or sign(-1) > 0;
+print ok and x;";
+
+fn record(source: &str) -> Coverage {
+    let recorder = Recorder::new(Coverage::from_source(source).unwrap());
+    let coverage = recorder.coverage();
+
+    run_with_hook(source, Box::new(recorder));
+
+    let coverage = coverage.borrow().clone();
+    coverage
+}
+
+#[test]
+fn counts_lines_and_branches() {
+    let coverage = record(SRC);
+
+    assert_eq!(
+        coverage
+            .lines
+            .iter()
+            .map(|(line, hits)| (*line, *hits))
+            .collect::<Vec<_>>(),
+        [
+            (1, 1),
+            (2, 1),
+            (3, 0),
+            (5, 1),
+            (7, 1),
+            (8, 0),
+            (10, 1),
+            (11, 1),
+            (12, 1)
+        ]
+    );
+    assert_eq!(
+        coverage.branches.values().copied().collect::<Vec<_>>(),
+        [[0, 1], [0, 1], [1, 0], [1, 0]]
+    );
+    assert_eq!(coverage.lines_hit(), (7, 9));
+    assert_eq!(coverage.branches_hit(), (4, 8));
+    assert_eq!(
+        coverage.summary("sign.lox"),
+        "sign.lox: lines 7/9 (77.8%), branches 4/8 (50.0%)"
+    );
+}
+
+#[test]
+fn writes_lcov() {
+    let coverage =
+        record("var a = true ? 1 : 2;\nif (a > 5) print a;\nfun f() { return a or false; }");
+
+    assert_eq!(
+        coverage.lcov("a.lox"),
+        "\
+TN:
+SF:a.lox
+BRDA:1,0,0,1
+BRDA:1,0,1,0
+BRDA:2,0,0,0
+BRDA:2,0,1,1
+BRDA:3,0,0,-
+BRDA:3,0,1,-
+BRF:6
+BRH:2
+DA:1,1
+DA:2,1
+DA:3,1
+LF:3
+LH:3
+end_of_record
+"
+    );
+}
@@ -0,0 +1,439 @@
+//! A debug adapter for Lox, speaking the Debug Adapter Protocol over stdio.
+//!
+//! The program runs on the adapter's own thread with a `Debugger` attached,
+//! while it's paused the `Adapter` frontend keeps answering requests until
+//! one of them resumes it. There's a single thread with the id 1.
+
+#[cfg(test)]
+mod tests;
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+use serde_json::{json, Value};
+
+use crate::debugger::{Debugger, Frontend, PauseReason, Resume, Session};
+use crate::error::LoxError;
+use crate::interpreter::error::RuntimeError;
+use crate::interpreter::Interpreter;
+use crate::lsp::transport::{read_message, write_message};
+
+const THREAD_ID: i64 = 1;
+/// The variables reference of the globals, the locals of frame `n` use `n + LOCALS`.
+const GLOBALS: i64 = 1;
+const LOCALS: i64 = 2;
+
+/// Runs the debug adapter on stdin and stdout until the client disconnects.
+pub fn run_stdio() -> io::Result<()> {
+    run(io::stdin().lock(), io::stdout())
+}
+
+/// Handles messages from `reader` until a 'disconnect' request or the end of the input.
+pub fn run<R, W>(reader: R, writer: W) -> io::Result<()>
+where
+    R: BufRead + 'static,
+    W: Write + 'static,
+{
+    let client = Rc::new(RefCell::new(Client {
+        reader,
+        writer,
+        seq: 1,
+        disconnected: false,
+    }));
+
+    let Some(launch) = configure(&client)? else {
+        return Ok(());
+    };
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(Box::new(OutputEvents::new(client.clone(), "stdout")));
+    let adapter = Adapter {
+        client: client.clone(),
+        path: launch.path.clone(),
+        stop_on_entry: launch.stop_on_entry,
+    };
+    interpreter.set_hook(Box::new(Debugger::new(adapter, launch.breakpoints)));
+
+    let exit_code = match interpreter.interpret_str(&launch.source) {
+        Ok(_) => 0,
+        Err(LoxError::Runtime(RuntimeError::Stopped(_))) => 0,
+        Err(e) => {
+            let mut output = OutputEvents::new(client.clone(), "stderr");
+            writeln!(output, "{e}")?;
+            1
+        }
+    };
+    // Dropping the interpreter sends the last line if it was printed without a line break.
+    drop(interpreter);
+
+    let mut client = client.borrow_mut();
+    if client.disconnected {
+        return Ok(());
+    }
+    client.event("exited", json!({ "exitCode": exit_code }))?;
+    client.event("terminated", json!({}))?;
+
+    // The client can still ask about threads before it disconnects.
+    while let Some(request) = client.read()? {
+        let command = request["command"].as_str().unwrap_or_default();
+        match command {
+            "disconnect" | "terminate" => return client.respond(&request, json!({})),
+            "threads" => client.respond(&request, threads())?,
+            _ => client.fail(&request, "The program has finished")?,
+        }
+    }
+
+    Ok(())
+}
+
+/// What the client asked to run.
+struct Launch {
+    path: String,
+    source: String,
+    stop_on_entry: bool,
+    breakpoints: BTreeSet<usize>,
+}
+
+/// Answers requests until 'configurationDone', `None` if the client disconnects first.
+fn configure<R: BufRead, W: Write>(client: &RefCell<Client<R, W>>) -> io::Result<Option<Launch>> {
+    let mut client = client.borrow_mut();
+    let mut launch: Option<Launch> = None;
+    let mut breakpoints = BTreeSet::new();
+
+    while let Some(request) = client.read()? {
+        let arguments = &request["arguments"];
+        let command = request["command"].as_str().unwrap_or_default();
+
+        match command {
+            "initialize" => {
+                client.respond(&request, capabilities())?;
+                client.event("initialized", json!({}))?;
+            }
+            "launch" => {
+                let path = arguments["program"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                match std::fs::read_to_string(&path) {
+                    Ok(source) => {
+                        launch = Some(Launch {
+                            path,
+                            source,
+                            stop_on_entry: arguments["stopOnEntry"].as_bool().unwrap_or(false),
+                            breakpoints: BTreeSet::new(),
+                        });
+                        client.respond(&request, json!({}))?;
+                    }
+                    Err(e) => client.fail(&request, &format!("Couldn't read '{path}': {e}"))?,
+                }
+            }
+            "setBreakpoints" => {
+                let body = set_breakpoints(&mut breakpoints, arguments);
+                client.respond(&request, body)?;
+            }
+            "setExceptionBreakpoints" => client.respond(&request, json!({}))?,
+            "threads" => client.respond(&request, threads())?,
+            "configurationDone" => {
+                client.respond(&request, json!({}))?;
+
+                if let Some(mut launch) = launch.take() {
+                    launch.breakpoints = breakpoints;
+                    return Ok(Some(launch));
+                }
+            }
+            "disconnect" | "terminate" => {
+                client.respond(&request, json!({}))?;
+                return Ok(None);
+            }
+            _ => client.fail(&request, &format!("Unknown command '{command}'"))?,
+        }
+    }
+
+    Ok(None)
+}
+
+/// The connection to the client.
+struct Client<R, W> {
+    reader: R,
+    writer: W,
+    /// The sequence number of the next message sent.
+    seq: i64,
+    /// Set once the client disconnects, the program is stopped at the next pause.
+    disconnected: bool,
+}
+
+impl<R: BufRead, W: Write> Client<R, W> {
+    /// Reads the next request, answering the ones that can't be parsed with a failed response.
+    fn read(&mut self) -> io::Result<Option<Value>> {
+        loop {
+            match read_message(&mut self.reader)? {
+                Some(Ok(request)) => return Ok(Some(request)),
+                // There's no sequence number or command to answer, 0 and "" stand in for them.
+                Some(Err(e)) => self.fail(&json!({ "seq": 0, "command": "" }), &e.to_string())?,
+                None => return Ok(None),
+            }
+        }
+    }
+
+    fn send(&mut self, mut message: Value) -> io::Result<()> {
+        message["seq"] = json!(self.seq);
+        self.seq += 1;
+
+        write_message(&mut self.writer, &message)
+    }
+
+    fn respond(&mut self, request: &Value, body: Value) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": true,
+            "command": request["command"],
+            "body": body,
+        }))
+    }
+
+    fn fail(&mut self, request: &Value, message: &str) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "success": false,
+            "command": request["command"],
+            "message": message,
+        }))
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> io::Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+}
+
+/// Sends what the program prints to the client as 'output' events, a line at a time.
+struct OutputEvents<R: BufRead, W: Write> {
+    client: Rc<RefCell<Client<R, W>>>,
+    category: &'static str,
+    line: Vec<u8>,
+}
+
+impl<R: BufRead, W: Write> OutputEvents<R, W> {
+    fn new(client: Rc<RefCell<Client<R, W>>>, category: &'static str) -> OutputEvents<R, W> {
+        OutputEvents {
+            client,
+            category,
+            line: Vec::new(),
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> Write for OutputEvents<R, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        if buf.contains(&b'\n') {
+            self.flush()?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.line.is_empty() {
+            return Ok(());
+        }
+
+        let output = String::from_utf8_lossy(&self.line).to_string();
+        self.line.clear();
+        self.client.borrow_mut().event(
+            "output",
+            json!({ "category": self.category, "output": output }),
+        )
+    }
+}
+
+impl<R: BufRead, W: Write> Drop for OutputEvents<R, W> {
+    fn drop(&mut self) {
+        self.flush().ok();
+    }
+}
+
+/// The frontend answering requests while the program is paused.
+struct Adapter<R, W> {
+    client: Rc<RefCell<Client<R, W>>>,
+    path: String,
+    stop_on_entry: bool,
+}
+
+impl<R: BufRead, W: Write> Adapter<R, W> {
+    fn pause(&mut self, session: &mut Session<'_>) -> io::Result<Resume> {
+        let reason = match session.reason {
+            PauseReason::Entry => "entry",
+            PauseReason::Breakpoint => "breakpoint",
+            PauseReason::Step => "step",
+        };
+        self.client.borrow_mut().event(
+            "stopped",
+            json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }),
+        )?;
+
+        // The client isn't borrowed while requests are handled, evaluating can print.
+        loop {
+            let Some(request) = self.client.borrow_mut().read()? else {
+                // The client went away without disconnecting.
+                self.client.borrow_mut().disconnected = true;
+                return Ok(Resume::Stop);
+            };
+            let arguments = &request["arguments"];
+            let command = request["command"].as_str().unwrap_or_default();
+
+            let resume = match command {
+                "continue" => Resume::Continue,
+                "next" => Resume::StepOver,
+                "stepIn" => Resume::StepIn,
+                "stepOut" => Resume::StepOut,
+                "disconnect" | "terminate" => Resume::Stop,
+                _ => {
+                    let response = self.handle(session, command, arguments);
+                    let mut client = self.client.borrow_mut();
+                    match response {
+                        Ok(body) => client.respond(&request, body)?,
+                        Err(message) => client.fail(&request, &message)?,
+                    }
+                    continue;
+                }
+            };
+
+            let mut client = self.client.borrow_mut();
+            if resume == Resume::Stop {
+                client.disconnected = true;
+                client.respond(&request, json!({}))?;
+            } else {
+                client.respond(&request, json!({ "allThreadsContinued": true }))?;
+            }
+            return Ok(resume);
+        }
+    }
+
+    /// Answers a request that doesn't resume the program.
+    fn handle(
+        &self,
+        session: &mut Session<'_>,
+        command: &str,
+        arguments: &Value,
+    ) -> Result<Value, String> {
+        match command {
+            "threads" => Ok(threads()),
+            "stackTrace" => Ok(self.stack_trace(session)),
+            "scopes" => Ok(scopes(arguments["frameId"].as_i64().unwrap_or(0))),
+            "variables" => {
+                let reference = arguments["variablesReference"].as_i64().unwrap_or(0);
+                Ok(variables(session, reference))
+            }
+            "evaluate" => {
+                let expression = arguments["expression"].as_str().unwrap_or_default();
+                let frame = arguments["frameId"].as_u64().unwrap_or(0) as usize;
+
+                session
+                    .evaluate(expression, frame)
+                    .map(|value| json!({ "result": value.to_string(), "variablesReference": 0 }))
+                    .map_err(|e| e.to_string())
+            }
+            "setBreakpoints" => Ok(set_breakpoints(session.breakpoints, arguments)),
+            _ => Err(format!("Unknown command '{command}'")),
+        }
+    }
+
+    fn stack_trace(&self, session: &Session<'_>) -> Value {
+        let frames: Vec<Value> = session
+            .frames()
+            .enumerate()
+            .map(|(id, frame)| {
+                json!({
+                    "id": id,
+                    "name": frame.name,
+                    "line": frame.position.line,
+                    "column": frame.position.column,
+                    "source": { "path": self.path },
+                })
+            })
+            .collect();
+
+        json!({ "totalFrames": frames.len(), "stackFrames": frames })
+    }
+}
+
+impl<R: BufRead, W: Write> Frontend for Adapter<R, W> {
+    fn paused(&mut self, session: &mut Session<'_>) -> Resume {
+        if self.client.borrow().disconnected {
+            return Resume::Stop;
+        }
+        if session.reason == PauseReason::Entry && !self.stop_on_entry {
+            return Resume::Continue;
+        }
+
+        self.pause(session).unwrap_or(Resume::Stop)
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "supportsConfigurationDoneRequest": true,
+        "supportsEvaluateForHovers": true,
+        "supportsTerminateRequest": true,
+    })
+}
+
+fn threads() -> Value {
+    json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })
+}
+
+/// Replaces the breakpoints with the lines of a 'setBreakpoints' request.
+fn set_breakpoints(breakpoints: &mut BTreeSet<usize>, arguments: &Value) -> Value {
+    let lines: Vec<usize> = arguments["breakpoints"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|breakpoint| breakpoint["line"].as_u64())
+        .map(|line| line as usize)
+        .collect();
+
+    breakpoints.clear();
+    breakpoints.extend(lines.iter().copied());
+
+    let verified: Vec<Value> = lines
+        .iter()
+        .map(|line| json!({ "verified": true, "line": line }))
+        .collect();
+
+    json!({ "breakpoints": verified })
+}
+
+fn scopes(frame: i64) -> Value {
+    json!({
+        "scopes": [
+            { "name": "Locals", "variablesReference": frame + LOCALS, "expensive": false },
+            { "name": "Globals", "variablesReference": GLOBALS, "expensive": false },
+        ]
+    })
+}
+
+fn variables(session: &Session<'_>, reference: i64) -> Value {
+    let bindings = if reference == GLOBALS {
+        session.interpreter.globals.bindings()
+    } else {
+        session.locals((reference - LOCALS).max(0) as usize)
+    };
+
+    let variables: Vec<Value> = bindings
+        .into_iter()
+        .map(|(name, value)| {
+            json!({
+                "name": name,
+                "value": value.to_string(),
+                "type": value.type_of().to_string(),
+                "variablesReference": 0,
+            })
+        })
+        .collect();
+
+    json!({ "variables": variables })
+}
@@ -0,0 +1,218 @@
+use std::io::Cursor;
+
+use serde_json::{json, Value};
+
+use super::run;
+use crate::lsp::transport::{read_message, write_message};
+use crate::utils::Output;
+
+const SRC: &str = "\
+fun add(a, b) {
+    var sum = a + b;
+    return sum;
+}
+var x = 1;
+print add(x, 2);
+print x;";
+
+fn request(seq: i64, command: &str, arguments: Value) -> Value {
+    json!({ "seq": seq, "type": "request", "command": command, "arguments": arguments })
+}
+
+/// Launches `SRC` after the given setup requests, then plays the requests sent while it runs.
+fn run_session(name: &str, setup: &[Value], requests: &[Value]) -> Vec<Value> {
+    let path = std::env::temp_dir().join(format!("lox_one_dap_{name}.lox"));
+    std::fs::write(&path, SRC).unwrap();
+
+    let mut messages = vec![
+        request(1, "initialize", json!({ "adapterID": "lox" })),
+        request(
+            2,
+            "launch",
+            json!({ "program": path, "stopOnEntry": false }),
+        ),
+    ];
+    messages.extend_from_slice(setup);
+    messages.push(request(10, "configurationDone", json!({})));
+    messages.extend_from_slice(requests);
+
+    let mut input = Vec::new();
+    for message in &messages {
+        write_message(&mut input, message).unwrap();
+    }
+
+    let sent = run_input(input);
+    std::fs::remove_file(&path).ok();
+
+    sent
+}
+
+/// Runs the adapter on raw `input`, returning everything it sent.
+fn run_input(input: Vec<u8>) -> Vec<Value> {
+    let output = Output::default();
+    run(Cursor::new(input), output.clone()).unwrap();
+
+    let mut output = Cursor::new(output.bytes());
+    let mut sent = Vec::new();
+    while let Some(message) = read_message(&mut output).unwrap() {
+        sent.push(message.unwrap());
+    }
+
+    sent
+}
+
+fn response(sent: &[Value], seq: i64) -> &Value {
+    sent.iter()
+        .find(|message| message["type"] == "response" && message["request_seq"] == seq)
+        .unwrap_or_else(|| panic!("No response to request {seq}"))
+}
+
+fn events<'a>(sent: &'a [Value], event: &str) -> Vec<&'a Value> {
+    sent.iter()
+        .filter(|message| message["type"] == "event" && message["event"] == event)
+        .collect()
+}
+
+#[test]
+fn runs_without_breakpoints() {
+    let sent = run_session("plain", &[], &[request(11, "disconnect", json!({}))]);
+
+    assert_eq!(
+        response(&sent, 1)["body"]["supportsConfigurationDoneRequest"],
+        true
+    );
+    assert_eq!(events(&sent, "initialized").len(), 1);
+
+    let output: Vec<&Value> = events(&sent, "output")
+        .into_iter()
+        .map(|event| &event["body"]["output"])
+        .collect();
+    assert_eq!(output, ["3\n", "1\n"]);
+    assert_eq!(events(&sent, "exited")[0]["body"]["exitCode"], 0);
+    assert_eq!(events(&sent, "terminated").len(), 1);
+    assert_eq!(response(&sent, 11)["success"], true);
+
+    let seqs: Vec<i64> = sent
+        .iter()
+        .map(|message| message["seq"].as_i64().unwrap())
+        .collect();
+    assert_eq!(seqs, (1..=seqs.len() as i64).collect::<Vec<i64>>());
+}
+
+#[test]
+fn inspects_the_program_at_a_breakpoint() {
+    let setup = [request(
+        3,
+        "setBreakpoints",
+        json!({ "source": { "path": "test.lox" }, "breakpoints": [{ "line": 3 }] }),
+    )];
+    let requests = [
+        request(11, "threads", json!({})),
+        request(12, "stackTrace", json!({ "threadId": 1 })),
+        request(13, "scopes", json!({ "frameId": 0 })),
+        request(14, "variables", json!({ "variablesReference": 2 })),
+        request(
+            15,
+            "evaluate",
+            json!({ "expression": "sum * 10", "frameId": 0 }),
+        ),
+        request(
+            16,
+            "evaluate",
+            json!({ "expression": "nope", "frameId": 1 }),
+        ),
+        request(17, "continue", json!({ "threadId": 1 })),
+        request(18, "disconnect", json!({})),
+    ];
+    let sent = run_session("breakpoint", &setup, &requests);
+
+    assert_eq!(
+        response(&sent, 3)["body"]["breakpoints"],
+        json!([{ "verified": true, "line": 3 }])
+    );
+
+    let stopped = events(&sent, "stopped");
+    assert_eq!(stopped.len(), 1);
+    assert_eq!(stopped[0]["body"]["reason"], "breakpoint");
+
+    assert_eq!(response(&sent, 11)["body"]["threads"][0]["id"], 1);
+
+    let frames = &response(&sent, 12)["body"]["stackFrames"];
+    assert_eq!(frames[0]["name"], "add");
+    assert_eq!(frames[0]["line"], 3);
+    assert_eq!(frames[1]["name"], "<script>");
+    assert_eq!(frames[1]["line"], 6);
+
+    assert_eq!(
+        response(&sent, 13)["body"]["scopes"][0]["variablesReference"],
+        2
+    );
+
+    let variables: Vec<(&Value, &Value)> = response(&sent, 14)["body"]["variables"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|variable| (&variable["name"], &variable["value"]))
+        .collect();
+    assert_eq!(
+        variables,
+        [
+            (&json!("sum"), &json!("3")),
+            (&json!("a"), &json!("1")),
+            (&json!("b"), &json!("2"))
+        ]
+    );
+
+    assert_eq!(response(&sent, 15)["body"]["result"], "30");
+    assert_eq!(response(&sent, 16)["success"], false);
+    assert_eq!(response(&sent, 17)["success"], true);
+    assert_eq!(events(&sent, "output").len(), 2);
+}
+
+#[test]
+fn steps_and_stops_on_disconnect() {
+    let setup = [request(
+        3,
+        "setBreakpoints",
+        json!({ "source": { "path": "test.lox" }, "breakpoints": [{ "line": 5 }] }),
+    )];
+    let requests = [
+        request(11, "next", json!({ "threadId": 1 })),
+        request(12, "stepIn", json!({ "threadId": 1 })),
+        request(13, "stackTrace", json!({ "threadId": 1 })),
+        request(14, "disconnect", json!({})),
+    ];
+    let sent = run_session("step", &setup, &requests);
+
+    let reasons: Vec<&Value> = events(&sent, "stopped")
+        .into_iter()
+        .map(|event| &event["body"]["reason"])
+        .collect();
+    assert_eq!(reasons, ["breakpoint", "step", "step"]);
+
+    let frames = &response(&sent, 13)["body"]["stackFrames"];
+    assert_eq!(frames[0]["name"], "add");
+    assert_eq!(frames[0]["line"], 2);
+
+    // The program is stopped, nothing is printed and no exit is reported.
+    assert!(events(&sent, "output").is_empty());
+    assert!(events(&sent, "exited").is_empty());
+}
+
+#[test]
+fn fails_garbled_requests_and_keeps_going() {
+    let mut input = b"Content-Length: 9\r\n\r\n{bad json".to_vec();
+    write_message(
+        &mut input,
+        &request(1, "initialize", json!({ "adapterID": "lox" })),
+    )
+    .unwrap();
+    write_message(&mut input, &request(2, "disconnect", json!({}))).unwrap();
+
+    let sent = run_input(input);
+
+    assert_eq!(sent[0]["type"], "response");
+    assert_eq!(sent[0]["success"], false);
+    assert_eq!(response(&sent, 1)["success"], true);
+    assert_eq!(response(&sent, 2)["success"], true);
+}
@@ -0,0 +1,239 @@
+//! A debugger that pauses a running program at breakpoints and between steps.
+//!
+//! `Debugger` is a `Hook` that keeps track of the call stack and decides when
+//! to pause, what happens while the program is paused is up to a `Frontend`,
+//! like the `Terminal` used by `lox_one debug`. Breakpoints and steps work on
+//! lines, stepping always goes on to another line or another call.
+
+pub mod terminal;
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::BTreeSet;
+
+use crate::ast::Stmt;
+use crate::error::LoxResult;
+use crate::interpreter::environment::Environment;
+use crate::interpreter::error::{RuntimeError, RuntimeResult};
+use crate::interpreter::hook::Hook;
+use crate::interpreter::Interpreter;
+use crate::lox_value::LoxValue;
+use crate::token::Position;
+
+/// A function call on the call stack, or the script itself at the bottom of it.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub name: String,
+    /// The position of the statement the frame is running.
+    pub position: Position,
+    /// Where the function was called, `None` for the script.
+    pub call_position: Option<Position>,
+    /// The scope of the statement the frame is running.
+    pub environment: Environment,
+}
+
+/// Why the program paused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseReason {
+    /// Before the first statement.
+    Entry,
+    Breakpoint,
+    Step,
+}
+
+/// How a paused program goes on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    /// Runs until the next breakpoint.
+    Continue,
+    /// Pauses at the next line, even inside a called function.
+    StepIn,
+    /// Pauses at the next line of the current function.
+    StepOver,
+    /// Pauses once the current function has returned.
+    StepOut,
+    /// Stops the program with a `RuntimeError::Stopped`.
+    Stop,
+}
+
+/// Decides what to do while the program is paused.
+pub trait Frontend {
+    fn paused(&mut self, session: &mut Session<'_>) -> Resume;
+}
+
+/// What a frontend can look at and change while the program is paused.
+pub struct Session<'a> {
+    pub interpreter: &'a mut Interpreter,
+    pub breakpoints: &'a mut BTreeSet<usize>,
+    pub reason: PauseReason,
+    frames: &'a [Frame],
+}
+
+impl Session<'_> {
+    /// Returns the frames on the call stack, the innermost first.
+    pub fn frames(&self) -> impl Iterator<Item = &Frame> {
+        self.frames.iter().rev()
+    }
+
+    /// Returns a frame by its index in `frames`, 0 is the innermost frame.
+    pub fn frame(&self, index: usize) -> Option<&Frame> {
+        self.frames.iter().rev().nth(index)
+    }
+
+    /// Returns where the program is paused.
+    pub fn position(&self) -> Position {
+        self.frames
+            .last()
+            .expect("The program pauses at a statement so there's a frame")
+            .position
+    }
+
+    /// Returns the local variables visible in a frame, inner scopes first.
+    pub fn locals(&self, frame: usize) -> Vec<(String, LoxValue)> {
+        let mut locals: Vec<(String, LoxValue)> = Vec::new();
+
+        if let Some(frame) = self.frame(frame) {
+            for (name, value) in frame.environment.local_scopes().into_iter().flatten() {
+                if !locals.iter().any(|(seen, _)| *seen == name) {
+                    locals.push((name, value));
+                }
+            }
+        }
+
+        locals
+    }
+
+    /// Evaluates an expression in the scope of a frame.
+    pub fn evaluate(&mut self, source: &str, frame: usize) -> LoxResult<LoxValue> {
+        let environment = match self.frame(frame) {
+            Some(frame) => frame.environment.clone(),
+            None => self.interpreter.globals.clone(),
+        };
+
+        self.interpreter.evaluate_in(source, &environment)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Entry,
+    Run,
+    StepIn,
+    /// Steps over calls made from a depth of the call stack.
+    StepOver(usize),
+    StepOut(usize),
+}
+
+pub struct Debugger<F> {
+    frontend: F,
+    breakpoints: BTreeSet<usize>,
+    frames: Vec<Frame>,
+    mode: Mode,
+    /// The depth of the call stack and the line of the last statement and of the last pause.
+    last_statement: Option<(usize, usize)>,
+    last_pause: Option<(usize, usize)>,
+}
+
+impl<F: Frontend> Debugger<F> {
+    /// Creates a debugger that pauses before the first statement.
+    pub fn new(frontend: F, breakpoints: impl IntoIterator<Item = usize>) -> Debugger<F> {
+        Debugger {
+            frontend,
+            breakpoints: breakpoints.into_iter().collect(),
+            frames: Vec::new(),
+            mode: Mode::Entry,
+            last_statement: None,
+            last_pause: None,
+        }
+    }
+
+    /// Returns why the program should pause at a statement on `line`, if it should.
+    fn pause_reason(&self, line: usize) -> Option<PauseReason> {
+        let here = (self.frames.len(), line);
+        let new_line = self.last_statement != Some(here);
+        let moved = self.last_pause != Some(here);
+
+        match self.mode {
+            Mode::Entry => Some(PauseReason::Entry),
+            _ if new_line && self.breakpoints.contains(&line) => Some(PauseReason::Breakpoint),
+            Mode::StepIn if moved => Some(PauseReason::Step),
+            Mode::StepOver(depth) if moved && self.frames.len() <= depth => Some(PauseReason::Step),
+            Mode::StepOut(depth) if self.frames.len() < depth => Some(PauseReason::Step),
+            _ => None,
+        }
+    }
+}
+
+impl<F: Frontend> Hook for Debugger<F> {
+    fn statement(&mut self, interpreter: &mut Interpreter, statement: &Stmt) -> RuntimeResult<()> {
+        // A block is only where its statements run, pausing at its '{' isn't useful.
+        if matches!(statement, Stmt::Block(..)) {
+            return Ok(());
+        }
+
+        let position = statement.get_position();
+        if self.frames.is_empty() {
+            self.frames.push(Frame {
+                name: "<script>".to_string(),
+                position,
+                call_position: None,
+                environment: interpreter.environment.clone(),
+            });
+        }
+
+        let frame = self.frames.last_mut().expect("There's at least the script");
+        frame.position = position;
+        frame.environment = interpreter.environment.clone();
+
+        let reason = self.pause_reason(position.line);
+        self.last_statement = Some((self.frames.len(), position.line));
+
+        let Some(reason) = reason else {
+            return Ok(());
+        };
+        self.last_pause = self.last_statement;
+
+        let mut session = Session {
+            interpreter,
+            breakpoints: &mut self.breakpoints,
+            reason,
+            frames: &self.frames,
+        };
+        let depth = self.frames.len();
+
+        self.mode = match self.frontend.paused(&mut session) {
+            Resume::Continue => Mode::Run,
+            Resume::StepIn => Mode::StepIn,
+            Resume::StepOver => Mode::StepOver(depth),
+            Resume::StepOut => Mode::StepOut(depth),
+            Resume::Stop => return Err(RuntimeError::Stopped(position)),
+        };
+
+        Ok(())
+    }
+
+    fn enter(
+        &mut self,
+        interpreter: &mut Interpreter,
+        name: &str,
+        _arguments: &[LoxValue],
+        position: Position,
+    ) {
+        self.frames.push(Frame {
+            name: name.to_string(),
+            position,
+            call_position: Some(position),
+            environment: interpreter.environment.clone(),
+        });
+    }
+
+    fn exit(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        _name: &str,
+        _result: &RuntimeResult<LoxValue>,
+    ) {
+        self.frames.pop();
+    }
+}
@@ -0,0 +1,187 @@
+use std::io::{self, BufRead, Write};
+
+use super::{Frontend, PauseReason, Resume, Session};
+
+const HELP: &str = "\
+Commands:
+  c, continue        Runs until the next breakpoint
+  s, step            Steps to the next line, into calls
+  n, next            Steps to the next line, over calls
+  o, out             Runs until the current function returns
+  b, break [line]    Adds a breakpoint, lists them without a line
+  d, delete <line>   Removes a breakpoint
+  bt, backtrace      Shows the call stack
+  f, frame <n>       Selects a frame of the call stack
+  l, locals          Shows the local variables of the selected frame
+  p, print <expr>    Evaluates an expression in the selected frame
+  w, watch <expr>    Shows an expression every time the program pauses
+  unwatch <n>        Removes a watch expression
+  list               Shows the source around the selected frame
+  q, quit            Stops the program";
+
+/// A command line debugger, reading commands from `input` and writing to `output`.
+pub struct Terminal<R, W> {
+    lines: Vec<String>,
+    input: R,
+    output: W,
+    watches: Vec<String>,
+    /// The frame commands look at, 0 is the innermost frame.
+    frame: usize,
+}
+
+impl<R: BufRead, W: Write> Terminal<R, W> {
+    pub fn new(source: &str, input: R, output: W) -> Terminal<R, W> {
+        Terminal {
+            lines: source.lines().map(str::to_string).collect(),
+            input,
+            output,
+            watches: Vec::new(),
+            frame: 0,
+        }
+    }
+
+    fn pause(&mut self, session: &mut Session<'_>) -> io::Result<Resume> {
+        self.frame = 0;
+
+        let position = session.position();
+        let reason = match session.reason {
+            PauseReason::Entry => "on entry",
+            PauseReason::Breakpoint => "at a breakpoint",
+            PauseReason::Step => "after a step",
+        };
+        let name = session.frame(0).map_or("", |frame| frame.name.as_str());
+        writeln!(self.output, "Paused {reason} in {name}, {position}.")?;
+        self.show_lines(position.line, position.line)?;
+        self.show_watches(session)?;
+
+        loop {
+            write!(self.output, "(debug) ")?;
+            self.output.flush()?;
+
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(Resume::Stop);
+            }
+
+            let line = line.trim();
+            let (command, argument) = match line.split_once(char::is_whitespace) {
+                Some((command, argument)) => (command, argument.trim()),
+                None => (line, ""),
+            };
+
+            match command {
+                "c" | "continue" => return Ok(Resume::Continue),
+                "s" | "step" => return Ok(Resume::StepIn),
+                "n" | "next" => return Ok(Resume::StepOver),
+                "o" | "out" => return Ok(Resume::StepOut),
+                "q" | "quit" => return Ok(Resume::Stop),
+                "b" | "break" if argument.is_empty() => {
+                    let lines: Vec<String> =
+                        session.breakpoints.iter().map(usize::to_string).collect();
+                    writeln!(self.output, "Breakpoints: {}", lines.join(", "))?;
+                }
+                "b" | "break" => match argument.parse() {
+                    Ok(line) => {
+                        session.breakpoints.insert(line);
+                        writeln!(self.output, "Added a breakpoint at line {line}.")?;
+                    }
+                    Err(_) => writeln!(self.output, "Expected a line number.")?,
+                },
+                "d" | "delete" => match argument.parse() {
+                    Ok(line) if session.breakpoints.remove(&line) => {
+                        writeln!(self.output, "Removed the breakpoint at line {line}.")?;
+                    }
+                    Ok(line) => writeln!(self.output, "There's no breakpoint at line {line}.")?,
+                    Err(_) => writeln!(self.output, "Expected a line number.")?,
+                },
+                "bt" | "backtrace" => {
+                    for (index, frame) in session.frames().enumerate() {
+                        let marker = if index == self.frame { '>' } else { ' ' };
+                        write!(
+                            self.output,
+                            "{marker} #{index} {}, line {}",
+                            frame.name, frame.position.line
+                        )?;
+                        match frame.call_position {
+                            Some(call) => writeln!(self.output, ", called at line {}", call.line)?,
+                            None => writeln!(self.output)?,
+                        }
+                    }
+                }
+                "f" | "frame" => match argument.parse() {
+                    Ok(index) if session.frame(index).is_some() => {
+                        self.frame = index;
+                        let line = session.frame(index).map_or(0, |frame| frame.position.line);
+                        self.show_lines(line, line)?;
+                    }
+                    _ => writeln!(
+                        self.output,
+                        "Expected the number of a frame from 'backtrace'."
+                    )?,
+                },
+                "l" | "locals" => {
+                    let locals = session.locals(self.frame);
+                    if locals.is_empty() {
+                        writeln!(self.output, "There are no local variables.")?;
+                    }
+                    for (name, value) in locals {
+                        writeln!(self.output, "{name} = {value}")?;
+                    }
+                }
+                "p" | "print" => match session.evaluate(argument, self.frame) {
+                    Ok(value) => writeln!(self.output, "{value}")?,
+                    Err(e) => writeln!(self.output, "{e}")?,
+                },
+                "w" | "watch" if !argument.is_empty() => {
+                    self.watches.push(argument.to_string());
+                    self.show_watches(session)?;
+                }
+                "unwatch" => match argument.parse::<usize>() {
+                    Ok(index) if index < self.watches.len() => {
+                        self.watches.remove(index);
+                    }
+                    _ => writeln!(self.output, "Expected the number of a watch expression.")?,
+                },
+                "list" => {
+                    let line = session
+                        .frame(self.frame)
+                        .map_or(0, |frame| frame.position.line);
+                    self.show_lines(line.saturating_sub(3), line + 3)?;
+                }
+                "h" | "help" => writeln!(self.output, "{HELP}")?,
+                "" => (),
+                _ => writeln!(
+                    self.output,
+                    "Unknown command '{command}', enter 'help' for the commands."
+                )?,
+            }
+        }
+    }
+
+    /// Shows the source lines from `first` to `last`, counting from 1.
+    fn show_lines(&mut self, first: usize, last: usize) -> io::Result<()> {
+        for number in first.max(1)..=last.min(self.lines.len()) {
+            writeln!(self.output, "{number:>4} | {}", self.lines[number - 1])?;
+        }
+
+        Ok(())
+    }
+
+    fn show_watches(&mut self, session: &mut Session<'_>) -> io::Result<()> {
+        for (index, watch) in self.watches.iter().enumerate() {
+            match session.evaluate(watch, self.frame) {
+                Ok(value) => writeln!(self.output, "watch #{index}: {watch} = {value}")?,
+                Err(e) => writeln!(self.output, "watch #{index}: {watch} = {e}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: BufRead, W: Write> Frontend for Terminal<R, W> {
+    fn paused(&mut self, session: &mut Session<'_>) -> Resume {
+        // The program can't be debugged any more if the terminal is gone.
+        self.pause(session).unwrap_or(Resume::Stop)
+    }
+}
@@ -0,0 +1,82 @@
+use std::io::Cursor;
+
+use super::terminal::Terminal;
+use super::*;
+
+use crate::error::LoxError;
+use crate::utils::Output;
+
+const SRC: &str = "\
+fun add(a, b) {
+    var sum = a + b;
+    return sum;
+}
+var x = 1;
+var y = add(x, 2);
+print y;";
+
+/// Debugs `SRC` with the given commands, returning whether it finished and what was written.
+fn debug(commands: &str, breakpoints: &[usize]) -> (LoxResult<()>, String) {
+    let output = Output::default();
+    let terminal = Terminal::new(SRC, Cursor::new(commands.to_string()), output.clone());
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_hook(Box::new(Debugger::new(
+        terminal,
+        breakpoints.iter().copied(),
+    )));
+    let result = interpreter.interpret_str(SRC).map(|_| ());
+
+    let written = output.text();
+    (result, written)
+}
+
+#[test]
+fn stops_at_breakpoints() {
+    let (result, written) = debug("b 3\nc\nbt\nlocals\np sum * 2\nc\n", &[]);
+    result.unwrap();
+
+    assert!(written.starts_with("Paused on entry in <script>, line 1, column 5.\n"));
+    assert!(written
+        .contains("Paused at a breakpoint in add, line 3, column 5.\n   3 |     return sum;\n"));
+    assert!(written.contains("> #0 add, line 3, called at line 6\n  #1 <script>, line 6\n"));
+    assert!(written.contains("sum = 3\na = 1\nb = 2\n"));
+    assert!(written.contains("(debug) 6\n"));
+}
+
+#[test]
+fn steps_through_calls() {
+    let (result, written) = debug("n\nn\ns\ns\no\nq\n", &[]);
+
+    assert!(matches!(
+        result,
+        Err(LoxError::Runtime(RuntimeError::Stopped(_)))
+    ));
+    assert_eq!(
+        written
+            .lines()
+            .filter(|line| line.contains("Paused"))
+            .map(|line| line.split(", ").nth(1).unwrap().to_string())
+            .collect::<Vec<String>>(),
+        ["line 1", "line 5", "line 6", "line 2", "line 3", "line 7"]
+    );
+}
+
+#[test]
+fn steps_over_calls() {
+    let (result, written) = debug("c\nn\nn\nc\n", &[6]);
+    result.unwrap();
+
+    assert!(written.contains("Paused at a breakpoint in <script>, line 6"));
+    assert!(written.contains("Paused after a step in <script>, line 7"));
+    assert!(!written.contains("in add"));
+}
+
+#[test]
+fn shows_watch_expressions() {
+    let (result, written) = debug("w x + 1\nn\nn\nunwatch 0\nc\n", &[]);
+    result.unwrap();
+
+    assert!(written.contains("watch #0: x + 1 = 2\n"));
+    assert_eq!(written.matches("watch #0").count(), 3);
+}
@@ -0,0 +1,15 @@
+use thiserror::Error;
+
+use super::SCHEMA_VERSION;
+
+#[derive(Debug, Error)]
+pub enum ReadError {
+    #[error("Couldn't parse the JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Unsupported schema version {0}, expected {SCHEMA_VERSION}.")]
+    Version(String),
+    #[error("Expected {expected} at {path}.")]
+    Invalid { expected: String, path: String },
+}
+
+pub type ReadResult<T> = Result<T, ReadError>;
@@ -0,0 +1,554 @@
+use std::rc::Rc;
+
+use serde_json::{json, Map, Value};
+
+use crate::ast::{Expr, ResolvedSlot, Stmt};
+use crate::function::{FunDecl, Parameter};
+use crate::lox_value::LoxValue;
+use crate::scanner::Scanner;
+use crate::token::{Position, Token};
+use crate::types::Type;
+
+use super::error::{ReadError, ReadResult};
+use super::SCHEMA_VERSION;
+
+/// Converts a program to the JSON document described in the module docs.
+pub fn program_to_json(statements: &[Stmt]) -> Value {
+    json!({
+        "version": SCHEMA_VERSION,
+        "statements": statements.iter().map(stmt_to_json).collect::<Vec<Value>>(),
+    })
+}
+
+pub fn stmt_to_json(statement: &Stmt) -> Value {
+    use Stmt::*;
+
+    match statement {
+        PrintStmt(expr) => json!({ "node": "Print", "expression": expr_to_json(expr) }),
+        ExprStmt(expr) => json!({ "node": "Expression", "expression": expr_to_json(expr) }),
+        Var {
+            name,
+            annotation,
+            initializer,
+            position,
+        } => json!({
+            "node": "Var",
+            "name": &**name,
+            "annotation": annotation.as_ref().map(type_to_json),
+            "initializer": expr_to_json(initializer),
+            "position": position_to_json(position),
+        }),
+        Block(statements, position) => json!({
+            "node": "Block",
+            "statements": statements.iter().map(stmt_to_json).collect::<Vec<Value>>(),
+            "position": position_to_json(position),
+        }),
+        IfStmt {
+            condition,
+            then_branch,
+            else_branch,
+            position,
+        } => json!({
+            "node": "If",
+            "condition": expr_to_json(condition),
+            "then": stmt_to_json(then_branch),
+            "else": else_branch.as_deref().map(stmt_to_json),
+            "position": position_to_json(position),
+        }),
+        WhileStmt {
+            condition,
+            body,
+            position,
+        } => json!({
+            "node": "While",
+            "condition": expr_to_json(condition),
+            "body": stmt_to_json(body),
+            "position": position_to_json(position),
+        }),
+        ForStmt {
+            initializer,
+            condition,
+            increment,
+            body,
+            position,
+        } => json!({
+            "node": "For",
+            "initializer": initializer.as_deref().map(stmt_to_json),
+            "condition": condition.as_ref().map(expr_to_json),
+            "increment": increment.as_ref().map(expr_to_json),
+            "body": stmt_to_json(body),
+            "position": position_to_json(position),
+        }),
+        BreakStmt(position) => json!({ "node": "Break", "position": position_to_json(position) }),
+        ContinueStmt(position) => {
+            json!({ "node": "Continue", "position": position_to_json(position) })
+        }
+        FunStmt {
+            fun_declaration,
+            position,
+        } => json!({
+            "node": "Function",
+            "declaration": declaration_to_json(fun_declaration),
+            "position": position_to_json(position),
+        }),
+        ReturnStmt { expr, position } => json!({
+            "node": "Return",
+            "value": expr.as_ref().map(expr_to_json),
+            "position": position_to_json(position),
+        }),
+    }
+}
+
+pub fn expr_to_json(expr: &Expr) -> Value {
+    use Expr::*;
+
+    match expr {
+        Identifier(name, position, _) => json!({
+            "node": "Identifier",
+            "name": &**name,
+            "position": position_to_json(position),
+        }),
+        Value { value, position } => json!({
+            "node": "Literal",
+            "value": literal_to_json(value),
+            "position": position_to_json(position),
+        }),
+        Grouping(expr, position) => json!({
+            "node": "Grouping",
+            "expression": expr_to_json(expr),
+            "position": position_to_json(position),
+        }),
+        Unary { op, rhs, position } => json!({
+            "node": "Unary",
+            "operator": op.lexeme,
+            "operand": expr_to_json(rhs),
+            "position": position_to_json(position),
+        }),
+        Binary {
+            lhs,
+            op,
+            rhs,
+            position,
+        } => json!({
+            "node": "Binary",
+            "operator": op.lexeme,
+            "left": expr_to_json(lhs),
+            "right": expr_to_json(rhs),
+            "position": position_to_json(position),
+        }),
+        Ternary {
+            condition,
+            result_1,
+            result_2,
+            position,
+        } => json!({
+            "node": "Ternary",
+            "condition": expr_to_json(condition),
+            "then": expr_to_json(result_1),
+            "else": expr_to_json(result_2),
+            "position": position_to_json(position),
+        }),
+        Assignment {
+            name,
+            value,
+            position,
+            ..
+        } => json!({
+            "node": "Assignment",
+            "name": &**name,
+            "value": expr_to_json(value),
+            "position": position_to_json(position),
+        }),
+        Call {
+            callee,
+            arguments,
+            position,
+        } => json!({
+            "node": "Call",
+            "callee": expr_to_json(callee),
+            "arguments": arguments.iter().map(expr_to_json).collect::<Vec<_>>(),
+            "position": position_to_json(position),
+        }),
+        Lambda {
+            fun_declaration,
+            position,
+        } => json!({
+            "node": "Lambda",
+            "declaration": declaration_to_json(fun_declaration),
+            "position": position_to_json(position),
+        }),
+        Interpolation { parts, position } => json!({
+            "node": "Interpolation",
+            "parts": parts.iter().map(expr_to_json).collect::<Vec<_>>(),
+            "position": position_to_json(position),
+        }),
+    }
+}
+
+fn declaration_to_json(declaration: &FunDecl) -> Value {
+    let params: Vec<Value> = declaration
+        .params
+        .iter()
+        .map(|param| {
+            json!({
+                "name": &*param.name,
+                "annotation": param.annotation.as_ref().map(type_to_json),
+                "position": position_to_json(&param.position),
+            })
+        })
+        .collect();
+
+    json!({
+        "name": &*declaration.name,
+        "params": params,
+        "return_type": declaration.return_type.as_ref().map(type_to_json),
+        "body": stmt_to_json(&declaration.body),
+        "position": position_to_json(&declaration.position),
+    })
+}
+
+pub fn position_to_json(position: &Position) -> Value {
+    json!({ "line": position.line, "column": position.column })
+}
+
+/// Converts a literal, the only values the parser puts in the tree.
+pub(super) fn literal_to_json(value: &LoxValue) -> Value {
+    match value {
+        LoxValue::Boolean(boolean) => json!(boolean),
+        LoxValue::Number(number) => json!(number),
+        LoxValue::String(string) => json!(&**string),
+        LoxValue::Nil => Value::Null,
+        value => json!(value.to_string()),
+    }
+}
+
+fn type_to_json(ty: &Type) -> Value {
+    match ty {
+        Type::Nilable(ty) => json!({ "nilable": type_to_json(ty) }),
+        Type::Function { params, ret } => json!({
+            "function": {
+                "params": params.iter().map(type_to_json).collect::<Vec<Value>>(),
+                "returns": type_to_json(ret),
+            }
+        }),
+        ty => json!(ty.to_string()),
+    }
+}
+
+/// Reads a program back from the JSON made by `program_to_json`.
+pub fn program_from_json(document: &Value) -> ReadResult<Vec<Stmt>> {
+    let reader = Reader {
+        value: document,
+        path: "$".to_string(),
+    };
+
+    let version = reader.field("version")?;
+    if version.value.as_u64() != Some(SCHEMA_VERSION) {
+        return Err(ReadError::Version(version.value.to_string()));
+    }
+
+    reader
+        .field("statements")?
+        .array()?
+        .iter()
+        .map(Reader::stmt)
+        .collect()
+}
+
+/// A value in the document being read and where it is, for errors.
+struct Reader<'a> {
+    value: &'a Value,
+    /// The path of the value from the root, e.g. `$.statements[0].initializer`.
+    path: String,
+}
+
+impl<'a> Reader<'a> {
+    fn invalid(&self, expected: &str) -> ReadError {
+        ReadError::Invalid {
+            expected: expected.to_string(),
+            path: self.path.clone(),
+        }
+    }
+
+    fn object(&self) -> ReadResult<&'a Map<String, Value>> {
+        self.value
+            .as_object()
+            .ok_or_else(|| self.invalid("an object"))
+    }
+
+    fn field(&self, name: &str) -> ReadResult<Reader<'a>> {
+        let value = self
+            .object()?
+            .get(name)
+            .ok_or_else(|| self.invalid(&format!("a '{name}' field")))?;
+
+        Ok(Reader {
+            value,
+            path: format!("{}.{name}", self.path),
+        })
+    }
+
+    /// Reads a field that can be null, `None` if it is.
+    fn optional(&self, name: &str) -> ReadResult<Option<Reader<'a>>> {
+        let field = self.field(name)?;
+        Ok((!field.value.is_null()).then_some(field))
+    }
+
+    fn array(&self) -> ReadResult<Vec<Reader<'a>>> {
+        let values = self
+            .value
+            .as_array()
+            .ok_or_else(|| self.invalid("an array"))?;
+
+        Ok(values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| Reader {
+                value,
+                path: format!("{}[{index}]", self.path),
+            })
+            .collect())
+    }
+
+    fn string(&self) -> ReadResult<String> {
+        self.value
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| self.invalid("a string"))
+    }
+
+    fn usize(&self) -> ReadResult<usize> {
+        self.value
+            .as_u64()
+            .map(|number| number as usize)
+            .ok_or_else(|| self.invalid("a positive integer"))
+    }
+
+    fn position(&self) -> ReadResult<Position> {
+        let position = self.field("position")?;
+        Ok(Position::new(
+            position.field("line")?.usize()?,
+            position.field("column")?.usize()?,
+        ))
+    }
+
+    fn boxed_stmt(&self, name: &str) -> ReadResult<Box<Stmt>> {
+        self.field(name)?.stmt().map(Box::new)
+    }
+
+    fn boxed_expr(&self, name: &str) -> ReadResult<Box<Expr>> {
+        self.field(name)?.expr().map(Box::new)
+    }
+
+    fn stmt(&self) -> ReadResult<Stmt> {
+        let node = self.field("node")?.string()?;
+
+        let statement = match node.as_str() {
+            "Print" => Stmt::PrintStmt(self.field("expression")?.expr()?),
+            "Expression" => Stmt::ExprStmt(self.field("expression")?.expr()?),
+            "Var" => Stmt::Var {
+                name: self.field("name")?.string()?.into(),
+                annotation: self.optional("annotation")?.map(|ty| ty.ty()).transpose()?,
+                initializer: self.field("initializer")?.expr()?,
+                position: self.position()?,
+            },
+            "Block" => Stmt::Block(
+                self.field("statements")?
+                    .array()?
+                    .iter()
+                    .map(Reader::stmt)
+                    .collect::<ReadResult<Vec<Stmt>>>()?,
+                self.position()?,
+            ),
+            "If" => Stmt::IfStmt {
+                condition: self.field("condition")?.expr()?,
+                then_branch: self.boxed_stmt("then")?,
+                else_branch: self
+                    .optional("else")?
+                    .map(|stmt| stmt.stmt().map(Box::new))
+                    .transpose()?,
+                position: self.position()?,
+            },
+            "While" => Stmt::WhileStmt {
+                condition: self.field("condition")?.expr()?,
+                body: self.boxed_stmt("body")?,
+                position: self.position()?,
+            },
+            "For" => Stmt::ForStmt {
+                initializer: self
+                    .optional("initializer")?
+                    .map(|stmt| stmt.stmt().map(Box::new))
+                    .transpose()?,
+                condition: self.optional("condition")?.map(|e| e.expr()).transpose()?,
+                increment: self.optional("increment")?.map(|e| e.expr()).transpose()?,
+                body: self.boxed_stmt("body")?,
+                position: self.position()?,
+            },
+            "Break" => Stmt::BreakStmt(self.position()?),
+            "Continue" => Stmt::ContinueStmt(self.position()?),
+            "Function" => Stmt::FunStmt {
+                fun_declaration: self.field("declaration")?.declaration()?,
+                position: self.position()?,
+            },
+            "Return" => Stmt::ReturnStmt {
+                expr: self.optional("value")?.map(|e| e.expr()).transpose()?,
+                position: self.position()?,
+            },
+            _ => return Err(self.field("node")?.invalid("the name of a statement")),
+        };
+
+        Ok(statement)
+    }
+
+    fn expr(&self) -> ReadResult<Expr> {
+        let node = self.field("node")?.string()?;
+        let position = self.position()?;
+
+        let expr = match node.as_str() {
+            "Identifier" => Expr::Identifier(
+                self.field("name")?.string()?.into(),
+                position,
+                ResolvedSlot::default(),
+            ),
+            "Literal" => Expr::Value {
+                value: self.field("value")?.literal()?,
+                position,
+            },
+            "Grouping" => Expr::Grouping(self.boxed_expr("expression")?, position),
+            "Unary" => Expr::Unary {
+                op: self.field("operator")?.operator(position)?,
+                rhs: self.boxed_expr("operand")?,
+                position,
+            },
+            "Binary" => Expr::Binary {
+                lhs: self.boxed_expr("left")?,
+                op: self.field("operator")?.operator(position)?,
+                rhs: self.boxed_expr("right")?,
+                position,
+            },
+            "Ternary" => Expr::Ternary {
+                condition: self.boxed_expr("condition")?,
+                result_1: self.boxed_expr("then")?,
+                result_2: self.boxed_expr("else")?,
+                position,
+            },
+            "Assignment" => Expr::Assignment {
+                name: self.field("name")?.string()?.into(),
+                value: self.boxed_expr("value")?,
+                position,
+                slot: ResolvedSlot::default(),
+            },
+            "Call" => Expr::Call {
+                callee: self.boxed_expr("callee")?,
+                arguments: self
+                    .field("arguments")?
+                    .array()?
+                    .iter()
+                    .map(Reader::expr)
+                    .collect::<ReadResult<Vec<Expr>>>()?,
+                position,
+            },
+            "Lambda" => Expr::Lambda {
+                fun_declaration: self.field("declaration")?.declaration()?,
+                position,
+            },
+            "Interpolation" => Expr::Interpolation {
+                parts: self
+                    .field("parts")?
+                    .array()?
+                    .iter()
+                    .map(Reader::expr)
+                    .collect::<ReadResult<Vec<Expr>>>()?,
+                position,
+            },
+            _ => return Err(self.field("node")?.invalid("the name of an expression")),
+        };
+
+        Ok(expr)
+    }
+
+    fn declaration(&self) -> ReadResult<Rc<FunDecl>> {
+        let params = self
+            .field("params")?
+            .array()?
+            .iter()
+            .map(|param| {
+                Ok(Parameter {
+                    name: param.field("name")?.string()?.into(),
+                    annotation: param
+                        .optional("annotation")?
+                        .map(|ty| ty.ty())
+                        .transpose()?,
+                    position: param.position()?,
+                })
+            })
+            .collect::<ReadResult<Vec<Parameter>>>()?;
+
+        Ok(Rc::new(FunDecl {
+            name: self.field("name")?.string()?.into(),
+            params,
+            return_type: self
+                .optional("return_type")?
+                .map(|ty| ty.ty())
+                .transpose()?,
+            body: self.boxed_stmt("body")?,
+            position: self.position()?,
+        }))
+    }
+
+    /// Reads an operator's token from its lexeme, operators are where their expression is.
+    fn operator(&self, position: Position) -> ReadResult<Token> {
+        let lexeme = self.string()?;
+        let tokens =
+            Scanner::tokens_from_str(&lexeme, false).map_err(|_| self.invalid("an operator"))?;
+
+        match tokens.as_slice() {
+            [token, _eof] if token.lexeme == lexeme => {
+                Ok(Token::new(token.token_type.clone(), lexeme, None, position))
+            }
+            _ => Err(self.invalid("an operator")),
+        }
+    }
+
+    fn literal(&self) -> ReadResult<LoxValue> {
+        match self.value {
+            Value::Null => Ok(LoxValue::Nil),
+            Value::Bool(boolean) => Ok(LoxValue::Boolean(*boolean)),
+            Value::Number(number) => number
+                .as_f64()
+                .map(LoxValue::Number)
+                .ok_or_else(|| self.invalid("a number")),
+            Value::String(string) => Ok(LoxValue::String(string.as_str().into())),
+            _ => Err(self.invalid("a literal")),
+        }
+    }
+
+    fn ty(&self) -> ReadResult<Type> {
+        if let Some(name) = self.value.as_str() {
+            return match name {
+                "any" => Ok(Type::Any),
+                "nil" => Ok(Type::Nil),
+                "bool" => Ok(Type::Bool),
+                "number" => Ok(Type::Number),
+                "string" => Ok(Type::String),
+                _ => Err(self.invalid("the name of a type")),
+            };
+        }
+
+        let object = self.object()?;
+        if object.contains_key("nilable") {
+            return Ok(Type::Nilable(Box::new(self.field("nilable")?.ty()?)));
+        }
+
+        let function = self.field("function")?;
+        Ok(Type::Function {
+            params: function
+                .field("params")?
+                .array()?
+                .iter()
+                .map(Reader::ty)
+                .collect::<ReadResult<Vec<Type>>>()?,
+            ret: Box::new(function.field("returns")?.ty()?),
+        })
+    }
+}
@@ -0,0 +1,92 @@
+//! Machine readable dumps of tokens and syntax trees, used by `lox_one tokens`
+//! and `lox_one parse`.
+//!
+//! # JSON schema, version 1
+//!
+//! A program is `{ "version": 1, "statements": [Stmt] }`. Every node is an
+//! object whose `"node"` field names it, positions are
+//! `{ "line": 1, "column": 1 }` and missing optional children are `null`.
+//!
+//! Statements:
+//!
+//! - `Print`, `Expression`: `expression`
+//! - `Var`: `name`, `annotation` (Type or null), `initializer`, `position`
+//! - `Block`: `statements`, `position`
+//! - `If`: `condition`, `then`, `else` (or null), `position`
+//! - `While`: `condition`, `body`, `position`
+//! - `For`: `initializer`, `condition`, `increment` (each or null), `body`, `position`
+//! - `Break`, `Continue`: `position`
+//! - `Function`: `declaration`, `position`
+//! - `Return`: `value` (or null), `position`
+//!
+//! Expressions, all with a `position`:
+//!
+//! - `Identifier`: `name`
+//! - `Literal`: `value`, a number, string, boolean or null for `nil`
+//! - `Grouping`: `expression`
+//! - `Unary`: `operator` (its lexeme, e.g. `"!"`), `operand`
+//! - `Binary`: `operator`, `left`, `right`
+//! - `Ternary`: `condition`, `then`, `else`
+//! - `Assignment`: `name`, `value`
+//! - `Call`: `callee`, `arguments`
+//! - `Lambda`: `declaration`
+//! - `Interpolation`: `parts`, alternating string literals and expressions
+//!
+//! A declaration is `{ "name", "params": [{ "name", "annotation", "position" }],
+//! "return_type", "body", "position" }`. Types are `"any"`, `"nil"`, `"bool"`,
+//! `"number"`, `"string"`, `{ "nilable": Type }` or
+//! `{ "function": { "params": [Type], "returns": Type } }`.
+//!
+//! Tokens are `{ "version": 1, "tokens": [{ "kind", "lexeme", "literal", "position" }] }`
+//! where `kind` is the name of the `TokenType`, e.g. `"LeftParen"`.
+//!
+//! The version changes whenever a document of the old version would be read
+//! differently.
+//!
+//! # S-expressions
+//!
+//! The S-expressions are only written, e.g. `(print (binary@1:9 + (literal@1:7 1)
+//! (literal@1:11 2)))`, with `@line:column` after the nodes' names and `_` for
+//! missing children.
+
+pub mod error;
+mod json;
+mod sexpr;
+
+#[cfg(test)]
+mod tests;
+
+use serde_json::{json, Value};
+
+use crate::ast::Stmt;
+use crate::token::Token;
+
+use self::error::ReadResult;
+
+pub use self::json::{expr_to_json, program_from_json, program_to_json, stmt_to_json};
+pub use self::sexpr::{expr_to_sexpr, program_to_sexpr, stmt_to_sexpr};
+
+/// The version of the JSON documents, see the module docs.
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// Converts the tokens of a program to JSON.
+pub fn tokens_to_json(tokens: &[Token]) -> Value {
+    let tokens: Vec<Value> = tokens
+        .iter()
+        .map(|token| {
+            json!({
+                "kind": format!("{:?}", token.token_type),
+                "lexeme": token.lexeme,
+                "literal": token.literal.as_ref().map(json::literal_to_json),
+                "position": json::position_to_json(&token.position),
+            })
+        })
+        .collect();
+
+    json!({ "version": SCHEMA_VERSION, "tokens": tokens })
+}
+
+/// Reads a program from the text of a JSON document made by `program_to_json`.
+pub fn read_program(text: &str) -> ReadResult<Vec<Stmt>> {
+    program_from_json(&serde_json::from_str(text)?)
+}
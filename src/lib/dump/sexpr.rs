@@ -0,0 +1,199 @@
+use crate::ast::{Expr, Stmt};
+use crate::function::FunDecl;
+use crate::lox_value::LoxValue;
+use crate::token::Position;
+
+/// Writes a program as S-expressions, one top level statement per line.
+pub fn program_to_sexpr(statements: &[Stmt]) -> String {
+    statements
+        .iter()
+        .map(|statement| stmt_to_sexpr(statement) + "\n")
+        .collect()
+}
+
+pub fn stmt_to_sexpr(statement: &Stmt) -> String {
+    use Stmt::*;
+
+    match statement {
+        PrintStmt(expr) => format!("(print {})", expr_to_sexpr(expr)),
+        ExprStmt(expr) => format!("(expression {})", expr_to_sexpr(expr)),
+        Var {
+            name,
+            annotation,
+            initializer,
+            position,
+        } => {
+            let annotation = match annotation {
+                Some(annotation) => format!("{:?}", annotation.to_string()),
+                None => "_".to_string(),
+            };
+            format!(
+                "(var{} {name} {annotation} {})",
+                at(position),
+                expr_to_sexpr(initializer)
+            )
+        }
+        Block(statements, position) => {
+            node("block", position, statements.iter().map(stmt_to_sexpr))
+        }
+        IfStmt {
+            condition,
+            then_branch,
+            else_branch,
+            position,
+        } => node(
+            "if",
+            position,
+            [
+                expr_to_sexpr(condition),
+                stmt_to_sexpr(then_branch),
+                optional(else_branch.as_deref(), stmt_to_sexpr),
+            ],
+        ),
+        WhileStmt {
+            condition,
+            body,
+            position,
+        } => node(
+            "while",
+            position,
+            [expr_to_sexpr(condition), stmt_to_sexpr(body)],
+        ),
+        ForStmt {
+            initializer,
+            condition,
+            increment,
+            body,
+            position,
+        } => node(
+            "for",
+            position,
+            [
+                optional(initializer.as_deref(), stmt_to_sexpr),
+                optional(condition.as_ref(), expr_to_sexpr),
+                optional(increment.as_ref(), expr_to_sexpr),
+                stmt_to_sexpr(body),
+            ],
+        ),
+        BreakStmt(position) => format!("(break{})", at(position)),
+        ContinueStmt(position) => format!("(continue{})", at(position)),
+        FunStmt {
+            fun_declaration,
+            position,
+        } => node("fun", position, [declaration_to_sexpr(fun_declaration)]),
+        ReturnStmt { expr, position } => {
+            node("return", position, [optional(expr.as_ref(), expr_to_sexpr)])
+        }
+    }
+}
+
+pub fn expr_to_sexpr(expr: &Expr) -> String {
+    use Expr::*;
+
+    match expr {
+        Identifier(name, position, _) => format!("(identifier{} {name})", at(position)),
+        Value { value, position } => {
+            let value = match value {
+                LoxValue::String(string) => format!("{string:?}"),
+                value => value.to_string(),
+            };
+            format!("(literal{} {value})", at(position))
+        }
+        Grouping(expr, position) => node("group", position, [expr_to_sexpr(expr)]),
+        Unary { op, rhs, position } => format!(
+            "(unary{} {} {})",
+            at(position),
+            op.lexeme,
+            expr_to_sexpr(rhs)
+        ),
+        Binary {
+            lhs,
+            op,
+            rhs,
+            position,
+        } => format!(
+            "(binary{} {} {} {})",
+            at(position),
+            op.lexeme,
+            expr_to_sexpr(lhs),
+            expr_to_sexpr(rhs)
+        ),
+        Ternary {
+            condition,
+            result_1,
+            result_2,
+            position,
+        } => node(
+            "ternary",
+            position,
+            [
+                expr_to_sexpr(condition),
+                expr_to_sexpr(result_1),
+                expr_to_sexpr(result_2),
+            ],
+        ),
+        Assignment {
+            name,
+            value,
+            position,
+            ..
+        } => format!("(assign{} {name} {})", at(position), expr_to_sexpr(value)),
+        Call {
+            callee,
+            arguments,
+            position,
+        } => node(
+            "call",
+            position,
+            std::iter::once(expr_to_sexpr(callee)).chain(arguments.iter().map(expr_to_sexpr)),
+        ),
+        Lambda {
+            fun_declaration,
+            position,
+        } => node("lambda", position, [declaration_to_sexpr(fun_declaration)]),
+        Interpolation { parts, position } => {
+            node("interpolation", position, parts.iter().map(expr_to_sexpr))
+        }
+    }
+}
+
+fn declaration_to_sexpr(declaration: &FunDecl) -> String {
+    let params: Vec<String> = declaration
+        .params
+        .iter()
+        .map(|param| format!("{:?}", param.to_string()))
+        .collect();
+    let return_type = match &declaration.return_type {
+        Some(ty) => format!("{:?}", ty.to_string()),
+        None => "_".to_string(),
+    };
+
+    format!(
+        "(declaration{} {} ({}) {return_type} {})",
+        at(&declaration.position),
+        declaration.name,
+        params.join(" "),
+        stmt_to_sexpr(&declaration.body)
+    )
+}
+
+/// Writes a node with its children.
+fn node(name: &str, position: &Position, children: impl IntoIterator<Item = String>) -> String {
+    let mut sexpr = format!("({name}{}", at(position));
+    for child in children {
+        sexpr.push(' ');
+        sexpr.push_str(&child);
+    }
+    sexpr.push(')');
+
+    sexpr
+}
+
+/// Writes a child that may be missing, as `_` if it is.
+fn optional<T>(child: Option<T>, to_sexpr: impl Fn(T) -> String) -> String {
+    child.map_or_else(|| "_".to_string(), to_sexpr)
+}
+
+fn at(position: &Position) -> String {
+    format!("@{}:{}", position.line, position.column)
+}
@@ -0,0 +1,144 @@
+use super::*;
+
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::utils::read_file;
+
+const SRC: &str = "\
+var count: number? = 2;
+fun add(a: number, b): number {
+    return a + b;
+}
+var double = (x) => x * 2;
+for (var i = 0; i < 3; i = i + 1) {
+    if (!(i == 1) and true) print \"${i}: ${add(i, count)}\"; else continue;
+}
+while (false) break;
+print count > 1 ? double(-count) : nil;";
+
+#[test]
+fn writes_programs_as_json() {
+    let statements = Parser::parse_str("var x: string? = \"a\";\nprint -x;").unwrap();
+
+    assert_eq!(
+        program_to_json(&statements),
+        json!({
+            "version": 1,
+            "statements": [
+                {
+                    "node": "Var",
+                    "name": "x",
+                    "annotation": { "nilable": "string" },
+                    "initializer": {
+                        "node": "Literal",
+                        "value": "a",
+                        "position": { "line": 1, "column": 18 },
+                    },
+                    "position": { "line": 1, "column": 5 },
+                },
+                {
+                    "node": "Print",
+                    "expression": {
+                        "node": "Unary",
+                        "operator": "-",
+                        "operand": {
+                            "node": "Identifier",
+                            "name": "x",
+                            "position": { "line": 2, "column": 8 },
+                        },
+                        "position": { "line": 2, "column": 7 },
+                    },
+                },
+            ],
+        })
+    );
+}
+
+#[test]
+fn reads_programs_back_from_json() {
+    let statements = Parser::parse_str(SRC).unwrap();
+    let document = program_to_json(&statements);
+
+    let read = read_program(&document.to_string()).unwrap();
+    assert_eq!(program_to_json(&read), document);
+    assert_eq!(program_to_sexpr(&read), program_to_sexpr(&statements));
+
+    // The positions are kept, so the program still resolves and runs.
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(Box::new(std::io::sink()));
+    interpreter.interpret(&read, false, false).unwrap();
+}
+
+#[test]
+fn reads_every_example() {
+    for example in [
+        "fun_decl",
+        "lambda",
+        "interpolation",
+        "strings",
+        "types",
+        "for_stmt",
+    ] {
+        let src = read_file(&format!("examples/{example}.lox"));
+        let document = program_to_json(&Parser::parse_str(&src).unwrap());
+
+        let read = read_program(&document.to_string()).unwrap();
+        assert_eq!(program_to_json(&read), document, "{example}");
+    }
+}
+
+#[test]
+fn reports_invalid_documents() {
+    let errors: Vec<String> = [
+        "{",
+        r#"{ "version": 2, "statements": [] }"#,
+        r#"{ "version": 1, "statements": [{ "node": "Print" }] }"#,
+        r#"{ "version": 1, "statements": [{ "node": "Loop" }] }"#,
+        r#"{ "version": 1, "statements": [{ "node": "Break", "position": { "line": -1, "column": 1 } }] }"#,
+        r#"{ "version": 1, "statements": [{ "node": "Expression", "expression":
+            { "node": "Unary", "operator": "!!", "operand": null, "position": { "line": 1, "column": 1 } } }] }"#,
+    ]
+    .iter()
+    .map(|text| read_program(text).unwrap_err().to_string())
+    .collect();
+
+    assert!(errors[0].starts_with("Couldn't parse the JSON"));
+    assert_eq!(
+        errors[1..],
+        [
+            "Unsupported schema version 2, expected 1.",
+            "Expected a 'expression' field at $.statements[0].",
+            "Expected the name of a statement at $.statements[0].node.",
+            "Expected a positive integer at $.statements[0].position.line.",
+            "Expected an operator at $.statements[0].expression.operator.",
+        ]
+    );
+}
+
+#[test]
+fn writes_programs_as_sexprs() {
+    let statements = Parser::parse_str("fun f(a) { return a or nil; }\nprint f(1 + 2);").unwrap();
+
+    assert_eq!(
+        program_to_sexpr(&statements),
+        "(fun@1:5 (declaration@1:5 f (\"a\") _ (block@1:10 (return@1:12 (binary@1:21 or (identifier@1:19 a) (literal@1:24 nil))))))\n\
+         (print (call@2:13 (identifier@2:7 f) (binary@2:11 + (literal@2:9 1) (literal@2:13 2))))\n"
+    );
+}
+
+#[test]
+fn writes_tokens_as_json() {
+    let tokens = Scanner::tokens_from_str("x = \"a\";", false).unwrap();
+
+    assert_eq!(
+        tokens_to_json(&tokens)["tokens"],
+        json!([
+            { "kind": "Identifier", "lexeme": "x", "literal": "x", "position": { "line": 1, "column": 1 } },
+            { "kind": "Equal", "lexeme": "=", "literal": null, "position": { "line": 1, "column": 3 } },
+            { "kind": "String", "lexeme": "\"a\"", "literal": "a", "position": { "line": 1, "column": 5 } },
+            { "kind": "Semicolon", "lexeme": ";", "literal": null, "position": { "line": 1, "column": 8 } },
+            { "kind": "Eof", "lexeme": "", "literal": null, "position": { "line": 1, "column": 9 } },
+        ])
+    );
+}
@@ -1,8 +1,11 @@
-use crate::{interpreter::error::RuntimeError, parser::error::ParserError};
+use crate::{
+    interpreter::error::RuntimeError, parser::error::ParserError, scanner::error::ScanError,
+};
 use colored::Colorize;
 
 #[derive(Debug)]
 pub enum LoxError {
+    Scan(ScanError),
     Parser(ParserError),
     Runtime(RuntimeError),
     IO(std::io::Error),
@@ -12,6 +15,7 @@ impl std::fmt::Display for LoxError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use LoxError::*;
         match self {
+            Scan(e) => write!(f, "{} {}", "Scanner Error:".red().bold(), e),
             Parser(e) => write!(f, "{} {}", "Parser Error:".red().bold(), e),
             Runtime(e) => write!(f, "{} {}", "Runtime Error".red().bold(), e),
             IO(e) => write!(f, "{} {}", "IO Error:".red().bold(), e),
@@ -0,0 +1,540 @@
+//! Pretty-prints Lox programs in a single consistent style.
+//!
+//! Statements are written from the AST, so the layout of the original source
+//! doesn't matter, except for comments and single blank lines between
+//! statements which are kept. Comments aren't part of the AST, they're written
+//! out in between the statements they were found between.
+
+#[cfg(test)]
+mod tests;
+
+use crate::ast::{Expr, Stmt};
+use crate::error::{LoxError, LoxResult};
+use crate::function::FunDecl;
+use crate::lox_value::LoxValue;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::token::{Comment, Position, Token};
+use crate::token_type::TokenType;
+
+/// The width lines are wrapped at when possible.
+pub const MAX_WIDTH: usize = 80;
+
+const INDENT: &str = "    ";
+
+/// Formats a whole program, the source must scan and parse without errors.
+pub fn format_source(source: &str) -> LoxResult<String> {
+    let mut scanner = Scanner::new(source);
+    scanner.scan_tokens().map_err(LoxError::Scan)?;
+
+    let statements = Parser::new(scanner.tokens.clone())
+        .program()
+        .map_err(LoxError::Parser)?;
+
+    let mut formatter = Formatter {
+        lines: source.lines().collect(),
+        tokens: scanner.tokens,
+        comments: scanner.comments,
+        next_comment: 0,
+        out: String::new(),
+        indent: 0,
+    };
+    formatter.program(&statements);
+
+    Ok(formatter.out)
+}
+
+struct Formatter<'a> {
+    lines: Vec<&'a str>,
+    tokens: Vec<Token>,
+    comments: Vec<Comment>,
+    /// The first comment that hasn't been written yet.
+    next_comment: usize,
+    out: String,
+    indent: usize,
+}
+
+impl Formatter<'_> {
+    fn program(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+        self.comments_before(Position::new(usize::MAX, usize::MAX));
+    }
+
+    /// Writes a statement on its own lines along with the comments before it.
+    fn statement(&mut self, statement: &Stmt) {
+        let start = self.stmt_start(statement);
+
+        self.comments_before(start);
+        self.separate(start.line);
+        self.out.push_str(&self.pad());
+        self.stmt_body(statement);
+        self.out.push('\n');
+    }
+
+    /// Writes a statement from the current column, without a line break at the end.
+    fn stmt_body(&mut self, statement: &Stmt) {
+        use Stmt::*;
+
+        match statement {
+            PrintStmt(expr) => {
+                self.out.push_str("print ");
+                self.expr_then(expr, ";");
+            }
+            ExprStmt(expr) => self.expr_then(expr, ";"),
+            Var {
+                name,
+                annotation,
+                initializer,
+                position,
+            } => {
+                self.out.push_str(&format!("var {name}"));
+                if let Some(annotation) = annotation {
+                    self.out.push_str(&format!(": {annotation}"));
+                }
+
+                // 'var a;' is parsed as if it was 'var a = nil;' with the nil placed at 'a'.
+                let implicit = matches!(
+                    initializer,
+                    Expr::Value { value: LoxValue::Nil, position: p } if p == position
+                );
+                if implicit {
+                    self.out.push(';');
+                } else {
+                    self.out.push_str(" = ");
+                    self.expr_then(initializer, ";");
+                }
+            }
+            Block(statements, position) => self.block(statements, *position),
+            IfStmt {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.out.push_str("if (");
+                self.expr_then(condition, ") ");
+                self.stmt_body(then_branch);
+
+                if let Some(else_branch) = else_branch {
+                    if matches!(**then_branch, Block(..)) {
+                        self.out.push(' ');
+                    } else {
+                        self.out.push('\n');
+                        self.out.push_str(&self.pad());
+                    }
+                    self.out.push_str("else ");
+                    self.stmt_body(else_branch);
+                }
+            }
+            WhileStmt {
+                condition, body, ..
+            } => {
+                self.out.push_str("while (");
+                self.expr_then(condition, ") ");
+                self.stmt_body(body);
+            }
+            ForStmt {
+                initializer,
+                condition,
+                increment,
+                body,
+                ..
+            } => {
+                self.out.push_str("for (");
+                match initializer {
+                    Some(initializer) => self.stmt_body(initializer),
+                    None => self.out.push(';'),
+                }
+                if let Some(condition) = condition {
+                    self.out.push(' ');
+                    self.expr_then(condition, "");
+                }
+                self.out.push(';');
+                if let Some(increment) = increment {
+                    self.out.push(' ');
+                    self.expr_then(increment, "");
+                }
+                self.out.push_str(") ");
+                self.stmt_body(body);
+            }
+            BreakStmt(_) => self.out.push_str("break;"),
+            ContinueStmt(_) => self.out.push_str("continue;"),
+            FunStmt {
+                fun_declaration, ..
+            } => {
+                self.out.push_str(&format!("fun {}", fun_declaration.name));
+                self.function(fun_declaration);
+            }
+            ReturnStmt { expr, .. } => match expr {
+                Some(expr) => {
+                    self.out.push_str("return ");
+                    self.expr_then(expr, ";");
+                }
+                None => self.out.push_str("return;"),
+            },
+        }
+    }
+
+    fn block(&mut self, statements: &[Stmt], open: Position) {
+        let close = self.closing_brace(open);
+
+        self.out.push('{');
+        if statements.is_empty() && !self.has_comments_before(close) {
+            self.out.push('}');
+            return;
+        }
+        self.out.push('\n');
+
+        self.indent += 1;
+        for statement in statements {
+            self.statement(statement);
+        }
+        self.comments_before(close);
+        self.indent -= 1;
+
+        self.out.push_str(&self.pad());
+        self.out.push('}');
+    }
+
+    /// Writes the parameters and body of a function.
+    fn function(&mut self, declaration: &FunDecl) {
+        self.out.push_str(&format!("{} ", signature(declaration)));
+
+        match &*declaration.body {
+            Stmt::Block(statements, position) => self.block(statements, *position),
+            body => self.stmt_body(body),
+        }
+    }
+
+    /// Writes an expression starting at the current column followed by `suffix`.
+    fn expr_then(&mut self, expr: &Expr, suffix: &str) {
+        let text = self.expr(expr, self.column(), suffix.len());
+
+        self.out.push_str(&text);
+        self.out.push_str(suffix);
+    }
+
+    /// Renders an expression that starts at `column` and is followed by `suffix` more characters.
+    /// It's kept on one line if it fits and broken over several lines otherwise.
+    fn expr(&mut self, expr: &Expr, column: usize, suffix: usize) -> String {
+        let next_comment = self.next_comment;
+        let flat = self.flat(expr);
+        if fits(&flat, column, suffix) {
+            return flat;
+        }
+
+        // Any comments in lambdas were written to the flat version, they'll be written again.
+        self.next_comment = next_comment;
+        self.broken(expr, column, suffix)
+    }
+
+    /// Renders an expression on a single line, apart from the bodies of lambdas.
+    fn flat(&mut self, expr: &Expr) -> String {
+        use Expr::*;
+
+        match expr {
+            Identifier(name, ..) => name.to_string(),
+            Value { value, position } => match self.token_at(*position) {
+                Some(token) => token.lexeme.clone(),
+                None => value.to_string(),
+            },
+            Grouping(expr, _) => format!("({})", self.flat(expr)),
+            Unary { op, rhs, .. } => format!("{}{}", op.lexeme, self.flat(rhs)),
+            Binary { lhs, op, rhs, .. } => {
+                format!("{} {} {}", self.flat(lhs), op.lexeme, self.flat(rhs))
+            }
+            Ternary {
+                condition,
+                result_1,
+                result_2,
+                ..
+            } => format!(
+                "{} ? {} : {}",
+                self.flat(condition),
+                self.flat(result_1),
+                self.flat(result_2)
+            ),
+            Assignment { name, value, .. } => format!("{} = {}", name, self.flat(value)),
+            Call {
+                callee, arguments, ..
+            } => {
+                let callee = self.flat(callee);
+                let arguments: Vec<String> = arguments.iter().map(|arg| self.flat(arg)).collect();
+
+                format!("{}({})", callee, arguments.join(", "))
+            }
+            Lambda {
+                fun_declaration,
+                position,
+            } => self.lambda(fun_declaration, *position),
+            Interpolation { parts, .. } => parts.iter().map(|part| self.flat(part)).collect(),
+        }
+    }
+
+    /// Renders an expression that doesn't fit on a line by breaking it at its outermost operator.
+    fn broken(&mut self, expr: &Expr, column: usize, suffix: usize) -> String {
+        use Expr::*;
+
+        match expr {
+            Call {
+                callee, arguments, ..
+            } if !arguments.is_empty() => {
+                let callee = self.expr(callee, column, 1);
+
+                self.indent += 1;
+                let pad = self.pad();
+                let last = arguments.len() - 1;
+                let arguments: Vec<String> = arguments
+                    .iter()
+                    .enumerate()
+                    .map(|(i, arg)| {
+                        let suffix = if i == last { 0 } else { 1 };
+                        format!("{pad}{}", self.expr(arg, pad.len(), suffix))
+                    })
+                    .collect();
+                self.indent -= 1;
+
+                format!("{callee}(\n{}\n{})", arguments.join(",\n"), self.pad())
+            }
+            Binary { op, .. } => {
+                // Operators of the same precedence are all broken at once, one per line.
+                let mut operands = Vec::new();
+                let mut first = expr;
+                while let Binary {
+                    lhs,
+                    op: lhs_op,
+                    rhs,
+                    ..
+                } = first
+                {
+                    if precedence(&lhs_op.token_type) != precedence(&op.token_type) {
+                        break;
+                    }
+                    operands.push((lhs_op, &**rhs));
+                    first = lhs;
+                }
+                operands.reverse();
+
+                let mut res = self.expr(first, column, 0);
+                self.indent += 1;
+                let pad = self.pad();
+                let last = operands.len() - 1;
+                for (i, (op, operand)) in operands.into_iter().enumerate() {
+                    let suffix = if i == last { suffix } else { 0 };
+                    let operand = self.expr(operand, pad.len() + op.lexeme.len() + 1, suffix);
+                    res.push_str(&format!("\n{pad}{} {operand}", op.lexeme));
+                }
+                self.indent -= 1;
+
+                res
+            }
+            Ternary {
+                condition,
+                result_1,
+                result_2,
+                ..
+            } => {
+                let condition = self.expr(condition, column, 0);
+
+                self.indent += 1;
+                let pad = self.pad();
+                let result_1 = self.expr(result_1, pad.len() + 2, 0);
+                let result_2 = self.expr(result_2, pad.len() + 2, suffix);
+                self.indent -= 1;
+
+                format!("{condition}\n{pad}? {result_1}\n{pad}: {result_2}")
+            }
+            Assignment { name, value, .. } => {
+                let value = self.expr(value, column + name.chars().count() + 3, suffix);
+                format!("{name} = {value}")
+            }
+            Grouping(expr, _) => format!("({})", self.expr(expr, column + 1, suffix + 1)),
+            Unary { op, rhs, .. } => {
+                let rhs = self.expr(rhs, column + op.lexeme.len(), suffix);
+                format!("{}{rhs}", op.lexeme)
+            }
+            _ => self.flat(expr),
+        }
+    }
+
+    fn lambda(&mut self, declaration: &FunDecl, position: Position) -> String {
+        let is_arrow = self
+            .token_at(position)
+            .is_some_and(|token| token.token_type == TokenType::LeftParen);
+
+        if !is_arrow {
+            return self.render(|f| {
+                f.out.push_str("fun ");
+                f.function(declaration);
+            });
+        }
+
+        let params = format!("{} => ", signature(declaration));
+        match &*declaration.body {
+            // An arrow function with an expression body is parsed into a block that returns it.
+            Stmt::Block(statements, position)
+                if self
+                    .token_at(*position)
+                    .is_some_and(|token| token.token_type == TokenType::LeftBrace) =>
+            {
+                let body = self.render(|f| f.block(statements, *position));
+                params + &body
+            }
+            Stmt::Block(statements, _) => match statements.as_slice() {
+                [Stmt::ReturnStmt {
+                    expr: Some(expr), ..
+                }] => params + &self.flat(expr),
+                _ => unreachable!("An arrow function body is either a block or an expression"),
+            },
+            _ => unreachable!("The body of a function is always a block"),
+        }
+    }
+
+    /// Runs `write` with an empty output, returning what it wrote.
+    fn render(&mut self, write: impl FnOnce(&mut Self)) -> String {
+        let out = std::mem::take(&mut self.out);
+        write(self);
+        std::mem::replace(&mut self.out, out)
+    }
+
+    /// Writes the comments that come before `position`.
+    ///
+    /// Comments that followed code in the source are added to the end of the last line written,
+    /// the rest go on lines of their own.
+    fn comments_before(&mut self, position: Position) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.position >= position {
+                break;
+            }
+            let comment = comment.clone();
+            self.next_comment += 1;
+
+            if comment.trailing && self.out.ends_with('\n') {
+                self.out.pop();
+                self.out.push_str(&format!(" {}\n", comment.text));
+            } else {
+                self.separate(comment.position.line);
+                self.out
+                    .push_str(&format!("{}{}\n", self.pad(), comment.text));
+            }
+        }
+    }
+
+    fn has_comments_before(&self, position: Position) -> bool {
+        self.comments
+            .get(self.next_comment)
+            .is_some_and(|comment| comment.position < position)
+    }
+
+    /// Keeps a blank line before `line` if there was one in the source.
+    fn separate(&mut self, line: usize) {
+        let blank_before = line >= 2
+            && self
+                .lines
+                .get(line - 2)
+                .is_some_and(|line| line.trim().is_empty());
+
+        if blank_before
+            && !self.out.is_empty()
+            && !self.out.ends_with("{\n")
+            && !self.out.ends_with("\n\n")
+        {
+            self.out.push('\n');
+        }
+    }
+
+    fn pad(&self) -> String {
+        INDENT.repeat(self.indent)
+    }
+
+    /// The number of characters written on the current line.
+    fn column(&self) -> usize {
+        let line = match self.out.rfind('\n') {
+            Some(index) => &self.out[index + 1..],
+            None => &self.out,
+        };
+
+        line.chars().count()
+    }
+
+    fn token_index(&self, position: Position) -> Result<usize, usize> {
+        self.tokens
+            .binary_search_by_key(&position, |token| token.position)
+    }
+
+    fn token_at(&self, position: Position) -> Option<&Token> {
+        self.token_index(position).ok().map(|i| &self.tokens[i])
+    }
+
+    /// The position of the token before the one at `position`.
+    fn token_before(&self, position: Position) -> Position {
+        let index = self.token_index(position).unwrap_or_else(|i| i);
+        self.tokens[index.saturating_sub(1)].position
+    }
+
+    /// Finds the '}' matching the '{' at `open`.
+    fn closing_brace(&self, open: Position) -> Position {
+        let mut depth = 0;
+
+        for token in &self.tokens[self.token_index(open).unwrap_or_else(|i| i)..] {
+            match token.token_type {
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace if depth == 1 => return token.position,
+                TokenType::RightBrace => depth -= 1,
+                _ => (),
+            }
+        }
+
+        Position::new(usize::MAX, usize::MAX)
+    }
+
+    /// Finds where a statement starts, which isn't always the position stored in the AST.
+    fn stmt_start(&self, statement: &Stmt) -> Position {
+        use Stmt::*;
+
+        match statement {
+            PrintStmt(expr) => self.token_before(expr.start_position()),
+            ExprStmt(expr) => expr.start_position(),
+            Var { position, .. } | FunStmt { position, .. } => self.token_before(*position),
+            // These are positioned at the ';' at their end.
+            BreakStmt(position) | ContinueStmt(position) => self.token_before(*position),
+            Block(_, position)
+            | IfStmt { position, .. }
+            | WhileStmt { position, .. }
+            | ForStmt { position, .. }
+            | ReturnStmt { position, .. } => *position,
+        }
+    }
+}
+
+/// The parameters of a function with its return type if it has one, e.g. `(a: number): number`.
+fn signature(declaration: &FunDecl) -> String {
+    match &declaration.return_type {
+        Some(ty) => format!("({}): {}", declaration.params_repr(), ty),
+        None => format!("({})", declaration.params_repr()),
+    }
+}
+
+/// Checks if `text` fits on the line from `column`, only the first line
+/// counts for text that spans several lines like lambdas.
+fn fits(text: &str, column: usize, suffix: usize) -> bool {
+    match text.split_once('\n') {
+        Some((first, _)) => column + first.chars().count() <= MAX_WIDTH,
+        None => column + text.chars().count() + suffix <= MAX_WIDTH,
+    }
+}
+
+fn precedence(token_type: &TokenType) -> u8 {
+    use TokenType::*;
+
+    match token_type {
+        Or => 0,
+        And => 1,
+        EqualEqual | BangEqual => 2,
+        Greater | GreaterEqual | Less | LessEqual => 3,
+        Plus | Minus => 4,
+        _ => 5,
+    }
+}
@@ -0,0 +1,142 @@
+use super::*;
+
+use crate::utils::read_file;
+
+fn assert_formats(title: &str, src: &str, expected: &str) {
+    let formatted = format_source(src).unwrap_or_else(|e| panic!("{title}:\n{e}"));
+    assert_eq!(formatted, expected, "{title}");
+
+    let reformatted = format_source(&formatted).unwrap_or_else(|e| panic!("{title}:\n{e}"));
+    assert_eq!(reformatted, formatted, "{title} isn't stable");
+}
+
+/// Formatting the example twice should give the same result.
+fn assert_stable_file(file_name: &str) {
+    let path = format!("examples/{}.lox", file_name);
+    let src = read_file(path.as_str());
+
+    let formatted = format_source(&src).unwrap_or_else(|e| panic!("{path}:\n{e}"));
+    let reformatted = format_source(&formatted).unwrap_or_else(|e| panic!("{path}:\n{e}"));
+    assert_eq!(reformatted, formatted, "{path}");
+}
+
+#[test]
+fn formats_examples_stably() {
+    for example in [
+        "assignment",
+        "block_stmt",
+        "continue",
+        "for_stmt",
+        "fun_decl",
+        "if_else_stmt",
+        "interpolation",
+        "lambda",
+        "logic_or",
+        "strings",
+        "variables",
+        "while_stmt",
+    ] {
+        assert_stable_file(example);
+    }
+}
+
+#[test]
+fn formats_statements() {
+    assert_formats(
+        "statements",
+        "var   a=1;var b;\nif(a>b)print a;else{print b;}\nwhile(a<3)a=a+1;\nfun f(x,y){return;}",
+        "var a = 1;\nvar b;\nif (a > b) print a;\nelse {\n    print b;\n}\nwhile (a < 3) a = a + 1;\nfun f(x, y) {\n    return;\n}\n",
+    );
+}
+
+#[test]
+fn keeps_for_loops() {
+    assert_formats(
+        "for loops",
+        "for(var i=0;i<3;i=i+1){print i;}\nfor(;;){break;}\nfor(i=0;;)continue;",
+        "for (var i = 0; i < 3; i = i + 1) {\n    print i;\n}\nfor (;;) {\n    break;\n}\nfor (i = 0;;) continue;\n",
+    );
+}
+
+#[test]
+fn formats_else_if_chains() {
+    assert_formats(
+        "else if",
+        "if (a) { print 1; } else if (b) { print 2; } else { print 3; }",
+        "if (a) {\n    print 1;\n} else if (b) {\n    print 2;\n} else {\n    print 3;\n}\n",
+    );
+}
+
+#[test]
+fn keeps_comments() {
+    let src = "// Leading comment\nvar a = 1; // trailing\n\n\n/* block */\nfun f() {\n    // inside\n    print a;\n    // at the end\n}\n{}\n{ // alone\n}\n// last";
+
+    assert_formats(
+        "comments",
+        src,
+        "// Leading comment\nvar a = 1; // trailing\n\n/* block */\nfun f() {\n    // inside\n    print a;\n    // at the end\n}\n{}\n{ // alone\n}\n// last\n",
+    );
+}
+
+#[test]
+fn keeps_comments_in_lambdas() {
+    assert_formats(
+        "lambda comments",
+        "var f = fun () {\n// returns one\nreturn 1; };",
+        "var f = fun () {\n    // returns one\n    return 1;\n};\n",
+    );
+}
+
+#[test]
+fn keeps_literals_as_written() {
+    assert_formats(
+        "literals",
+        "print r\"C:\\dir\" + \"tab\\t\"+\"\"\"\n  multi\n  \"\"\";\nprint 1.50 + \"${ 1+2 } is ${\"three\"}\";",
+        "print r\"C:\\dir\" + \"tab\\t\" + \"\"\"\n  multi\n  \"\"\";\nprint 1.50 + \"${1 + 2} is ${\"three\"}\";\n",
+    );
+}
+
+#[test]
+fn formats_arrow_functions() {
+    assert_formats(
+        "arrow functions",
+        "var f=(a,b)=>a+b;\nvar g=()=>{print 1;};\nvar h = (x)=>(y)=>x*y;",
+        "var f = (a, b) => a + b;\nvar g = () => {\n    print 1;\n};\nvar h = (x) => (y) => x * y;\n",
+    );
+}
+
+#[test]
+fn wraps_long_calls() {
+    assert_formats(
+        "long call",
+        "print some_function(first_argument_is_long, second_argument_is_longer, third_one);",
+        "print some_function(\n    first_argument_is_long,\n    second_argument_is_longer,\n    third_one\n);\n",
+    );
+}
+
+#[test]
+fn wraps_long_binary_expressions() {
+    assert_formats(
+        "long binary",
+        "{ var total = first_value_in_the_sum + second_value_in_the_sum * 2 + third_value_in_the_sum; }",
+        "{\n    var total = first_value_in_the_sum\n        + second_value_in_the_sum * 2\n        + third_value_in_the_sum;\n}\n",
+    );
+}
+
+#[test]
+fn reports_parser_errors() {
+    assert!(matches!(format_source("print ;"), Err(LoxError::Parser(_))));
+    assert!(matches!(
+        format_source("print \"a;"),
+        Err(LoxError::Scan(_))
+    ));
+}
+
+#[test]
+fn keeps_type_annotations() {
+    assert_formats(
+        "annotations",
+        "var a:number?;var b : string=\"b\";\nfun f(x:number,y):fun(number):bool{return nil;}\nvar g=(x:any):string=>x;\nvar h=fun(x:(fun():nil)?):nil{};",
+        "var a: number?;\nvar b: string = \"b\";\nfun f(x: number, y): fun(number): bool {\n    return nil;\n}\nvar g = (x: any): string => x;\nvar h = fun (x: (fun(): nil)?): nil {};\n",
+    );
+}
@@ -1,60 +1,143 @@
 use crate::{
     ast::Stmt,
     callable::Callable,
-    interpreter::{error::RuntimeResult, Interpreter},
+    interpreter::{environment::Environment, error::RuntimeResult, Interpreter},
     lox_value::LoxValue,
+    token::Position,
+    types::Type,
 };
 
 use std::fmt;
+use std::rc::Rc;
 
 use parse_display::Display;
 
 #[derive(Debug, Display, Clone)]
 #[display("<fun {name}>")]
 pub struct FunDecl {
-    pub name: String,
-    pub params: Vec<String>,
+    pub name: Rc<str>,
+    pub params: Vec<Parameter>,
+    pub return_type: Option<Type>,
     pub body: Box<Stmt>,
+    /// The position of the function's name, or of 'fun' / '(' for anonymous functions.
+    pub position: Position,
 }
 
-pub type NativeFunction = fn(&mut Interpreter, &[LoxValue]) -> RuntimeResult<LoxValue>;
+impl FunDecl {
+    /// Returns the parameter list as it would be written in source, e.g. "a: number, b".
+    pub fn params_repr(&self) -> String {
+        self.params
+            .iter()
+            .map(|param| param.to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// The type of the function from its annotations, unannotated parts are `any`.
+    pub fn signature(&self) -> Type {
+        Type::Function {
+            params: self
+                .params
+                .iter()
+                .map(|param| param.annotation.clone().unwrap_or(Type::Any))
+                .collect(),
+            ret: Box::new(self.return_type.clone().unwrap_or(Type::Any)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name: Rc<str>,
+    pub annotation: Option<Type>,
+    pub position: Position,
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.annotation {
+            Some(annotation) => write!(f, "{}: {}", self.name, annotation),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// A function written in Rust, called with its arguments and where it's called from.
+pub type NativeFunction = fn(&mut Interpreter, &[LoxValue], Position) -> RuntimeResult<LoxValue>;
 
 #[derive(Display, Clone)]
 pub enum Function {
-    #[display("<native fun {}>")]
+    #[display("<native fun {name}>")]
     Native {
-        name: String,
+        name: Rc<str>,
         arity: usize,
         callable: NativeFunction,
     },
-    #[display("{0}")]
-    User(FunDecl),
+    #[display("{declaration}")]
+    User {
+        declaration: Rc<FunDecl>,
+        closure: Environment,
+    },
 }
 
+/// Natives are equal when they have the same name, user functions only when they're
+/// the same declaration closing over the same scope.
 impl PartialEq for Function {
     fn eq(&self, other: &Self) -> bool {
-        self.name() == other.name()
-    }
+        use Function::*;
 
-    fn ne(&self, other: &Self) -> bool {
-        self.name() != other.name()
+        match (self, other) {
+            (Native { name: l, .. }, Native { name: r, .. }) => l == r,
+            (
+                User {
+                    declaration: l,
+                    closure: l_closure,
+                },
+                User {
+                    declaration: r,
+                    closure: r_closure,
+                },
+            ) => Rc::ptr_eq(l, r) && l_closure.same_scope(r_closure),
+            _ => false,
+        }
     }
 }
 
 impl Function {
-    pub fn new_native_fun(
-        name: String,
-        arity: usize,
-        callable: fn(&mut Interpreter, &[LoxValue]) -> RuntimeResult<LoxValue>,
-    ) -> Function {
+    pub fn new_native_fun(name: String, arity: usize, callable: NativeFunction) -> Function {
         Function::Native {
-            name,
+            name: name.into(),
             arity,
             callable,
         }
     }
-    pub fn new_user_fun(decl: FunDecl) -> Function {
-        Function::User(decl)
+
+    /// Creates a user function that closes over `closure`.
+    pub fn new_user_fun(declaration: Rc<FunDecl>, closure: Environment) -> Function {
+        Function::User {
+            declaration,
+            closure,
+        }
+    }
+
+    /// Returns the parameter list as it would be written in source, natives' parameters
+    /// have no names and are shown as '_'.
+    pub fn params_repr(&self) -> String {
+        match self {
+            Function::Native { arity, .. } => vec!["_"; *arity].join(", "),
+            Function::User { declaration, .. } => declaration.params_repr(),
+        }
+    }
+
+    /// The type of the function, natives take and return `any`.
+    pub fn type_of(&self) -> Type {
+        match self {
+            Function::Native { arity, .. } => Type::Function {
+                params: vec![Type::Any; *arity],
+                ret: Box::new(Type::Any),
+            },
+            Function::User { declaration, .. } => declaration.signature(),
+        }
     }
 }
 
@@ -63,12 +146,8 @@ impl Callable for Function {
         use Function::*;
 
         match self {
-            Native {
-                name,
-                arity: _,
-                callable: _,
-            } => name.to_owned(),
-            User(decl) => decl.name.to_owned(),
+            Native { name, .. } => name.to_string(),
+            User { declaration, .. } => declaration.name.to_string(),
         }
     }
 
@@ -76,39 +155,38 @@ impl Callable for Function {
         use Function::*;
 
         match self {
-            Native {
-                name: _,
-                arity,
-                callable: _,
-            } => arity.to_owned(),
-            User(decl) => decl.params.len(),
+            Native { arity, .. } => arity.to_owned(),
+            User { declaration, .. } => declaration.params.len(),
         }
     }
 
-    fn call(&self, interpreter: &mut Interpreter, args: &[LoxValue]) -> RuntimeResult<LoxValue> {
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        args: &[LoxValue],
+        position: Position,
+    ) -> RuntimeResult<LoxValue> {
         use Function::*;
 
         match self {
-            Native {
-                name: _,
-                arity: _,
-                callable,
-            } => callable(interpreter, args),
-            User(decl) => {
-                // Create new environment
-                interpreter.environment.begin_scope();
-                for (param, arg) in decl.params.iter().zip(args.into_iter().cloned()) {
-                    interpreter.environment.define(param, arg);
+            Native { callable, .. } => callable(interpreter, args, position),
+            User {
+                declaration,
+                closure,
+            } => {
+                // The body runs in a new scope on top of the environment the function was declared in.
+                let mut environment = closure.clone();
+                environment.begin_scope();
+                for (param, arg) in declaration.params.iter().zip(args.iter().cloned()) {
+                    environment.define(param.name.clone(), arg);
                 }
+                interpreter.allocated();
 
-                let res = interpreter.execute(&decl.body, false, true)?;
-                interpreter.environment.end_scope();
+                let previous = std::mem::replace(&mut interpreter.environment, environment);
+                let res = interpreter.execute(&declaration.body, false, true);
+                interpreter.environment = previous;
 
-                if let Some(value) = res {
-                    Ok(value)
-                } else {
-                    Ok(LoxValue::Nil)
-                }
+                Ok(res?.unwrap_or(LoxValue::Nil))
             }
         }
     }
@@ -118,15 +196,11 @@ impl fmt::Debug for Function {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use Function::*;
         match self {
-            Native {
-                name,
-                arity: _,
-                callable: _,
-            } => {
+            Native { name, .. } => {
                 write!(f, "<native fun {name}>")
             }
-            User(decl) => {
-                write!(f, "<user fun {}>", &decl.name)
+            User { declaration, .. } => {
+                write!(f, "<user fun {}>", &declaration.name)
             }
         }
     }
@@ -0,0 +1,126 @@
+//! Runs the programs in `tests/lox` and compares what they print with the
+//! comments in them, as in the Crafting Interpreters test suite.
+//!
+//! A line ending in `// expect: <text>` expects the program to print `<text>`
+//! as its next line of output, `// expect runtime error: <message>` expects it
+//! to stop with that error after printing everything expected before it.
+
+use std::path::{Path, PathBuf};
+
+use super::*;
+use crate::utils::{line_diff, Output};
+
+const DIRECTORY: &str = "tests/lox";
+
+/// What a program printed, followed by the error it stopped with.
+#[derive(Debug, Default, PartialEq)]
+struct Outcome {
+    output: Vec<String>,
+    error: Option<String>,
+}
+
+impl Outcome {
+    /// Returns the outcome the comments of a program expect.
+    fn expected(source: &str) -> Outcome {
+        let mut expected = Outcome::default();
+
+        for line in source.lines() {
+            if let Some((_, text)) = line.split_once("// expect: ") {
+                expected.output.push(text.to_string());
+            } else if let Some((_, message)) = line.split_once("// expect runtime error: ") {
+                expected.error = Some(message.to_string());
+            }
+        }
+
+        expected
+    }
+
+    fn of_running(source: &str, gc: GcConfig) -> Outcome {
+        let output = Output::default();
+        let mut interpreter = Interpreter::new();
+        interpreter.set_output(Box::new(output.clone()));
+        interpreter.set_gc_config(gc);
+
+        let error = interpreter.interpret_str(source).err().map(|e| match e {
+            LoxError::Scan(e) => e.to_string(),
+            LoxError::Parser(e) => e.to_string(),
+            LoxError::Runtime(e) => e.to_string(),
+            LoxError::IO(e) => e.to_string(),
+        });
+
+        let output = output.text();
+        Outcome {
+            output: output.lines().map(str::to_string).collect(),
+            error,
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let error = self.error.iter().map(|error| format!("error: {error}"));
+        self.output.iter().cloned().chain(error).collect()
+    }
+}
+
+/// Returns the `.lox` files in a directory and its subdirectories, sorted by path.
+fn lox_files(directory: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    for entry in std::fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(lox_files(&path));
+        } else if path.extension().is_some_and(|extension| extension == "lox") {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    files
+}
+
+/// Shows the expected and actual lines side by side, marking the lines that differ.
+fn diff(expected: &Outcome, actual: &Outcome) -> String {
+    let (expected, actual) = (expected.lines(), actual.lines());
+    let expected: Vec<&str> = expected.iter().map(String::as_str).collect();
+    let actual: Vec<&str> = actual.iter().map(String::as_str).collect();
+
+    format!("(- expected, + actual)\n{}", line_diff(&expected, &actual))
+}
+
+/// Runs every program, panicking with the differences for those that don't do what they expect.
+fn check_programs(gc: GcConfig) {
+    let files = lox_files(Path::new(DIRECTORY));
+    assert!(!files.is_empty(), "There are no programs in {DIRECTORY}");
+
+    let mut failures = Vec::new();
+    for file in &files {
+        let source = std::fs::read_to_string(file).unwrap();
+        let expected = Outcome::expected(&source);
+        let actual = Outcome::of_running(&source, gc);
+
+        if expected != actual {
+            failures.push(format!("{}:\n{}", file.display(), diff(&expected, &actual)));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} programs didn't do what they expect:\n\n{}",
+        failures.len(),
+        files.len(),
+        failures.join("\n\n")
+    );
+}
+
+#[test]
+fn matches_expected_output() {
+    check_programs(GcConfig::default());
+}
+
+#[test]
+fn matches_expected_output_when_collecting_at_every_allocation() {
+    check_programs(GcConfig {
+        stress: true,
+        ..GcConfig::default()
+    });
+}
@@ -1,70 +1,355 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::{Rc, Weak};
 
+use crate::function::Function;
 use crate::lox_value::LoxValue;
 
-use super::globals::_print;
+/// Where the resolver found a variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    /// A local in the scope `distance` scopes above the current one.
+    Local { distance: usize, index: usize },
+    /// A global, at this index of the global scope.
+    Global(usize),
+}
 
+/// A handle to a chain of scopes.
+///
+/// Cloning an `Environment` is cheap and yields a handle to the same scopes,
+/// which is how closures capture the environment they were declared in.
+///
+/// Variables live in slots numbered in the order they're defined in, which is
+/// the order the resolver declares them in, so they're read without looking
+/// their names up.
 #[derive(Debug, Clone)]
 pub struct Environment {
-    scopes: Vec<HashMap<String, LoxValue>>,
+    scope: Rc<RefCell<Scope>>,
+}
+
+/// A handle to a scope that doesn't keep it alive, for the heap to track scopes with.
+#[derive(Debug, Clone)]
+pub(super) struct WeakEnvironment(Weak<RefCell<Scope>>);
+
+impl WeakEnvironment {
+    pub fn upgrade(&self) -> Option<Environment> {
+        self.0.upgrade().map(|scope| Environment { scope })
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.0.strong_count() > 0
+    }
+}
+
+#[derive(Debug, Default)]
+struct Scope {
+    /// The names of the slots, a local declared twice in the same scope gets two slots.
+    names: Vec<Rc<str>>,
+    /// The values of the slots, `None` for globals that are used before they're defined.
+    values: Vec<Option<LoxValue>>,
+    /// The slots by name, only kept for the global scope where globals can be
+    /// redefined and are given their slots by name.
+    slots: HashMap<Rc<str>, usize>,
+    enclosing: Option<Environment>,
+    /// Whether the heap tracks the scope.
+    tracked: bool,
+}
+
+impl Scope {
+    /// Returns the slot of the last variable with this name.
+    fn slot(&self, name: &str) -> Option<usize> {
+        if self.enclosing.is_none() {
+            return self.slots.get(name).copied();
+        }
+
+        self.names
+            .iter()
+            .rposition(|slot_name| **slot_name == *name)
+    }
+
+    /// Returns the defined names and values, sorted by name, with only the last
+    /// of the slots that share a name.
+    fn values(&self) -> Vec<(String, LoxValue)> {
+        let mut values: Vec<(String, LoxValue)> = Vec::new();
+
+        for (name, value) in self.names.iter().zip(&self.values).rev() {
+            if let Some(value) = value {
+                if !values.iter().any(|(seen, _)| **seen == **name) {
+                    values.push((name.to_string(), value.clone()));
+                }
+            }
+        }
+        values.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        values
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Environment {
     pub fn new() -> Environment {
         Environment {
-            scopes: vec![HashMap::new()],
+            scope: Rc::new(RefCell::new(Scope::default())),
         }
     }
 
     pub fn begin_scope(&mut self) {
-        self.scopes.push(HashMap::new());
+        let scope = Scope {
+            enclosing: Some(self.clone()),
+            ..Scope::default()
+        };
+        self.scope = Rc::new(RefCell::new(scope));
     }
 
     pub fn end_scope(&mut self) {
-        if self.scopes.len() < 2 {
-            panic!("You can't end the global scope dumbass")
+        let enclosing = self.scope.borrow().enclosing.clone();
+
+        match enclosing {
+            Some(enclosing) => *self = enclosing,
+            None => panic!("You can't end the global scope dumbass"),
         }
-        self.scopes.pop();
     }
 
-    pub fn get(&self, name: &str) -> Option<LoxValue> {
-        for scope in self.scopes.iter().rev() {
-            if scope.contains_key(name) {
-                return scope.get(name).map(|v| v.to_owned());
+    /// Returns the environment `distance` scopes above this one.
+    fn ancestor(&self, distance: usize) -> Environment {
+        let mut environment = self.clone();
+
+        for _ in 0..distance {
+            let enclosing = environment
+                .scope
+                .borrow()
+                .enclosing
+                .clone()
+                .expect("The resolver computed a distance deeper than the environment");
+            environment = enclosing;
+        }
+
+        environment
+    }
+
+    /// Whether both handles are to the same innermost scope.
+    pub fn same_scope(&self, other: &Environment) -> bool {
+        Rc::ptr_eq(&self.scope, &other.scope)
+    }
+
+    /// Returns the names defined in the innermost scope.
+    pub fn names(&self) -> Vec<String> {
+        self.scope
+            .borrow()
+            .values()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Returns every name visible from this scope with its value, inner scopes first.
+    /// Names shadowed by an inner scope only appear once, with the inner value.
+    pub fn bindings(&self) -> Vec<(String, LoxValue)> {
+        let mut bindings: Vec<(String, LoxValue)> = Vec::new();
+        let mut environment = Some(self.clone());
+
+        while let Some(current) = environment {
+            let scope = current.scope.borrow();
+
+            for (name, value) in scope.values() {
+                if !bindings.iter().any(|(seen, _)| *seen == name) {
+                    bindings.push((name, value));
+                }
             }
+
+            environment = scope.enclosing.clone();
+        }
+
+        bindings
+    }
+
+    /// Returns the names and values defined in each scope from this one outwards, sorted
+    /// by name. The global scope, the one without an enclosing scope, isn't included.
+    pub fn local_scopes(&self) -> Vec<Vec<(String, LoxValue)>> {
+        self.local_environments()
+            .iter()
+            .map(|environment| environment.scope.borrow().values())
+            .collect()
+    }
+
+    /// Returns the names of the slots of each scope from this one outwards, in slot
+    /// order, so that code resolved to run in this environment finds them.
+    pub fn local_slot_names(&self) -> Vec<Vec<Rc<str>>> {
+        self.local_environments()
+            .iter()
+            .map(|environment| environment.scope.borrow().names.clone())
+            .collect()
+    }
+
+    fn local_environments(&self) -> Vec<Environment> {
+        let mut environments = Vec::new();
+        let mut environment = self.clone();
+
+        loop {
+            let enclosing = environment.scope.borrow().enclosing.clone();
+            let Some(enclosing) = enclosing else {
+                break;
+            };
+
+            environments.push(environment);
+            environment = enclosing;
         }
 
-        None
+        environments
     }
 
-    pub fn get_at(&self, name: &str, depth: usize) -> Option<LoxValue> {
-        self.scopes[depth].get(name).map(|v| v.to_owned())
+    /// Looks a variable up by name, for code that wasn't resolved.
+    pub fn get(&self, name: &str) -> Option<LoxValue> {
+        let scope = self.scope.borrow();
+
+        match scope.slot(name) {
+            Some(slot) => scope.values[slot].clone(),
+            None => scope.enclosing.as_ref().and_then(|e| e.get(name)),
+        }
+    }
+
+    pub fn get_at(&self, distance: usize, index: usize) -> Option<LoxValue> {
+        if distance == 0 {
+            return self.scope.borrow().values.get(index).cloned().flatten();
+        }
+
+        self.ancestor(distance)
+            .scope
+            .borrow()
+            .values
+            .get(index)
+            .cloned()
+            .flatten()
     }
 
-    pub fn assign_at(&mut self, name: &str, value: LoxValue, depth: usize) -> Option<()> {
-        if self.scopes[depth].contains_key(name) {
-            self.scopes[depth].insert(name.to_string(), value);
-            Some(())
-        } else {
-            None
+    pub fn assign_at(&mut self, distance: usize, index: usize, value: LoxValue) -> Option<()> {
+        let ancestor = self.ancestor(distance);
+        let mut scope = ancestor.scope.borrow_mut();
+
+        match scope.values.get_mut(index) {
+            Some(slot @ Some(_)) => {
+                *slot = Some(value);
+                Some(())
+            }
+            _ => None,
         }
     }
 
-    pub fn define(&mut self, name: &str, initializer: LoxValue) {
-        self.scopes
-            .last_mut()
-            .unwrap()
-            .insert(name.to_string(), initializer);
+    /// Defines a variable in a new slot, or in the slot a global already has.
+    pub fn define(&mut self, name: impl Into<Rc<str>>, initializer: LoxValue) {
+        let name = name.into();
+
+        if self.scope.borrow().enclosing.is_none() {
+            let slot = self.global_slot(&name);
+            self.scope.borrow_mut().values[slot] = Some(initializer);
+            return;
+        }
+
+        let mut scope = self.scope.borrow_mut();
+        scope.names.push(name);
+        scope.values.push(Some(initializer));
     }
 
+    /// Returns the slot of a global, giving it one if it doesn't have one yet.
+    /// Call this on the global scope, slots are never taken away.
+    pub fn global_slot(&self, name: &str) -> usize {
+        let mut scope = self.scope.borrow_mut();
+
+        if let Some(slot) = scope.slots.get(name) {
+            return *slot;
+        }
+
+        let slot = scope.values.len();
+        let name: Rc<str> = Rc::from(name);
+        scope.names.push(name.clone());
+        scope.values.push(None);
+        scope.slots.insert(name, slot);
+
+        slot
+    }
+
+    /// Assigns to a variable by name, for code that wasn't resolved.
     pub fn assign(&mut self, name: &str, value: LoxValue) -> Option<()> {
-        for scope in self.scopes.iter_mut().rev() {
-            if scope.contains_key(name) {
-                scope.insert(name.to_string(), value);
-                return Some(());
+        let mut scope = self.scope.borrow_mut();
+
+        match scope.slot(name) {
+            Some(slot) if scope.values[slot].is_some() => {
+                scope.values[slot] = Some(value);
+                Some(())
             }
+            Some(_) => None,
+            None => match scope.enclosing.as_mut() {
+                Some(enclosing) => enclosing.assign(name, value),
+                None => None,
+            },
         }
+    }
+
+    fn downgrade(&self) -> WeakEnvironment {
+        WeakEnvironment(Rc::downgrade(&self.scope))
+    }
+
+    /// Marks the innermost scope and the ones enclosing it as tracked by the heap, returns
+    /// the ones that weren't already. The global scope is never tracked.
+    pub(super) fn track(&self) -> Vec<WeakEnvironment> {
+        let mut untracked = Vec::new();
+        let mut environment = self.clone();
+
+        loop {
+            let enclosing = {
+                let mut scope = environment.scope.borrow_mut();
+                match scope.enclosing.clone() {
+                    Some(enclosing) if !scope.tracked => {
+                        scope.tracked = true;
+                        enclosing
+                    }
+                    _ => break,
+                }
+            };
+
+            untracked.push(environment.downgrade());
+            environment = enclosing;
+        }
+
+        untracked
+    }
+
+    /// Identifies the innermost scope, handles to the same scope have the same id.
+    pub(super) fn id(&self) -> usize {
+        Rc::as_ptr(&self.scope) as usize
+    }
+
+    /// The number of handles to the innermost scope.
+    pub(super) fn handles(&self) -> usize {
+        Rc::strong_count(&self.scope)
+    }
+
+    /// Calls `f` with every handle the innermost scope holds, to its enclosing scope and
+    /// in the closures of its functions. Returns `false` if the scope is being changed.
+    pub(super) fn for_each_reference(&self, mut f: impl FnMut(&Environment)) -> bool {
+        let Ok(scope) = self.scope.try_borrow() else {
+            return false;
+        };
+
+        scope.enclosing.iter().for_each(&mut f);
+        for value in scope.values.iter().flatten() {
+            if let LoxValue::Function(Function::User { closure, .. }) = value {
+                f(closure);
+            }
+        }
+
+        true
+    }
 
-        None
+    /// Empties the innermost scope, dropping its variables and its enclosing scope.
+    pub(super) fn clear(&self) {
+        // Dropped once the scope isn't borrowed, freeing it can free the scopes it held.
+        let scope = std::mem::take(&mut *self.scope.borrow_mut());
+        drop(scope);
     }
 }
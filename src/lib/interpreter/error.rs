@@ -14,7 +14,7 @@ pub enum RuntimeError {
     IncorrectArity { name: String, position: Position },
     #[error("Type '{type_name}' is not callable, on line {position}.")]
     NotCallable {
-        type_name: LoxValue,
+        type_name: Box<LoxValue>,
         position: Position,
     },
     #[error("")]
@@ -29,6 +29,32 @@ pub enum RuntimeError {
     InvalidReturn(Position),
     #[error("var '{0}' is being used in it's initializer, {1}.")]
     VarUsedInOwnInitializer(String, Position),
+    #[error("Execution was stopped by the debugger, {0}.")]
+    Stopped(Position),
+    #[error("Assertion failed, {position}: {message}")]
+    AssertionFailed { message: String, position: Position },
+}
+
+impl RuntimeError {
+    /// Returns where the error happened, control flow signals have no position.
+    pub fn position(&self) -> Option<Position> {
+        use RuntimeError::*;
+
+        match self {
+            Generic(_, p)
+            | DivisionByZero(p)
+            | InvalidContinue(p)
+            | InvalidBreak(p)
+            | InvalidReturn(p)
+            | VarUsedInOwnInitializer(_, p)
+            | Stopped(p) => Some(*p),
+            VarDoesNotExist { position, .. }
+            | IncorrectArity { position, .. }
+            | NotCallable { position, .. }
+            | AssertionFailed { position, .. } => Some(*position),
+            ValidContinue | ValidBreak => None,
+        }
+    }
 }
 
 pub type RuntimeResult<T> = Result<T, RuntimeError>;
@@ -1,13 +1,30 @@
 use crate::lox_value::LoxValue;
+use crate::token::Position;
 
 use crate::interpreter::{error::RuntimeResult, Interpreter};
 
-
-pub fn clock(_interpreter: &mut Interpreter, _args: &[LoxValue]) -> RuntimeResult<LoxValue> {
+pub fn clock(
+    _interpreter: &mut Interpreter,
+    _args: &[LoxValue],
+    _position: Position,
+) -> RuntimeResult<LoxValue> {
     Ok(LoxValue::Number(13124312.0))
 }
 
-pub fn _print(_interpreter: &mut Interpreter, args: &[LoxValue]) -> RuntimeResult<LoxValue> {
+/// Collects garbage, returns how many scopes were freed.
+pub fn gc(
+    interpreter: &mut Interpreter,
+    _args: &[LoxValue],
+    _position: Position,
+) -> RuntimeResult<LoxValue> {
+    Ok(LoxValue::Number(interpreter.collect_garbage() as f64))
+}
+
+pub fn _print(
+    _interpreter: &mut Interpreter,
+    args: &[LoxValue],
+    _position: Position,
+) -> RuntimeResult<LoxValue> {
     println!("{}", args[0]);
     Ok(LoxValue::Nil)
-}
\ No newline at end of file
+}
@@ -0,0 +1,183 @@
+//! Frees the scopes that only keep each other alive.
+//!
+//! Scopes are reference counted, so most of them are freed as soon as the code
+//! running in them is done. A function declared in a scope keeps that scope
+//! alive as its closure while the scope keeps the function alive as one of its
+//! variables, and counting never frees such a cycle. The heap tracks the scopes
+//! closures capture and the ones enclosing them, the only scopes that can end up
+//! in a cycle, and every so often marks the ones that can still be reached and
+//! empties the others, which breaks their cycles.
+//!
+//! The roots are the interpreter's current and global environments, and any
+//! scope with more handles than the tracked scopes account for: those are held
+//! by Rust code, like the environments of the calls below the current one or a
+//! function being called.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::environment::{Environment, WeakEnvironment};
+
+/// When the heap collects garbage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GcConfig {
+    /// Collects once the scopes that survived the last collection and those
+    /// tracked since reach this many.
+    pub threshold: usize,
+    /// After a collection, the next one waits until there are this many times
+    /// the scopes that survived, if that's more than `threshold`.
+    pub growth: usize,
+    /// Collects every time a scope is created or captured, to find scopes freed
+    /// while they're still used.
+    pub stress: bool,
+}
+
+impl Default for GcConfig {
+    fn default() -> Self {
+        GcConfig {
+            threshold: 1024,
+            growth: 2,
+            stress: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GcStats {
+    pub collections: usize,
+    /// The scopes tracked so far.
+    pub tracked: usize,
+    /// The scopes freed by collections, the others are freed when they're no longer used.
+    pub freed: usize,
+    /// The tracked scopes still alive.
+    pub live: usize,
+}
+
+impl fmt::Display for GcStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} collections, {} scopes tracked, {} freed by the collector, {} live",
+            self.collections, self.tracked, self.freed, self.live
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct Heap {
+    scopes: Vec<WeakEnvironment>,
+    config: GcConfig,
+    /// How many scopes have to be tracked for the next collection to run.
+    next_collection: usize,
+    stats: GcStats,
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new(GcConfig::default())
+    }
+}
+
+impl Heap {
+    pub fn new(config: GcConfig) -> Heap {
+        Heap {
+            scopes: Vec::new(),
+            config,
+            next_collection: config.threshold,
+            stats: GcStats::default(),
+        }
+    }
+
+    pub fn set_config(&mut self, config: GcConfig) {
+        self.config = config;
+        self.next_collection = config.threshold;
+    }
+
+    pub fn config(&self) -> GcConfig {
+        self.config
+    }
+
+    pub fn stats(&self) -> GcStats {
+        GcStats {
+            live: self.scopes.iter().filter(|scope| scope.is_alive()).count(),
+            ..self.stats
+        }
+    }
+
+    /// Tracks a scope a closure captures and the ones enclosing it, returns whether
+    /// it's time to collect.
+    pub fn track(&mut self, environment: &Environment) -> bool {
+        let untracked = environment.track();
+        self.stats.tracked += untracked.len();
+        self.scopes.extend(untracked);
+
+        self.config.stress || self.scopes.len() >= self.next_collection
+    }
+
+    /// Empties the tracked scopes that can't be reached from the roots, returns how many
+    /// there were.
+    pub fn collect(&mut self, roots: &[&Environment]) -> usize {
+        let scopes: Vec<Environment> = self
+            .scopes
+            .iter()
+            .filter_map(WeakEnvironment::upgrade)
+            .collect();
+        let indexes: HashMap<usize, usize> = scopes
+            .iter()
+            .enumerate()
+            .map(|(index, scope)| (scope.id(), index))
+            .collect();
+
+        // The handles tracked scopes hold to each other, a scope with more is held from
+        // outside and is a root. `scopes` holds one more.
+        let mut held = vec![0; scopes.len()];
+        for scope in &scopes {
+            scope.for_each_reference(|reference| {
+                if let Some(index) = indexes.get(&reference.id()) {
+                    held[*index] += 1;
+                }
+            });
+        }
+        let mut reachable: Vec<bool> = scopes
+            .iter()
+            .zip(&held)
+            .map(|(scope, held)| scope.handles() > held + 1)
+            .collect();
+
+        let mut pending: Vec<usize> = (0..scopes.len()).filter(|i| reachable[*i]).collect();
+        let mut mark = |reference: &Environment, pending: &mut Vec<usize>| {
+            if let Some(&index) = indexes.get(&reference.id()) {
+                if !reachable[index] {
+                    reachable[index] = true;
+                    pending.push(index);
+                }
+            }
+        };
+        for root in roots {
+            mark(root, &mut pending);
+            root.for_each_reference(|reference| mark(reference, &mut pending));
+        }
+        while let Some(index) = pending.pop() {
+            scopes[index].for_each_reference(|reference| mark(reference, &mut pending));
+        }
+
+        let mut freed = 0;
+        for (scope, reachable) in scopes.iter().zip(&reachable) {
+            if !reachable {
+                scope.clear();
+                freed += 1;
+            }
+        }
+        drop(scopes);
+
+        self.scopes.retain(WeakEnvironment::is_alive);
+        self.next_collection = self
+            .config
+            .threshold
+            .max(self.scopes.len() * self.config.growth);
+        self.stats.collections += 1;
+        self.stats.freed += freed;
+
+        freed
+    }
+}
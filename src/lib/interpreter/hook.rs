@@ -0,0 +1,60 @@
+use std::fmt;
+
+use crate::{ast::Stmt, lox_value::LoxValue, token::Position};
+
+use super::{error::RuntimeResult, Interpreter};
+
+/// Watches a program run, e.g. a debugger pausing at breakpoints or a tracer.
+///
+/// A hook is attached with `Interpreter::set_hook`, without one the interpreter
+/// only checks that there's none. The hook is taken out of the interpreter while
+/// it runs so it can use the interpreter, code it runs isn't seen by the hook.
+pub trait Hook {
+    /// Called before a statement is executed, an error stops the program.
+    fn statement(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        _statement: &Stmt,
+    ) -> RuntimeResult<()> {
+        Ok(())
+    }
+
+    /// Called when a function is called at `position`, before its body runs.
+    fn enter(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        _name: &str,
+        _arguments: &[LoxValue],
+        _position: Position,
+    ) {
+    }
+
+    /// Called when a variable is declared or assigned a value at `position`.
+    fn assign(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        _name: &str,
+        _value: &LoxValue,
+        _position: Position,
+    ) {
+    }
+
+    /// Called when an `if`, a ternary or an `and` / `or` at `position` picks a branch,
+    /// `condition` is whether its condition or left side was truthy.
+    fn branch(&mut self, _interpreter: &mut Interpreter, _position: Position, _condition: bool) {}
+
+    /// Called when a function call finishes, whether it returned or failed.
+    fn exit(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        _name: &str,
+        _result: &RuntimeResult<LoxValue>,
+    ) {
+    }
+}
+
+impl fmt::Debug for dyn Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<hook>")
+    }
+}
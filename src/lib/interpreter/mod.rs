@@ -1,48 +1,147 @@
 pub mod environment;
 pub mod error;
+pub mod heap;
+pub mod hook;
 
+#[cfg(test)]
+mod conformance;
 mod globals;
-mod resolver;
+pub mod resolver;
 #[cfg(test)]
 mod tests;
 
-use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::rc::Rc;
 
 use crate::ast::{Expr, Stmt};
 use crate::error::{LoxError, LoxResult};
-use crate::function::Function;
+use crate::function::{FunDecl, Function, NativeFunction};
 use crate::interpreter::resolver::Resolver;
 use crate::lox_value::LoxValue;
 use crate::parser::Parser;
 use crate::token::Position;
 use crate::token_type::TokenType;
 
-use self::environment::Environment;
+use self::environment::{Environment, Slot};
 use self::error::{RuntimeError, RuntimeResult};
+use self::heap::{GcConfig, GcStats, Heap};
+use self::hook::Hook;
 
-#[derive(Debug)]
 pub struct Interpreter {
     pub environment: Environment,
     pub globals: Environment,
-    pub locals: HashMap<Position, usize>,
+    /// Tracks the scopes closures capture, to free the ones that only keep each other alive.
+    heap: Heap,
+    hook: Option<Box<dyn Hook>>,
+    /// Where `print` writes to, stdout unless it's changed with `set_output`.
+    output: Box<dyn Write>,
+}
+
+impl fmt::Debug for Interpreter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interpreter")
+            .field("environment", &self.environment)
+            .field("globals", &self.globals)
+            .field("heap", &self.heap)
+            .field("hook", &self.hook)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Interpreter {
-        let mut globals = Environment::new();
-
-        globals.define(
-            "clock",
-            LoxValue::Function(Function::new_native_fun(
-                "clock".to_string(),
-                0,
-                globals::clock,
-            )),
-        );
-        Interpreter {
-            environment: Environment::new(),
+        let globals = Environment::new();
+
+        let mut interpreter = Interpreter {
+            environment: globals.clone(),
             globals,
-            locals: HashMap::new(),
+            heap: Heap::default(),
+            hook: None,
+            output: Box::new(io::stdout()),
+        };
+        interpreter.define_native("clock", 0, globals::clock);
+        interpreter.define_native("gc", 0, globals::gc);
+
+        interpreter
+    }
+
+    /// Defines a global native function, e.g. the assertions of the test runner.
+    pub fn define_native(&mut self, name: &str, arity: usize, callable: NativeFunction) {
+        let function = Function::new_native_fun(name.to_string(), arity, callable);
+        self.globals.define(name, LoxValue::Function(function));
+    }
+
+    /// Makes `print` write to `output` instead of stdout.
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    /// Changes when garbage is collected.
+    pub fn set_gc_config(&mut self, config: GcConfig) {
+        self.heap.set_config(config);
+    }
+
+    pub fn gc_stats(&self) -> GcStats {
+        self.heap.stats()
+    }
+
+    /// Frees the scopes that only keep each other alive, returns how many were freed.
+    pub fn collect_garbage(&mut self) -> usize {
+        self.heap.collect(&[&self.environment, &self.globals])
+    }
+
+    /// Creates a function closing over the current environment, which the heap then tracks.
+    fn closure(&mut self, declaration: &Rc<FunDecl>) -> Function {
+        if self.heap.track(&self.environment) {
+            self.collect_garbage();
+        }
+
+        Function::new_user_fun(declaration.clone(), self.environment.clone())
+    }
+
+    /// Called whenever a scope is created, collects garbage in stress mode.
+    pub(crate) fn allocated(&mut self) {
+        if self.heap.config().stress {
+            self.collect_garbage();
+        }
+    }
+
+    /// Starts a new innermost scope.
+    fn begin_scope(&mut self) {
+        self.environment.begin_scope();
+        self.allocated();
+    }
+
+    /// Attaches a hook that sees every statement, function call and assignment.
+    pub fn set_hook(&mut self, hook: Box<dyn Hook>) {
+        self.hook = Some(hook);
+    }
+
+    /// Detaches the hook, if there's one.
+    pub fn take_hook(&mut self) -> Option<Box<dyn Hook>> {
+        self.hook.take()
+    }
+
+    /// Runs `f` with the hook if there's one.
+    fn with_hook<T>(&mut self, f: impl FnOnce(&mut dyn Hook, &mut Interpreter) -> T) -> Option<T> {
+        let mut hook = self.hook.take()?;
+        let result = f(hook.as_mut(), self);
+        self.hook = Some(hook);
+
+        Some(result)
+    }
+
+    /// Tells the hook, if there's one, which branch was picked at `position`.
+    fn branch(&mut self, position: Position, condition: bool) {
+        if self.hook.is_some() {
+            self.with_hook(|hook, interpreter| hook.branch(interpreter, position, condition));
         }
     }
 
@@ -55,46 +154,50 @@ impl Interpreter {
         match expr {
             Value { value, position: _ } => Ok(value.to_owned()),
             Grouping(inner_expr, _position) => self.evaluate(inner_expr),
-            Expr::Identifier(name, position) => {
-                let res = if let Some(depth) = self.locals.get(position) {
-                    if let Some(e) = self.environment.get_at(name, depth.to_owned()) {
-                        return Ok(e);
-                    } else {
-                        Err(RuntimeError::VarDoesNotExist {
-                            name: name.to_owned(),
-                            position: position.to_owned(),
-                        })
-                    }
-                } else {
-                    if let Some(e) = self.globals.get(name) {
-                        return Ok(e);
-                    } else {
-                        Err(RuntimeError::VarDoesNotExist {
-                            name: name.to_owned(),
-                            position: position.to_owned(),
-                        })
+            Expr::Identifier(name, position, slot) => {
+                let value = match &slot.get() {
+                    Some(Slot::Local { distance, index }) => {
+                        self.environment.get_at(*distance, *index)
                     }
+                    Some(Slot::Global(index)) => self.globals.get_at(0, *index),
+                    None => self.globals.get(name),
                 };
-                res
+
+                value.ok_or_else(|| RuntimeError::VarDoesNotExist {
+                    name: name.to_string(),
+                    position: position.to_owned(),
+                })
             }
             Assignment {
                 name,
                 value,
                 position,
+                slot,
             } => {
-                let previous = self.environment.get(&name);
+                let value = self.evaluate(value.as_ref())?;
 
-                if previous.is_none() {
-                    Err(RuntimeError::VarDoesNotExist {
-                        name: name.to_owned(),
+                let assigned = match &slot.get() {
+                    Some(Slot::Local { distance, index }) => {
+                        self.environment.assign_at(*distance, *index, value.clone())
+                    }
+                    Some(Slot::Global(index)) => self.globals.assign_at(0, *index, value.clone()),
+                    None => self.globals.assign(name, value.clone()),
+                };
+
+                if assigned.is_none() {
+                    return Err(RuntimeError::VarDoesNotExist {
+                        name: name.to_string(),
                         position: position.to_owned(),
-                    })
-                } else {
-                    let value = self.evaluate(value.as_ref())?;
-                    self.environment.assign(name.as_str(), value);
+                    });
+                }
 
-                    Ok(LoxValue::Nil)
+                if self.hook.is_some() {
+                    self.with_hook(|hook, interpreter| {
+                        hook.assign(interpreter, name, &value, *position)
+                    });
                 }
+
+                Ok(value)
             }
             Unary {
                 op,
@@ -122,9 +225,22 @@ impl Interpreter {
                 lhs,
                 op,
                 rhs,
-                position: _,
+                position,
             } => {
-                let (lhs, rhs) = (self.evaluate(lhs)?, self.evaluate(rhs)?);
+                let lhs = self.evaluate(lhs)?;
+
+                // Logical operators only evaluate their right side when the left doesn't decide.
+                if let And | Or = op.token_type {
+                    let truthy = lhs.is_truthy();
+                    self.branch(*position, truthy);
+
+                    if truthy == (op.token_type == Or) {
+                        return Ok(LoxValue::Boolean(truthy));
+                    }
+                    return Ok(LoxValue::Boolean(self.evaluate(rhs)?.is_truthy()));
+                }
+
+                let rhs = self.evaluate(rhs)?;
 
                 match (&op.token_type, lhs, rhs) {
                     // Arithmetic Operators
@@ -140,21 +256,9 @@ impl Interpreter {
                         Ok(LoxValue::Number(l / r))
                     }
 
-                    // Logical Operators
-                    (And, left, right) => {
-                        Ok(LoxValue::Boolean(left.is_truthy() && right.is_truthy()))
-                    }
-                    (Or, left, right) => {
-                        Ok(LoxValue::Boolean(left.is_truthy() || right.is_truthy()))
-                    }
-
                     // Comparison Operators
-                    (EqualEqual, LoxValue::Number(l), LoxValue::Number(r)) => {
-                        Ok(LoxValue::Boolean(l == r))
-                    }
-                    (BangEqual, LoxValue::Number(l), LoxValue::Number(r)) => {
-                        Ok(LoxValue::Boolean(l != r))
-                    }
+                    (EqualEqual, l, r) => Ok(LoxValue::Boolean(l.is_equal(&r))),
+                    (BangEqual, l, r) => Ok(LoxValue::Boolean(!l.is_equal(&r))),
                     (Greater, LoxValue::Number(l), LoxValue::Number(r)) => {
                         Ok(LoxValue::Boolean(l > r))
                     }
@@ -169,13 +273,11 @@ impl Interpreter {
                     }
 
                     // String Concatenation
-                    (Plus, LoxValue::String(s1), rhs) => Ok(LoxValue::String(format!(
-                        "{}{}",
-                        s1.to_string(),
-                        rhs.to_string()
-                    ))),
+                    (Plus, LoxValue::String(s1), rhs) => Ok(LoxValue::String(
+                        format!("{}{}", s1, self.stringify(&rhs)?).into(),
+                    )),
                     (Star, LoxValue::String(s1), LoxValue::Number(n)) => {
-                        Ok(LoxValue::String(s1.repeat(n as usize)))
+                        Ok(LoxValue::String(s1.repeat(n as usize).into()))
                     }
 
                     (_op, _lhs, _rhs) => Err(RuntimeError::Generic(
@@ -188,9 +290,12 @@ impl Interpreter {
                 condition,
                 result_1,
                 result_2,
-                position: _,
+                position,
             } => {
-                if self.evaluate(condition)?.is_truthy() {
+                let condition = self.evaluate(condition)?.is_truthy();
+                self.branch(*position, condition);
+
+                if condition {
                     self.evaluate(result_1)
                 } else {
                     self.evaluate(result_2)
@@ -211,7 +316,18 @@ impl Interpreter {
                             evaluated_arguments.push(self.evaluate(argument)?)
                         }
 
-                        callable.call(self, &evaluated_arguments)
+                        if self.hook.is_none() {
+                            return callable.call(self, &evaluated_arguments, *position);
+                        }
+
+                        let name = callable.name();
+                        self.with_hook(|hook, interpreter| {
+                            hook.enter(interpreter, &name, &evaluated_arguments, *position)
+                        });
+                        let result = callable.call(self, &evaluated_arguments, *position);
+                        self.with_hook(|hook, interpreter| hook.exit(interpreter, &name, &result));
+
+                        result
                     } else {
                         Err(RuntimeError::IncorrectArity {
                             name: callable.name(),
@@ -220,14 +336,35 @@ impl Interpreter {
                     }
                 } else {
                     Err(RuntimeError::NotCallable {
-                        type_name: callee,
+                        type_name: Box::new(callee),
                         position: position.to_owned(),
                     })
                 }
             }
+            Lambda {
+                fun_declaration, ..
+            } => Ok(LoxValue::Function(self.closure(fun_declaration))),
+            Expr::Interpolation { parts, position: _ } => {
+                let mut res = std::string::String::new();
+
+                for part in parts {
+                    let value = self.evaluate(part)?;
+                    res.push_str(&self.stringify(&value)?);
+                }
+
+                Ok(LoxValue::String(res.into()))
+            }
         }
     }
 
+    /// Converts a value to the string used by `print`, concatenation and interpolation.
+    ///
+    /// This is fallible so that user defined conversions, like a class's
+    /// `toString` method, can run Lox code.
+    pub fn stringify(&mut self, value: &LoxValue) -> RuntimeResult<String> {
+        Ok(value.to_string())
+    }
+
     /// Executes a statement.
     pub fn execute(
         &mut self,
@@ -237,38 +374,58 @@ impl Interpreter {
     ) -> RuntimeResult<Option<LoxValue>> {
         use Stmt::*;
 
+        if self.hook.is_some() {
+            self.with_hook(|hook, interpreter| hook.statement(interpreter, statement))
+                .transpose()?;
+        }
+
         match statement {
             ExprStmt(expr) => {
                 self.evaluate(expr)?;
             }
             PrintStmt(expr) => {
-                println!("{}", self.evaluate(expr)?);
+                let value = self.evaluate(expr)?;
+                let text = self.stringify(&value)?;
+                writeln!(self.output, "{text}").map_err(|e| {
+                    RuntimeError::Generic(format!("Couldn't print: {e}"), expr.start_position())
+                })?;
             }
             Var {
                 name,
                 initializer,
-                postion: _,
+                position,
+                ..
             } => {
                 let initializer = self.evaluate(initializer)?;
-                self.environment.define(name, initializer);
+
+                if self.hook.is_some() {
+                    self.with_hook(|hook, interpreter| {
+                        hook.assign(interpreter, name, &initializer, *position)
+                    });
+                }
+
+                self.environment.define(name.clone(), initializer);
             }
-            Block(declarations) => {
-                self.environment.begin_scope();
-                let res = self.interpret(declarations, in_loop, in_function)?;
+            Block(declarations, _) => {
+                self.begin_scope();
+                let res = self.execute_block(declarations, in_loop, in_function);
                 self.environment.end_scope();
 
-                return Ok(res);
+                return res;
             }
             IfStmt {
                 condition,
                 then_branch,
                 else_branch,
-                position: _,
+                position,
             } => {
-                if self.evaluate(condition)?.is_truthy() {
-                    self.execute(&then_branch, true, in_function)?;
+                let condition = self.evaluate(condition)?.is_truthy();
+                self.branch(*position, condition);
+
+                if condition {
+                    return self.execute(then_branch, in_loop, in_function);
                 } else if let Some(stmt) = else_branch {
-                    self.execute(&stmt, true, in_function)?;
+                    return self.execute(stmt, in_loop, in_function);
                 }
             }
             WhileStmt {
@@ -277,9 +434,33 @@ impl Interpreter {
                 position: _,
             } => {
                 while self.evaluate(condition)?.is_truthy() {
-                    self.execute(body, true, in_function)?;
+                    match self.execute(body, true, in_function) {
+                        Ok(Some(value)) => return Ok(Some(value)),
+                        Ok(None) | Err(RuntimeError::ValidContinue) => (),
+                        Err(RuntimeError::ValidBreak) => break,
+                        Err(e) => return Err(e),
+                    }
                 }
             }
+            ForStmt {
+                initializer,
+                condition,
+                increment,
+                body,
+                position: _,
+            } => {
+                self.begin_scope();
+                let res = self.execute_for(
+                    initializer.as_deref(),
+                    condition.as_ref(),
+                    increment.as_ref(),
+                    body,
+                    in_function,
+                );
+                self.environment.end_scope();
+
+                return res;
+            }
             BreakStmt(position) => {
                 if in_loop {
                     return Err(RuntimeError::ValidBreak);
@@ -291,24 +472,24 @@ impl Interpreter {
                 if in_loop {
                     return Err(RuntimeError::ValidContinue);
                 } else {
-                    return Err(RuntimeError::InvalidBreak(position.to_owned()));
+                    return Err(RuntimeError::InvalidContinue(position.to_owned()));
                 }
             }
             FunStmt {
                 fun_declaration: decl,
                 position: _,
-            } => self.environment.define(
-                &decl.name,
-                LoxValue::Function(Function::User(decl.to_owned())),
-            ),
+            } => {
+                let function = self.closure(decl);
+                self.environment
+                    .define(decl.name.clone(), LoxValue::Function(function));
+            }
             ReturnStmt { expr, position } => {
                 if in_function {
-                    if let Some(value) = expr {
-                        let value = self.evaluate(value)?;
-                        return Ok(Some(value));
-                    } else {
-                        return Ok(None);
-                    }
+                    let value = match expr {
+                        Some(expr) => self.evaluate(expr)?,
+                        None => LoxValue::Nil,
+                    };
+                    return Ok(Some(value));
                 } else {
                     return Err(RuntimeError::InvalidReturn(position.to_owned()));
                 }
@@ -318,50 +499,129 @@ impl Interpreter {
         Ok(None)
     }
 
-    /// Executes statements given.
+    /// Resolves and then executes the statements given.
     pub fn interpret(
         &mut self,
         statements: &[Stmt],
         in_loop: bool,
         in_function: bool,
     ) -> RuntimeResult<Option<LoxValue>> {
-        use RuntimeError::*;
+        let mut resolver = Resolver::new(self);
+        resolver.resolve_program(statements)?;
 
-        if self.locals.is_empty() {
-            let mut resolver = Resolver::new(self);
-            resolver.resolve_program(statements)?;
-        }
+        self.execute_block(statements, in_loop, in_function)
+    }
 
+    /// Executes statements given in the current environment.
+    fn execute_block(
+        &mut self,
+        statements: &[Stmt],
+        in_loop: bool,
+        in_function: bool,
+    ) -> RuntimeResult<Option<LoxValue>> {
+        // 'break' and 'continue' go on up to the loop they're in.
         for statement in statements {
-            match self.execute(statement, in_loop, in_function) {
-                Ok(None) => {
-                    continue;
+            if let Some(value) = self.execute(statement, in_loop, in_function)? {
+                return Ok(Some(value));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Runs a 'for' loop in the scope holding the variables from its initializer.
+    fn execute_for(
+        &mut self,
+        initializer: Option<&Stmt>,
+        condition: Option<&Expr>,
+        increment: Option<&Expr>,
+        body: &Stmt,
+        in_function: bool,
+    ) -> RuntimeResult<Option<LoxValue>> {
+        if let Some(initializer) = initializer {
+            self.execute(initializer, false, in_function)?;
+        }
+
+        loop {
+            if let Some(condition) = condition {
+                if !self.evaluate(condition)?.is_truthy() {
+                    break;
                 }
+            }
+
+            match self.execute(body, true, in_function) {
                 Ok(Some(value)) => return Ok(Some(value)),
-                Err(ValidBreak) => {
-                    return Ok(None);
-                }
-                Err(ValidContinue) => {
-                    continue;
-                }
-                err => {
-                    err?;
-                }
+                Ok(None) | Err(RuntimeError::ValidContinue) => (),
+                Err(RuntimeError::ValidBreak) => break,
+                Err(e) => return Err(e),
+            }
+
+            if let Some(increment) = increment {
+                self.evaluate(increment)?;
             }
         }
 
         Ok(None)
     }
 
-    fn resolve(&mut self, expr: &Expr, depth: usize) -> RuntimeResult<()> {
-        self.locals.insert(expr.get_position().to_owned(), depth);
-        Ok(())
+    fn resolve(&mut self, expr: &Expr, slot: Slot) {
+        if let Expr::Identifier(.., resolved) | Expr::Assignment { slot: resolved, .. } = expr {
+            resolved.set(Some(slot));
+        }
     }
 
     pub fn interpret_str(&mut self, source: &str) -> LoxResult<Option<LoxValue>> {
         let stmts = Parser::parse_str(source)?;
 
         self.interpret(&stmts, false, false)
-            .map_err(|e| LoxError::Runtime(e))
+            .map_err(LoxError::Runtime)
+    }
+
+    /// Returns the variables that can be used from the current scope with their values,
+    /// e.g. for completion in the REPL.
+    pub fn visible_bindings(&self) -> Vec<(String, LoxValue)> {
+        let mut bindings = self.environment.bindings();
+
+        for (name, value) in self.globals.bindings() {
+            if !bindings.iter().any(|(seen, _)| *seen == name) {
+                bindings.push((name, value));
+            }
+        }
+
+        bindings
+    }
+
+    /// Evaluates the source as a single expression in the global scope.
+    pub fn evaluate_str(&mut self, source: &str) -> LoxResult<LoxValue> {
+        let globals = self.globals.clone();
+        self.evaluate_in(source, &globals)
+    }
+
+    /// Evaluates the source as a single expression in `environment`, e.g. the scope a
+    /// debugger is paused in. What was resolved for the running program is kept.
+    pub fn evaluate_in(&mut self, source: &str, environment: &Environment) -> LoxResult<LoxValue> {
+        let stmts = Parser::parse_str(&format!("{source};"))?;
+
+        let expr = match stmts.as_slice() {
+            [Stmt::ExprStmt(expr)] => expr,
+            stmts => {
+                return Err(LoxError::Runtime(RuntimeError::Generic(
+                    "Expected a single expression".to_string(),
+                    stmts
+                        .first()
+                        .map_or(Position::new(1, 1), |stmt| stmt.get_position()),
+                )))
+            }
+        };
+
+        let previous = std::mem::replace(&mut self.environment, environment.clone());
+
+        let result = Resolver::in_environment(self, environment)
+            .resolve_program(&stmts)
+            .and_then(|_| self.evaluate(expr));
+
+        self.environment = previous;
+
+        result.map_err(LoxError::Runtime)
     }
 }
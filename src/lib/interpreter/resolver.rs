@@ -1,18 +1,77 @@
 use std::collections::HashMap;
+use std::rc::Rc;
 
 use crate::{
-    ast::{Expr, Stmt},
+    ast::{walk_expr, walk_stmt, walk_stmts, Expr, Stmt, Visitor},
     function::FunDecl,
+    token::Position,
 };
 
 use super::{
+    environment::{Environment, Slot},
     error::{RuntimeError, RuntimeResult},
     Interpreter,
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Variable,
+    Function,
+    Parameter,
+}
+
+/// A declared name and every place it's used.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub declaration: Position,
+    /// The parameters of a function, empty for other kinds of symbols.
+    pub params: Vec<String>,
+    pub references: Vec<Position>,
+    /// The number of scopes enclosing the declaration, 0 for globals.
+    pub depth: usize,
+    /// Where the variable with the same name this one hides was declared.
+    pub shadows: Option<Position>,
+}
+
+/// The declarations and references found while resolving a program.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    pub symbols: Vec<Symbol>,
+    /// Names used in the program that it never declares, like native functions.
+    pub unresolved: Vec<(String, Position)>,
+}
+
+/// A local variable in a scope being resolved.
+#[derive(Debug, Clone, Copy)]
+struct Local {
+    /// Whether its initializer has been resolved, it can't be used before that.
+    defined: bool,
+    slot: usize,
+}
+
+/// The locals declared in a scope, by name, and how many slots they use.
+#[derive(Debug, Default)]
+struct Scope {
+    locals: HashMap<Rc<str>, Local>,
+    /// A local declared twice gets a second slot, the first one is still around
+    /// for closures that captured it.
+    slots: usize,
+}
+
 pub struct Resolver<'a> {
     interpreter: &'a mut Interpreter,
-    scopes: Vec<HashMap<String, bool>>,
+    scopes: Vec<Scope>,
+    /// Only recorded when the resolver is created with `Resolver::with_symbols`.
+    symbols: Option<SymbolTable>,
+    /// Indexes into `symbols` for the names declared in each scope.
+    symbol_scopes: Vec<HashMap<String, usize>>,
+    global_symbols: HashMap<String, usize>,
+    /// References to globals are bound once the whole program has been seen,
+    /// since functions can refer to globals declared after them.
+    global_references: Vec<(String, Position)>,
+    error: Option<RuntimeError>,
 }
 
 impl<'a> Resolver<'a> {
@@ -20,195 +79,165 @@ impl<'a> Resolver<'a> {
         Resolver {
             interpreter,
             scopes: Vec::new(),
+            symbols: None,
+            symbol_scopes: Vec::new(),
+            global_symbols: HashMap::new(),
+            global_references: Vec::new(),
+            error: None,
         }
     }
 
-    pub fn resolve_stmt(&mut self, stmt: &Stmt) -> RuntimeResult<()> {
-        use Stmt::*;
+    /// Creates a resolver that also builds a `SymbolTable` for tooling.
+    pub fn with_symbols(interpreter: &'a mut Interpreter) -> Resolver<'a> {
+        Resolver {
+            symbols: Some(SymbolTable::default()),
+            ..Resolver::new(interpreter)
+        }
+    }
 
-        match stmt {
-            Block(statements) => {
-                self.begin_scope();
-                self.resolve_stmts(statements)?;
-                self.end_scope();
-            }
-            Var {
-                name, initializer, ..
-            } => {
-                // Declare the variable in the innnermost scope marking it as 'still resolving'.
-                self.declare(name);
-                match initializer {
-                    Expr::Value { value, position: _ } => {
-                        // If the variable has a non-null initializer resolve the initializer.
-                        if !value.is_nil() {
-                            self.resolve_expr(&initializer)?;
-                        }
-                    }
-                    _ => (),
-                }
-                self.define(name);
-            }
-            FunStmt {
-                fun_declaration: fun_decl @ FunDecl { name, .. },
-                ..
-            } => {
-                self.declare(&name);
-                self.define(&name);
-                self.resolve_function(fun_decl)?;
-            }
-            ExprStmt(expr) => {
-                self.resolve_expr(expr)?;
-            }
-            IfStmt {
-                condition,
-                then_branch,
-                else_branch,
-                ..
-            } => {
-                self.resolve_expr(condition)?;
-                self.resolve_block(then_branch)?;
+    /// Creates a resolver for code that runs in `environment` rather than the global scope,
+    /// e.g. an expression evaluated where a debugger is paused.
+    pub fn in_environment(
+        interpreter: &'a mut Interpreter,
+        environment: &Environment,
+    ) -> Resolver<'a> {
+        let mut resolver = Resolver::new(interpreter);
 
-                if let Some(else_block) = else_branch {
-                    self.resolve_block(&else_block)?;
-                }
+        for names in environment.local_slot_names().into_iter().rev() {
+            resolver.begin_scope();
+            for name in names {
+                resolver.declare(&name);
+                resolver.define(&name);
             }
-            PrintStmt(expr) => self.resolve_expr(expr)?,
-            ReturnStmt { expr, .. } => {
-                if let Some(value) = expr {
-                    self.resolve_expr(value)?;
-                }
-            }
-            WhileStmt {
-                condition, body, ..
-            } => {
-                self.resolve_expr(condition)?;
-                self.resolve_block(&body)?;
-            }
-            ContinueStmt(_) => (),
-            BreakStmt(_) => (),
         }
 
-        Ok(())
+        resolver
     }
 
-    fn resolve_function(&mut self, fun_declaration: &FunDecl) -> RuntimeResult<()> {
-        self.begin_scope();
-        for param in &fun_declaration.params {
-            self.declare(param);
-            self.define(param);
+    /// Returns the symbols recorded so far, even if resolving failed part way.
+    pub fn into_symbols(self) -> SymbolTable {
+        let mut table = self.symbols.unwrap_or_default();
+
+        for (name, position) in self.global_references {
+            match self.global_symbols.get(&name) {
+                Some(index) => table.symbols[*index].references.push(position),
+                None => table.unresolved.push((name, position)),
+            }
         }
-        self.resolve_block(&fun_declaration.body)?;
-        self.end_scope();
 
-        Ok(())
+        table
     }
 
-    fn resolve_expr(&mut self, expr: &Expr) -> RuntimeResult<()> {
-        use Expr::*;
-
-        match expr {
-            Identifier(name, position) => {
-                // Check if variable is initialized yet.
-                if !self.scopes.is_empty() {
-                    if let Some(false) = self.scopes.last_mut().unwrap().get(name) {
-                        return Err(RuntimeError::VarUsedInOwnInitializer(
-                            name.to_owned(),
-                            position.to_owned(),
-                        ));
-                    }
-                }
-
-                self.resolve_local(expr, name)?;
-            }
-            assign_expr @ Assignment { name, value, .. } => {
-                self.resolve_expr(&value)?;
-                self.resolve_local(assign_expr, name)?;
-            }
-            Binary { lhs, rhs, .. } => {
-                self.resolve_expr(lhs)?;
-                self.resolve_expr(rhs)?;
-            }
-            Call {
-                callee, arguments, ..
-            } => {
-                self.resolve_expr(callee)?;
+    /// Records the slot of the variable and how many scopes away from the innermost
+    /// one it's declared. Variables that aren't found are assumed to be globals.
+    fn resolve_local(&mut self, expr: &Expr, name: &str) {
+        let position = expr.get_position();
 
-                for argument in arguments {
-                    self.resolve_expr(argument)?;
-                }
-            }
-            Grouping(expr, ..) => {
-                self.resolve_expr(expr)?;
-            }
-            Value { .. } => (),
-            Unary { rhs, .. } => self.resolve_expr(rhs)?,
-            Ternary {
-                condition,
-                result_1,
-                result_2,
-                ..
-            } => {
-                self.resolve_expr(condition)?;
-                self.resolve_expr(result_1)?;
-                self.resolve_expr(result_2)?;
+        for (distance, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(local) = scope.locals.get(name) {
+                let index = local.slot;
+                self.record_reference(name, position, distance);
+                return self
+                    .interpreter
+                    .resolve(expr, Slot::Local { distance, index });
             }
         }
 
-        Ok(())
-    }
-
-    fn resolve_local(&mut self, expr: &Expr, name: &str) -> RuntimeResult<()> {
-        for (index, scope) in self.scopes.iter().rev().enumerate() {
-            if scope.contains_key(name) {
-                let depth = self.scopes.len() - 1 - index;
-                self.interpreter.resolve(expr, depth)?;
-            }
+        if self.symbols.is_some() {
+            self.global_references.push((name.to_owned(), position));
         }
-        Ok(())
+
+        // Globals get their slots as they're found, they're defined by name when they run.
+        let index = self.interpreter.globals.global_slot(name);
+        self.interpreter.resolve(expr, Slot::Global(index));
     }
 
     /// TODO Resolves a mutable slice of statements
     pub fn resolve_stmts(&mut self, stmts: &[Stmt]) -> RuntimeResult<()> {
-        for stmt in stmts {
-            self.resolve_stmt(stmt)?;
-        }
-        Ok(())
+        self.visit_stmts(stmts);
+        self.error.take().map_or(Ok(()), Err)
     }
 
+    /// Resolves a whole program, top-level declarations live in the global scope.
     pub fn resolve_program(&mut self, stmts: &[Stmt]) -> RuntimeResult<()> {
-        self.begin_scope();
-        self.resolve_stmts(stmts)?;
-        self.end_scope();
-
-        Ok(())
-    }
-
-    fn resolve_block(&mut self, block: &Stmt) -> RuntimeResult<()> {
-        self.begin_scope();
-        if let Stmt::Block(stmts) = block {
-            self.resolve_stmts(stmts)?;
-        }
-        self.end_scope();
-
-        Ok(())
+        self.resolve_stmts(stmts)
     }
 
     fn begin_scope(&mut self) {
-        self.scopes.push(HashMap::new())
+        self.scopes.push(Scope::default());
+        self.symbol_scopes.push(HashMap::new());
     }
 
     fn end_scope(&mut self) {
         self.scopes.pop();
+        self.symbol_scopes.pop();
+    }
+
+    fn record_declaration(
+        &mut self,
+        name: &str,
+        declaration: Position,
+        kind: SymbolKind,
+        params: Vec<String>,
+    ) {
+        let table = match self.symbols.as_mut() {
+            Some(table) => table,
+            None => return,
+        };
+
+        // Only locals can shadow, redeclaring a global replaces it.
+        let shadows = if self.scopes.is_empty() {
+            None
+        } else {
+            self.symbol_scopes
+                .iter()
+                .rev()
+                .skip(1)
+                .find_map(|scope| scope.get(name))
+                .or_else(|| self.global_symbols.get(name))
+                .map(|index| table.symbols[*index].declaration)
+        };
+
+        table.symbols.push(Symbol {
+            name: name.to_owned(),
+            kind,
+            declaration,
+            params,
+            references: Vec::new(),
+            depth: self.scopes.len(),
+            shadows,
+        });
+
+        let index = table.symbols.len() - 1;
+        match self.symbol_scopes.last_mut() {
+            Some(scope) => scope.insert(name.to_owned(), index),
+            None => self.global_symbols.insert(name.to_owned(), index),
+        };
+    }
+
+    fn record_reference(&mut self, name: &str, position: Position, distance: usize) {
+        if let Some(table) = self.symbols.as_mut() {
+            let scope = &self.symbol_scopes[self.symbol_scopes.len() - 1 - distance];
+
+            if let Some(index) = scope.get(name) {
+                table.symbols[*index].references.push(position);
+            }
+        }
     }
 
     /// Adds the variable to the innermost scope so that it shadows any outer one and so that
     /// we know that the variable exists. We mark it as 'not ready yet' by binding a `false` value to
     /// the scope map. The value associated with a key in the scope map represents whether or now we have
     /// finished resolving the  variable's initializer.
-    fn declare(&mut self, name: &str) {
+    fn declare(&mut self, name: &Rc<str>) {
         // If there is no current scope then just return
         if let Some(innermost_scope) = self.scopes.last_mut() {
-            innermost_scope.insert(name.to_owned(), false);
+            let local = Local {
+                defined: false,
+                slot: innermost_scope.slots,
+            };
+            innermost_scope.locals.insert(name.clone(), local);
+            innermost_scope.slots += 1;
         }
     }
 
@@ -216,8 +245,105 @@ impl<'a> Resolver<'a> {
     /// the new variable now exists but is unavailable. Once the initializer expression is done, the
     /// variable is 'declared' (it's value in the scope map is set to `true`).
     fn define(&mut self, name: &str) {
-        if let Some(innermost_scope) = self.scopes.last_mut() {
-            innermost_scope.insert(name.to_owned(), true);
+        if let Some(local) = self
+            .scopes
+            .last_mut()
+            .and_then(|scope| scope.locals.get_mut(name))
+        {
+            local.defined = true;
+        }
+    }
+}
+
+impl Visitor for Resolver<'_> {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        use Stmt::*;
+
+        if self.error.is_some() {
+            return;
+        }
+
+        match stmt {
+            Block(statements, _) => {
+                self.begin_scope();
+                walk_stmts(self, statements);
+                self.end_scope();
+            }
+            Var {
+                name,
+                initializer,
+                position,
+                ..
+            } => {
+                // Declare the variable in the innnermost scope marking it as 'still resolving'.
+                self.declare(name);
+                self.record_declaration(name, *position, SymbolKind::Variable, Vec::new());
+                self.visit_expr(initializer);
+                self.define(name);
+            }
+            FunStmt {
+                fun_declaration: fun_decl,
+                ..
+            } => {
+                let name = &fun_decl.name;
+                self.declare(name);
+                let params = fun_decl.params.iter().map(|p| p.name.to_string()).collect();
+                self.record_declaration(name, fun_decl.position, SymbolKind::Function, params);
+                self.define(name);
+                self.visit_function(fun_decl);
+            }
+            // Variables declared in the initializer are scoped to the loop.
+            ForStmt { .. } => {
+                self.begin_scope();
+                walk_stmt(self, stmt);
+                self.end_scope();
+            }
+            stmt => walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_function(&mut self, fun_declaration: &FunDecl) {
+        self.begin_scope();
+        for param in &fun_declaration.params {
+            self.declare(&param.name);
+            self.record_declaration(
+                &param.name,
+                param.position,
+                SymbolKind::Parameter,
+                Vec::new(),
+            );
+            self.define(&param.name);
+        }
+        self.visit_stmt(&fun_declaration.body);
+        self.end_scope();
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        use Expr::*;
+
+        if self.error.is_some() {
+            return;
+        }
+
+        match expr {
+            Identifier(name, position, _) => {
+                // Check if variable is initialized yet.
+                let local = self.scopes.last().and_then(|scope| scope.locals.get(name));
+                if let Some(Local { defined: false, .. }) = local {
+                    self.error = Some(RuntimeError::VarUsedInOwnInitializer(
+                        name.to_string(),
+                        position.to_owned(),
+                    ));
+                    return;
+                }
+
+                self.resolve_local(expr, name);
+            }
+            Assignment { name, value, .. } => {
+                self.visit_expr(value);
+                self.resolve_local(expr, name);
+            }
+            expr => walk_expr(self, expr),
         }
     }
 }
@@ -1,14 +1,20 @@
+use std::collections::HashMap;
+
 use super::*;
+use crate::ast::{walk_expr, Visitor};
+use crate::interpreter::heap::GcConfig;
 use crate::parser::Parser;
 use crate::scanner::Scanner;
 
-use crate::utils::{log_items, read_file};
+use crate::utils::read_file;
 
 fn assert_execution_of(title: &str, src: &str, verbose: bool) -> Interpreter {
-    let tokens = Scanner::tokens_from_str(src, verbose);
+    if verbose {
+        Scanner::tokens_from_str(src, verbose).ok();
+    }
 
     let statements = Parser::parse_str(src).unwrap_or_else(|e| {
-        println!("{e}");
+        println!("{title}:\n{e}");
         panic!()
     });
 
@@ -18,7 +24,12 @@ fn assert_execution_of(title: &str, src: &str, verbose: bool) -> Interpreter {
         println!("Interpreter:\n{:?}", interpreter);
     }
 
-    interpreter.interpret(&statements, false, false).unwrap();
+    interpreter
+        .interpret(&statements, false, false)
+        .unwrap_or_else(|e| {
+            println!("{title}:\n{e}");
+            panic!()
+        });
 
     interpreter
 }
@@ -99,3 +110,337 @@ fn executes_call_statements() {
 fn executes_fun_declaration() {
     assert_execution_of_file("examples/fun_decl.lox", false);
 }
+
+#[test]
+fn executes_lambdas() {
+    assert_execution_of_file("examples/lambda.lox", false);
+}
+
+#[test]
+fn lambdas_capture_their_environment() {
+    let src = "
+        fun make_counter() {
+            var count = 0;
+            return () => {
+                count = count + 1;
+                return count;
+            };
+        }
+
+        var counter = make_counter();
+        counter();
+        var result = counter();
+    ";
+    let interpreter = assert_execution_of("closures", src, false);
+
+    assert!(matches!(
+        interpreter.globals.get("result"),
+        Some(LoxValue::Number(n)) if n == 2.0
+    ));
+}
+
+#[test]
+fn executes_interpolation() {
+    assert_execution_of_file("examples/interpolation.lox", false);
+}
+
+#[test]
+fn interpolates_expressions_into_strings() {
+    let src = r#"
+        var name = "Ada";
+        var greeting = "Hello ${name}, you are ${35 + 1}, ${nil} ${true}";
+    "#;
+    let interpreter = assert_execution_of("interpolation", src, false);
+
+    assert!(matches!(
+        interpreter.globals.get("greeting"),
+        Some(LoxValue::String(s)) if &*s == "Hello Ada, you are 36, nil true"
+    ));
+}
+
+#[test]
+fn executes_strings() {
+    assert_execution_of_file("examples/strings.lox", false);
+}
+
+#[test]
+fn compares_values_of_every_type() {
+    let src = r#"
+        var f = fun () {};
+        var g = f;
+        fun make() {
+            return () => nil;
+        }
+
+        var numbers = 1 == 1;
+        var strings = "a" != "a";
+        var mixed = "1" == 1;
+        var nils = nil == nil;
+        var nil_and_false = nil == false;
+        var same_function = f == g;
+        var other_function = f == fun () {};
+        var natives = clock == clock;
+        var closures = make() == make();
+    "#;
+    let interpreter = assert_execution_of("equality", src, false);
+
+    for (name, expected) in [
+        ("numbers", true),
+        ("strings", false),
+        ("mixed", false),
+        ("nils", true),
+        ("nil_and_false", false),
+        ("same_function", true),
+        ("other_function", false),
+        ("natives", true),
+        ("closures", false),
+    ] {
+        assert!(
+            matches!(interpreter.globals.get(name), Some(LoxValue::Boolean(b)) if b == expected),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn ignores_type_annotations() {
+    assert_execution_of_file("examples/types.lox", false);
+}
+
+#[test]
+fn evaluates_single_expressions() {
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .interpret_str("var a = 2; fun f(x: number): string { return \"${x}\"; }")
+        .unwrap();
+
+    let value = interpreter.evaluate_str("a * 3").unwrap();
+    assert!(value.is_equal(&LoxValue::Number(6.0)));
+
+    let types: Vec<String> = ["f", "clock", "f(a) + nil", "a == 2", "nil"]
+        .iter()
+        .map(|src| interpreter.evaluate_str(src).unwrap().type_of().to_string())
+        .collect();
+    assert_eq!(
+        types,
+        ["fun(number): string", "fun(): any", "string", "bool", "nil"]
+    );
+
+    assert!(interpreter.evaluate_str("print 1").is_err());
+}
+
+#[test]
+fn lists_visible_bindings() {
+    let mut interpreter = Interpreter::new();
+    interpreter.interpret_str("var b = 1; var a = 2;").unwrap();
+
+    interpreter.environment.begin_scope();
+    interpreter.environment.define("b", LoxValue::Nil);
+    interpreter.environment.define("c", LoxValue::Nil);
+
+    let names: Vec<(String, bool)> = interpreter
+        .visible_bindings()
+        .into_iter()
+        .map(|(name, value)| (name, value.is_nil()))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            ("b".to_string(), true),
+            ("c".to_string(), true),
+            ("a".to_string(), false),
+            ("clock".to_string(), false),
+            ("gc".to_string(), false)
+        ]
+    );
+}
+
+#[test]
+fn short_circuits_logical_operators() {
+    let src = "
+        var calls = 0;
+        fun f() { calls = calls + 1; return true; }
+        var a = false and f();
+        var b = true or f();
+        var c = true and f();
+        var d = nil or f();
+    ";
+    let interpreter = assert_execution_of("short circuit", src, false);
+
+    assert!(interpreter
+        .globals
+        .get("calls")
+        .unwrap()
+        .is_equal(&LoxValue::Number(2.0)));
+    assert!(interpreter
+        .globals
+        .get("a")
+        .unwrap()
+        .is_equal(&LoxValue::Boolean(false)));
+    assert!(interpreter
+        .globals
+        .get("d")
+        .unwrap()
+        .is_equal(&LoxValue::Boolean(true)));
+}
+
+/// Collects the slots the names of a program were resolved to, by position.
+#[derive(Default)]
+struct Slots(HashMap<Position, Option<Slot>>);
+
+impl Visitor for Slots {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Expr::Identifier(_, position, slot) = expr {
+            self.0.insert(*position, slot.get());
+        }
+        walk_expr(self, expr)
+    }
+}
+
+#[test]
+fn resolves_variables_to_slots() {
+    let statements =
+        Parser::parse_str("var g = 1;\nfun f(a) { var b = a; return b + g; }").unwrap();
+    let mut interpreter = Interpreter::new();
+    interpreter.interpret(&statements, false, false).unwrap();
+
+    let mut slots = Slots::default();
+    slots.visit_stmts(&statements);
+    let slot = |column| slots.0[&Position::new(2, column)];
+    assert_eq!(
+        slot(20),
+        Some(Slot::Local {
+            distance: 1,
+            index: 0
+        })
+    );
+    assert_eq!(
+        slot(30),
+        Some(Slot::Local {
+            distance: 0,
+            index: 0
+        })
+    );
+    // The natives 'clock' and 'gc' are defined first.
+    assert_eq!(slot(34), Some(Slot::Global(2)));
+}
+
+#[test]
+fn keeps_the_slots_of_earlier_programs() {
+    // Both functions read a variable at the same position, like inputs of the REPL.
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .interpret_str("fun f() { var a = 1; var b = 2; return b; }")
+        .unwrap();
+    interpreter
+        .interpret_str("fun g() { var a = 1; var b = 2; return a; }")
+        .unwrap();
+    interpreter
+        .interpret_str("fun h(p) {{{{ return p; }}}}")
+        .unwrap();
+
+    for (src, expected) in [("f()", 2.0), ("g()", 1.0), ("h(3)", 3.0)] {
+        let value = interpreter.evaluate_str(src).unwrap();
+        assert!(
+            value.is_equal(&LoxValue::Number(expected)),
+            "{src}: {value}"
+        );
+    }
+}
+
+#[test]
+fn shares_functions_and_strings_between_variables() {
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .interpret_str("fun f() { return 1; }\nvar g = f;\nvar s = \"text\";\nvar t = s;")
+        .unwrap();
+
+    let (Some(LoxValue::Function(f)), Some(LoxValue::Function(g))) =
+        (interpreter.globals.get("f"), interpreter.globals.get("g"))
+    else {
+        panic!("Expected two functions");
+    };
+    let (Function::User { declaration: f, .. }, Function::User { declaration: g, .. }) = (f, g)
+    else {
+        panic!("Expected two user functions");
+    };
+    assert!(std::rc::Rc::ptr_eq(&f, &g));
+
+    let (Some(LoxValue::String(s)), Some(LoxValue::String(t))) =
+        (interpreter.globals.get("s"), interpreter.globals.get("t"))
+    else {
+        panic!("Expected two strings");
+    };
+    assert!(std::rc::Rc::ptr_eq(&s, &t));
+}
+
+/// Declares a function in a new scope each time round the loop, each closure keeps its scope alive.
+const CYCLES: &str = "
+    for (var i = 0; i < 50; i = i + 1) {
+        fun f() { return i; }
+    }
+";
+
+#[test]
+fn collects_scopes_closures_keep_alive() {
+    let mut interpreter = Interpreter::new();
+    interpreter.interpret_str(CYCLES).unwrap();
+
+    let leaked = interpreter.gc_stats().live;
+    assert!(leaked >= 50, "{:?}", interpreter.gc_stats());
+
+    assert!(interpreter.collect_garbage() >= 50);
+    let stats = interpreter.gc_stats();
+    assert_eq!(stats.live, 0);
+    assert_eq!(stats.collections, 1);
+    assert_eq!(stats.freed, leaked);
+}
+
+#[test]
+fn keeps_reachable_closures() {
+    let src = "
+        fun make_counter() {
+            var count = 0;
+            fun increment() {
+                count = count + 1;
+                return count;
+            }
+            gc();
+            return increment;
+        }
+
+        var counter = make_counter();
+        counter();
+        var freed = gc();
+        var result = counter();
+    ";
+    let interpreter = assert_execution_of("gc", src, false);
+
+    assert!(matches!(
+        interpreter.globals.get("freed"),
+        Some(LoxValue::Number(n)) if n == 0.0
+    ));
+    assert!(matches!(
+        interpreter.globals.get("result"),
+        Some(LoxValue::Number(n)) if n == 2.0
+    ));
+}
+
+#[test]
+fn collects_when_the_threshold_is_reached() {
+    let stats = |threshold| {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_gc_config(GcConfig {
+            threshold,
+            ..GcConfig::default()
+        });
+        interpreter.interpret_str(CYCLES).unwrap();
+        interpreter.gc_stats()
+    };
+
+    assert_eq!(stats(1000).collections, 0);
+
+    let stats = stats(10);
+    assert!(stats.collections >= 4, "{stats:?}");
+    assert!(stats.live <= 20, "{stats:?}");
+}
@@ -1,16 +1,30 @@
 pub mod ast;
+pub mod function;
 pub mod lox_value;
 
 pub mod scanner;
 pub mod token;
 pub mod token_type;
+pub mod types;
 
 pub mod parser;
 
 pub mod error;
 pub mod interpreter;
 
+pub mod checker;
+pub mod coverage;
+pub mod dap;
+pub mod debugger;
+pub mod dump;
+pub mod formatter;
+pub mod linter;
+pub mod lsp;
+pub mod optimizer;
+pub mod profiler;
+pub mod testing;
+pub mod tracer;
+
 mod callable;
-mod function;
 
 mod utils;
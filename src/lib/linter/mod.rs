@@ -0,0 +1,431 @@
+//! Warnings for code that runs but probably doesn't do what was meant.
+//!
+//! The linter works on the `SymbolTable` built by the `Resolver` along with a
+//! walk over the AST. Warnings can be silenced with comment directives:
+//!
+//! ```text
+//! var unused = 1; // lint-allow: W001
+//! // lint-allow: shadowed-variable, W004
+//! // lint-allow-file: all
+//! ```
+//!
+//! A trailing directive applies to its own line, a directive on a line of its
+//! own to the line after it and `lint-allow-file` to the whole file.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::ast::{walk_expr, walk_function, walk_stmt, Expr, Stmt, Visitor};
+use crate::error::{LoxError, LoxResult};
+use crate::function::FunDecl;
+use crate::interpreter::resolver::{Resolver, SymbolKind, SymbolTable};
+use crate::interpreter::Interpreter;
+use crate::lox_value::LoxValue;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::token::{Comment, Position};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    UnusedVariable,
+    ShadowedVariable,
+    UnreachableCode,
+    SelfAssignment,
+    ConstantCondition,
+    WrongArity,
+    TopLevelReturn,
+}
+
+impl WarningKind {
+    const ALL: [WarningKind; 7] = [
+        WarningKind::UnusedVariable,
+        WarningKind::ShadowedVariable,
+        WarningKind::UnreachableCode,
+        WarningKind::SelfAssignment,
+        WarningKind::ConstantCondition,
+        WarningKind::WrongArity,
+        WarningKind::TopLevelReturn,
+    ];
+
+    pub fn code(&self) -> &'static str {
+        use WarningKind::*;
+
+        match self {
+            UnusedVariable => "W001",
+            ShadowedVariable => "W002",
+            UnreachableCode => "W003",
+            SelfAssignment => "W004",
+            ConstantCondition => "W005",
+            WrongArity => "W006",
+            TopLevelReturn => "W007",
+        }
+    }
+
+    /// The name that can be used instead of the code in directives.
+    pub fn name(&self) -> &'static str {
+        use WarningKind::*;
+
+        match self {
+            UnusedVariable => "unused-variable",
+            ShadowedVariable => "shadowed-variable",
+            UnreachableCode => "unreachable-code",
+            SelfAssignment => "self-assignment",
+            ConstantCondition => "constant-condition",
+            WrongArity => "wrong-arity",
+            TopLevelReturn => "top-level-return",
+        }
+    }
+
+    fn from_directive(word: &str) -> Vec<WarningKind> {
+        if word.eq_ignore_ascii_case("all") {
+            return WarningKind::ALL.to_vec();
+        }
+
+        WarningKind::ALL
+            .into_iter()
+            .filter(|kind| word.eq_ignore_ascii_case(kind.code()) || word == kind.name())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub message: String,
+    pub position: Position,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "warning[{}]: {}, {}.",
+            self.kind.code(),
+            self.message,
+            self.position
+        )
+    }
+}
+
+/// Lints a whole program, the source must scan and parse without errors.
+pub fn lint_source(source: &str) -> LoxResult<Vec<Warning>> {
+    let mut scanner = Scanner::new(source);
+    scanner.scan_tokens().map_err(LoxError::Scan)?;
+
+    let statements = Parser::new(scanner.tokens)
+        .program()
+        .map_err(LoxError::Parser)?;
+
+    let mut interpreter = Interpreter::new();
+    let mut resolver = Resolver::with_symbols(&mut interpreter);
+    resolver
+        .resolve_program(&statements)
+        .map_err(LoxError::Runtime)?;
+    let symbols = resolver.into_symbols();
+
+    let mut linter = Linter::new(&symbols);
+    linter.symbols();
+    linter.visit_stmts(&statements);
+
+    let directives = Directives::new(&scanner.comments);
+    let mut warnings: Vec<Warning> = linter
+        .warnings
+        .into_iter()
+        .filter(|warning| !directives.allows(warning))
+        .collect();
+    warnings.sort_by_key(|warning| warning.position);
+
+    Ok(warnings)
+}
+
+struct Linter<'a> {
+    table: &'a SymbolTable,
+    /// The symbol each reference refers to.
+    references: HashMap<Position, usize>,
+    /// The number of functions the code being linted is nested in.
+    function_depth: usize,
+    warnings: Vec<Warning>,
+}
+
+impl<'a> Linter<'a> {
+    fn new(table: &'a SymbolTable) -> Linter<'a> {
+        let references = table
+            .symbols
+            .iter()
+            .enumerate()
+            .flat_map(|(index, symbol)| symbol.references.iter().map(move |p| (*p, index)))
+            .collect();
+
+        Linter {
+            table,
+            references,
+            function_depth: 0,
+            warnings: Vec::new(),
+        }
+    }
+
+    fn warn(&mut self, kind: WarningKind, message: String, position: Position) {
+        self.warnings.push(Warning {
+            kind,
+            message,
+            position,
+        });
+    }
+
+    /// Checks the declarations found by the resolver.
+    fn symbols(&mut self) {
+        for symbol in &self.table.symbols {
+            let what = match symbol.kind {
+                SymbolKind::Variable => "Variable",
+                SymbolKind::Parameter => "Parameter",
+                SymbolKind::Function => "Function",
+            };
+
+            // Globals can be used by code that's run later, like in the REPL.
+            let unused = symbol.depth > 0
+                && symbol.kind != SymbolKind::Function
+                && symbol.references.is_empty()
+                && !symbol.name.starts_with('_');
+            if unused {
+                self.warn(
+                    WarningKind::UnusedVariable,
+                    format!("{what} '{}' is never used", symbol.name),
+                    symbol.declaration,
+                );
+            }
+
+            if let Some(shadowed) = symbol.shadows {
+                self.warn(
+                    WarningKind::ShadowedVariable,
+                    format!(
+                        "{what} '{}' shadows the one declared at {shadowed}",
+                        symbol.name
+                    ),
+                    symbol.declaration,
+                );
+            }
+        }
+    }
+
+    /// Warns about a condition that's always the same, `while (true)` is allowed for infinite loops.
+    fn condition(&mut self, condition: &Expr, statement: &str) {
+        let infinite_loop = statement != "if"
+            && matches!(
+                condition,
+                Expr::Value {
+                    value: LoxValue::Boolean(true),
+                    ..
+                }
+            );
+        if infinite_loop || !is_constant(condition) {
+            return;
+        }
+
+        let message = match Interpreter::new().evaluate(condition) {
+            Ok(value) => format!(
+                "The condition of this '{statement}' is always {}",
+                value.is_truthy()
+            ),
+            Err(_) => format!("The condition of this '{statement}' is a constant"),
+        };
+        self.warn(
+            WarningKind::ConstantCondition,
+            message,
+            condition.start_position(),
+        );
+    }
+
+    /// Checks calls to functions declared with `fun` and to native functions.
+    fn arity(&mut self, name: &str, position: Position, arguments: usize) {
+        let arity = match self.references.get(&position) {
+            Some(index) => {
+                let symbol = &self.table.symbols[*index];
+                if symbol.kind != SymbolKind::Function {
+                    return;
+                }
+                symbol.params.len()
+            }
+            None => match Interpreter::new()
+                .globals
+                .get(name)
+                .and_then(|value| value.as_callable())
+            {
+                Some(native) => native.arity(),
+                None => return,
+            },
+        };
+
+        if arity != arguments {
+            self.warn(
+                WarningKind::WrongArity,
+                format!(
+                    "'{name}' takes {arity} argument{} but is called with {arguments}",
+                    if arity == 1 { "" } else { "s" }
+                ),
+                position,
+            );
+        }
+    }
+}
+
+impl Visitor for Linter<'_> {
+    fn visit_stmts(&mut self, stmts: &[Stmt]) {
+        let mut terminated = false;
+        let mut reported = false;
+
+        for stmt in stmts {
+            // Only the first unreachable statement is reported.
+            if terminated && !reported {
+                self.warn(
+                    WarningKind::UnreachableCode,
+                    "Unreachable code".to_string(),
+                    stmt.get_position(),
+                );
+                reported = true;
+            }
+
+            self.visit_stmt(stmt);
+            terminated = terminated || terminates(stmt);
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        use Stmt::*;
+
+        match stmt {
+            IfStmt { condition, .. } => self.condition(condition, "if"),
+            WhileStmt { condition, .. } => self.condition(condition, "while"),
+            ForStmt {
+                condition: Some(condition),
+                ..
+            } => self.condition(condition, "for"),
+            ReturnStmt { position, .. } if self.function_depth == 0 => self.warn(
+                WarningKind::TopLevelReturn,
+                "'return' outside of a function".to_string(),
+                *position,
+            ),
+            _ => (),
+        }
+
+        walk_stmt(self, stmt)
+    }
+
+    fn visit_function(&mut self, declaration: &FunDecl) {
+        self.function_depth += 1;
+        walk_function(self, declaration);
+        self.function_depth -= 1;
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        use Expr::*;
+
+        match expr {
+            Assignment {
+                name,
+                value,
+                position,
+                ..
+            } if matches!(&**value, Identifier(value_name, ..) if value_name == name) => {
+                self.warn(
+                    WarningKind::SelfAssignment,
+                    format!("'{name}' is assigned to itself"),
+                    *position,
+                );
+            }
+            Call {
+                callee, arguments, ..
+            } => {
+                if let Identifier(name, position, _) = &**callee {
+                    self.arity(name, *position, arguments.len());
+                }
+            }
+            _ => (),
+        }
+
+        walk_expr(self, expr)
+    }
+}
+
+/// Checks if nothing after the statement in the same block can run.
+fn terminates(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::ReturnStmt { .. } | Stmt::BreakStmt(_) | Stmt::ContinueStmt(_) => true,
+        Stmt::Block(stmts, _) => stmts.iter().any(terminates),
+        Stmt::IfStmt {
+            then_branch,
+            else_branch: Some(else_branch),
+            ..
+        } => terminates(then_branch) && terminates(else_branch),
+        _ => false,
+    }
+}
+
+/// Checks if an expression is made up only of literals.
+fn is_constant(expr: &Expr) -> bool {
+    match expr {
+        Expr::Value { .. } => true,
+        Expr::Grouping(expr, _) => is_constant(expr),
+        Expr::Unary { rhs, .. } => is_constant(rhs),
+        Expr::Binary { lhs, rhs, .. } => is_constant(lhs) && is_constant(rhs),
+        _ => false,
+    }
+}
+
+/// The warnings allowed by `lint-allow` comments.
+struct Directives {
+    file: Vec<WarningKind>,
+    lines: HashMap<usize, Vec<WarningKind>>,
+}
+
+impl Directives {
+    fn new(comments: &[Comment]) -> Directives {
+        let mut directives = Directives {
+            file: Vec::new(),
+            lines: HashMap::new(),
+        };
+
+        for comment in comments {
+            let text = comment
+                .text
+                .trim_start_matches('/')
+                .trim_start_matches('*')
+                .trim_end_matches('/')
+                .trim_end_matches('*')
+                .trim();
+
+            let (kinds, line) = if let Some(kinds) = text.strip_prefix("lint-allow-file:") {
+                (kinds, None)
+            } else if let Some(kinds) = text.strip_prefix("lint-allow:") {
+                let line = if comment.trailing {
+                    comment.position.line
+                } else {
+                    comment.position.line + 1
+                };
+                (kinds, Some(line))
+            } else {
+                continue;
+            };
+
+            let kinds = kinds
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .flat_map(WarningKind::from_directive);
+            match line {
+                Some(line) => directives.lines.entry(line).or_default().extend(kinds),
+                None => directives.file.extend(kinds),
+            }
+        }
+
+        directives
+    }
+
+    fn allows(&self, warning: &Warning) -> bool {
+        self.file.contains(&warning.kind)
+            || self
+                .lines
+                .get(&warning.position.line)
+                .is_some_and(|kinds| kinds.contains(&warning.kind))
+    }
+}
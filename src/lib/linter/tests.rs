@@ -0,0 +1,92 @@
+use super::*;
+
+fn lint(src: &str) -> Vec<(&'static str, usize)> {
+    lint_source(src)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .iter()
+        .map(|warning| (warning.kind.code(), warning.position.line))
+        .collect()
+}
+
+#[test]
+fn warns_about_unused_locals() {
+    let src =
+        "var global = 1;\nfun f(used, unused, _ignored) {\n    var local = used;\n}\nf(1, 2, 3);";
+
+    assert_eq!(lint(src), [("W001", 2), ("W001", 3)]);
+}
+
+#[test]
+fn warns_about_shadowing() {
+    let src = "var a = 1;\n{\n    var a = 2;\n    {\n        var a = 3;\n    }\n    print a;\n}";
+
+    let warnings = lint_source(src).unwrap();
+    assert_eq!(warnings[0].kind, WarningKind::ShadowedVariable);
+    assert_eq!(
+        warnings[0].message,
+        "Variable 'a' shadows the one declared at line 1, column 5"
+    );
+    assert_eq!(lint(src), [("W002", 3), ("W001", 5), ("W002", 5)]);
+}
+
+#[test]
+fn warns_about_unreachable_code() {
+    let src = "fun f(x) {\n    if (x) {\n        return 1;\n    } else {\n        return 2;\n    }\n    print x;\n    print x;\n}\nwhile (f(1)) {\n    break;\n    print 3;\n}";
+
+    assert_eq!(lint(src), [("W003", 7), ("W003", 12)]);
+}
+
+#[test]
+fn warns_about_self_assignment() {
+    assert_eq!(lint("var a = 1;\na = a;\na = a + 1;"), [("W004", 2)]);
+}
+
+#[test]
+fn warns_about_constant_conditions() {
+    let src = "if (1 > 2) print 1;\nwhile (true) break;\nwhile (!nil) break;\nfor (;false;) {}";
+
+    let warnings = lint_source(src).unwrap();
+    assert_eq!(
+        warnings[0].message,
+        "The condition of this 'if' is always false"
+    );
+    assert_eq!(lint(src), [("W005", 1), ("W005", 3), ("W005", 4)]);
+}
+
+#[test]
+fn warns_about_wrong_arity() {
+    let src =
+        "fun add(a, b) { return a + b; }\nadd(1);\nadd(1, 2);\nclock(1);\nvar f = add;\nf(1);";
+
+    let warnings = lint_source(src).unwrap();
+    assert_eq!(
+        warnings[0].message,
+        "'add' takes 2 arguments but is called with 1"
+    );
+    assert_eq!(lint(src), [("W006", 2), ("W006", 4)]);
+}
+
+#[test]
+fn warns_about_top_level_return() {
+    assert_eq!(
+        lint("fun f() { return 2; }\nvar g = () => { return 3; };\nreturn 1;"),
+        [("W007", 3)]
+    );
+}
+
+#[test]
+fn allows_warnings_with_directives() {
+    let src = "fun f(a) { // lint-allow: W001\n    var b = 1;\n}\n// lint-allow: unused-variable shadowed-variable\nfun g(f) {}\nfun h(c) {}";
+    assert_eq!(lint(src), [("W001", 2), ("W001", 6)]);
+
+    let src = "/* lint-allow-file: all */\nreturn 1;\nfun f(a) {}";
+    assert_eq!(lint(src), []);
+}
+
+#[test]
+fn lints_the_examples_without_errors() {
+    for example in ["fun_decl", "lambda", "interpolation", "for_stmt"] {
+        let src = crate::utils::read_file(&format!("examples/{example}.lox"));
+        lint_source(&src).unwrap_or_else(|e| panic!("{example}:\n{e}"));
+    }
+}
@@ -1,9 +1,13 @@
+use std::rc::Rc;
+
 use crate::token_type::TokenType;
+use crate::types::Type;
 
 use super::{callable::Callable, function::Function};
 
 use parse_display::Display;
 
+/// A Lox value, cloning one is cheap as strings and functions are shared.
 #[derive(Debug, Display, Clone)]
 pub enum LoxValue {
     #[display("{0}")]
@@ -11,11 +15,11 @@ pub enum LoxValue {
     #[display("{0}")]
     Number(f64),
     #[display("{0}")]
-    String(String),
+    String(Rc<str>),
     #[display("{0}")]
     Function(Function),
     #[display("{0}")]
-    Identifier(String),
+    Identifier(Rc<str>),
     #[display("nil")]
     Nil,
 }
@@ -33,8 +37,20 @@ impl LoxValue {
     }
 
     pub fn is_nil(&self) -> bool {
-        match self {
-            Self::Nil => true,
+        matches!(self, Self::Nil)
+    }
+
+    /// Checks if two values are equal, values of different types are never equal and
+    /// functions are only equal to themselves.
+    pub fn is_equal(&self, other: &LoxValue) -> bool {
+        use LoxValue::*;
+
+        match (self, other) {
+            (Boolean(l), Boolean(r)) => l == r,
+            (Number(l), Number(r)) => l == r,
+            (String(l), String(r)) => l == r,
+            (Function(l), Function(r)) => l == r,
+            (Nil, Nil) => true,
             _ => false,
         }
     }
@@ -52,16 +68,16 @@ impl LoxValue {
         }
     }
 
-    pub fn to_string(&self) -> String {
+    /// Returns the type of the value, functions have the type their annotations give them.
+    pub fn type_of(&self) -> Type {
         use LoxValue::*;
 
         match self {
-            Number(n) => n.to_string(),
-            String(s) => s.clone(),
-            Boolean(b) => b.to_string(),
-            Function(f) => format!("{f}"),
-            Identifier(_) => panic!("You can't concatenate an identifier stupid."),
-            Nil => "nil".to_string(),
+            Boolean(_) => Type::Bool,
+            Number(_) => Type::Number,
+            String(_) | Identifier(_) => Type::String,
+            Nil => Type::Nil,
+            Function(fun) => fun.type_of(),
         }
     }
 
@@ -0,0 +1,253 @@
+use serde_json::{json, Value};
+
+use crate::interpreter::resolver::{Resolver, Symbol, SymbolKind, SymbolTable};
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::scanner::{error::ScanError, Scanner};
+use crate::token::{Position, Token};
+use crate::token_type::TokenType;
+
+/// A problem found while analysing a document.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub position: Position,
+    pub message: String,
+}
+
+/// An open `.lox` file together with everything the scanner, parser and resolver found in it.
+pub struct Document {
+    lines: Vec<Vec<char>>,
+    tokens: Vec<Token>,
+    /// The positions of each matching '{' and '}', used to find the extent of scopes.
+    braces: Vec<(Position, Position)>,
+    pub symbols: SymbolTable,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Document {
+    pub fn new(text: &str) -> Document {
+        let mut diagnostics = Vec::new();
+
+        let mut scanner = Scanner::new(text);
+        if let Err(ScanError::Bundle(errors)) = scanner.scan_tokens() {
+            diagnostics.extend(errors.iter().map(diagnostic));
+        }
+        let tokens = scanner.tokens;
+
+        let (statements, errors) = Parser::new(tokens.clone()).program_with_errors();
+        diagnostics.extend(errors.iter().map(|e| Diagnostic {
+            position: e.position().unwrap_or(Position::new(1, 1)),
+            message: e.to_string(),
+        }));
+
+        let mut interpreter = Interpreter::new();
+        let mut resolver = Resolver::with_symbols(&mut interpreter);
+        if let Err(e) = resolver.resolve_program(&statements) {
+            diagnostics.push(Diagnostic {
+                position: e.position().unwrap_or(Position::new(1, 1)),
+                message: e.to_string(),
+            });
+        }
+        let symbols = resolver.into_symbols();
+
+        Document {
+            lines: text
+                .split('\n')
+                .map(|line| line.chars().collect())
+                .collect(),
+            braces: match_braces(&tokens),
+            tokens,
+            symbols,
+            diagnostics,
+        }
+    }
+
+    /// Converts a position to an LSP position, which is 0 based and counts UTF-16 code units.
+    pub fn to_lsp(&self, position: Position) -> Value {
+        let line = position.line.saturating_sub(1);
+        let character: usize = self
+            .lines
+            .get(line)
+            .map(|chars| {
+                chars
+                    .iter()
+                    .take(position.column.saturating_sub(1))
+                    .map(|c| c.len_utf16())
+                    .sum()
+            })
+            .unwrap_or(0);
+
+        json!({ "line": line, "character": character })
+    }
+
+    /// Converts an LSP position to a position in the document.
+    pub fn position_of(&self, position: &Value) -> Position {
+        let line = position["line"].as_u64().unwrap_or(0) as usize;
+        let character = position["character"].as_u64().unwrap_or(0) as usize;
+
+        let mut units = 0;
+        let mut column = 1;
+        for c in self.lines.get(line).into_iter().flatten() {
+            if units >= character {
+                break;
+            }
+            units += c.len_utf16();
+            column += 1;
+        }
+
+        Position::new(line + 1, column)
+    }
+
+    /// Returns the LSP range covering `length` characters from `position`.
+    pub fn range(&self, position: Position, length: usize) -> Value {
+        let end = Position::new(position.line, position.column + length);
+
+        json!({ "start": self.to_lsp(position), "end": self.to_lsp(end) })
+    }
+
+    /// Returns the symbol whose declaration or one of its references is under `position`.
+    pub fn symbol_at(&self, position: Position) -> Option<&Symbol> {
+        self.symbols.symbols.iter().find(|symbol| {
+            std::iter::once(&symbol.declaration)
+                .chain(symbol.references.iter())
+                .any(|p| covers(*p, &symbol.name, position))
+        })
+    }
+
+    /// Returns the name of a builtin used under `position`.
+    pub fn unresolved_at(&self, position: Position) -> Option<&str> {
+        self.symbols
+            .unresolved
+            .iter()
+            .find(|(name, p)| covers(*p, name, position))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the symbols that can be referred to at `position`.
+    pub fn visible_symbols(&self, position: Position) -> Vec<&Symbol> {
+        self.symbols
+            .symbols
+            .iter()
+            .filter(|symbol| {
+                symbol.depth == 0
+                    || (symbol.declaration < position
+                        && self.scope_end(symbol).is_some_and(|end| position <= end))
+            })
+            .collect()
+    }
+
+    /// Finds where the scope a local is declared in ends.
+    fn scope_end(&self, symbol: &Symbol) -> Option<Position> {
+        let index = self
+            .tokens
+            .iter()
+            .position(|token| token.position == symbol.declaration)?;
+
+        // Parameters and 'for' loop variables are declared in the header before their scope.
+        let is_parameter = symbol.kind == SymbolKind::Parameter;
+        let in_header = is_parameter
+            || (index >= 2 && self.tokens[index - 2].token_type == TokenType::LeftParen);
+
+        if in_header {
+            let mut body = self.header_end(index)? + 1;
+            // Skip a function's return type and an arrow function's '=>'.
+            if is_parameter {
+                body += self.tokens[body..].iter().position(|token| {
+                    matches!(token.token_type, TokenType::LeftBrace | TokenType::Arrow)
+                })?;
+                if self.tokens[body].token_type == TokenType::Arrow {
+                    body += 1;
+                }
+            }
+
+            match self.tokens.get(body)?.token_type {
+                TokenType::LeftBrace => self.closing_brace(self.tokens[body].position),
+                // An arrow function's expression or a loop's statement without braces.
+                _ => self.end_without_braces(body),
+            }
+        } else {
+            self.braces
+                .iter()
+                .filter(|(open, close)| *open < symbol.declaration && symbol.declaration < *close)
+                .max_by_key(|(open, _)| *open)
+                .map(|(_, close)| *close)
+        }
+    }
+
+    /// Returns the index of the ')' closing the parentheses the token at `index` is in.
+    fn header_end(&self, index: usize) -> Option<usize> {
+        let mut depth = 0;
+
+        for (offset, token) in self.tokens[index..].iter().enumerate() {
+            match token.token_type {
+                TokenType::LeftParen => depth += 1,
+                TokenType::RightParen if depth == 0 => return Some(index + offset),
+                TokenType::RightParen => depth -= 1,
+                _ => (),
+            }
+        }
+
+        None
+    }
+
+    fn closing_brace(&self, open: Position) -> Option<Position> {
+        self.braces
+            .iter()
+            .find(|(start, _)| *start == open)
+            .map(|(_, close)| *close)
+    }
+
+    /// Finds where an expression or a statement without braces starting at `index` ends:
+    /// at its ';', or at the ',' or bracket that closes what it's in.
+    fn end_without_braces(&self, index: usize) -> Option<Position> {
+        let mut depth = 0;
+
+        for token in &self.tokens[index..] {
+            match token.token_type {
+                TokenType::LeftParen | TokenType::LeftBrace => depth += 1,
+                TokenType::RightParen | TokenType::RightBrace if depth > 0 => depth -= 1,
+                TokenType::Semicolon
+                | TokenType::Comma
+                | TokenType::RightParen
+                | TokenType::RightBrace
+                | TokenType::Eof => return Some(token.position),
+                _ => (),
+            }
+        }
+
+        None
+    }
+}
+
+fn diagnostic(error: &ScanError) -> Diagnostic {
+    Diagnostic {
+        position: error.position().unwrap_or(Position::new(1, 1)),
+        message: error.to_string(),
+    }
+}
+
+/// Checks if `position` is inside the identifier `name` starting at `start`.
+fn covers(start: Position, name: &str, position: Position) -> bool {
+    start.line == position.line
+        && start.column <= position.column
+        && position.column <= start.column + name.chars().count()
+}
+
+fn match_braces(tokens: &[Token]) -> Vec<(Position, Position)> {
+    let mut braces = Vec::new();
+    let mut open = Vec::new();
+
+    for token in tokens {
+        match token.token_type {
+            TokenType::LeftBrace => open.push(token.position),
+            TokenType::RightBrace => {
+                if let Some(start) = open.pop() {
+                    braces.push((start, token.position));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    braces
+}
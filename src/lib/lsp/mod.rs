@@ -0,0 +1,298 @@
+//! A language server for Lox, spoken over stdio.
+//!
+//! Documents are re-analysed with the scanner, parser and resolver every time
+//! they change, the resulting `SymbolTable` answers every other request.
+
+mod document;
+#[cfg(test)]
+mod tests;
+pub mod transport;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Value};
+
+use crate::interpreter::resolver::SymbolKind;
+use crate::interpreter::Interpreter;
+use crate::scanner::Scanner;
+use crate::token::Position;
+use document::Document;
+use transport::{read_message, write_message};
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_REQUEST: i64 = -32600;
+
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+}
+
+/// Runs the language server on stdin and stdout until the client exits.
+pub fn run_stdio() -> io::Result<()> {
+    Server::new().run(&mut io::stdin().lock(), &mut io::stdout().lock())
+}
+
+impl Server {
+    pub fn new() -> Server {
+        Server::default()
+    }
+
+    /// Handles messages from `reader` until an 'exit' notification or the end of the input.
+    pub fn run(&mut self, reader: &mut impl BufRead, writer: &mut impl Write) -> io::Result<()> {
+        while let Some(message) = read_message(reader)? {
+            let message = match message {
+                Ok(message) => message,
+                Err(e) => {
+                    write_message(writer, &error(&Value::Null, PARSE_ERROR, &e.to_string()))?;
+                    continue;
+                }
+            };
+
+            let method = message["method"].as_str().unwrap_or_default();
+            if method == "exit" {
+                break;
+            }
+
+            for response in self.handle(method, &message) {
+                write_message(writer, &response)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the messages to send back in reply to `message`.
+    fn handle(&mut self, method: &str, message: &Value) -> Vec<Value> {
+        let params = &message["params"];
+
+        // Notifications have no id and never get a response.
+        let Some(id) = message.get("id") else {
+            return self.notify(method, params).into_iter().collect();
+        };
+
+        if self.shutdown {
+            return vec![error(id, INVALID_REQUEST, "The server is shutting down")];
+        }
+
+        let result = match method {
+            "initialize" => capabilities(),
+            "shutdown" => {
+                self.shutdown = true;
+                Value::Null
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/completion" => self.completion(params),
+            _ => {
+                return vec![error(
+                    id,
+                    METHOD_NOT_FOUND,
+                    &format!("Unknown method {method}"),
+                )]
+            }
+        };
+
+        vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })]
+    }
+
+    fn notify(&mut self, method: &str, params: &Value) -> Option<Value> {
+        let uri = params["textDocument"]["uri"].as_str()?.to_string();
+
+        let text = match method {
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str()?,
+            // Only full document sync is supported so the last change holds the whole text.
+            "textDocument/didChange" => {
+                params["contentChanges"].as_array()?.last()?["text"].as_str()?
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return Some(publish_diagnostics(&uri, Vec::new()));
+            }
+            _ => return None,
+        };
+
+        let document = Document::new(text);
+        let diagnostics = document
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                json!({
+                    "range": document.range(diagnostic.position, 1),
+                    "severity": 1,
+                    "source": "lox_one",
+                    "message": diagnostic.message,
+                })
+            })
+            .collect();
+        self.documents.insert(uri.clone(), document);
+
+        Some(publish_diagnostics(&uri, diagnostics))
+    }
+
+    /// Finds the document and position a request is about.
+    fn locate<'a>(&'a self, params: &'a Value) -> Option<(&'a str, &'a Document, Position)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let document = self.documents.get(uri)?;
+
+        Some((uri, document, document.position_of(&params["position"])))
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let Some((uri, document, position)) = self.locate(params) else {
+            return Value::Null;
+        };
+
+        match document.symbol_at(position) {
+            Some(symbol) => json!({
+                "uri": uri,
+                "range": document.range(symbol.declaration, symbol.name.chars().count()),
+            }),
+            None => Value::Null,
+        }
+    }
+
+    fn references(&self, params: &Value) -> Value {
+        let Some((uri, document, position)) = self.locate(params) else {
+            return Value::Null;
+        };
+        let Some(symbol) = document.symbol_at(position) else {
+            return json!([]);
+        };
+
+        let length = symbol.name.chars().count();
+        let include_declaration = params["context"]["includeDeclaration"]
+            .as_bool()
+            .unwrap_or(true);
+
+        include_declaration
+            .then_some(&symbol.declaration)
+            .into_iter()
+            .chain(symbol.references.iter())
+            .map(|p| json!({ "uri": uri, "range": document.range(*p, length) }))
+            .collect()
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let Some((_, document, position)) = self.locate(params) else {
+            return Value::Null;
+        };
+
+        let contents = if let Some(symbol) = document.symbol_at(position) {
+            match symbol.kind {
+                SymbolKind::Function => format!(
+                    "fun {}({}) — arity {}",
+                    symbol.name,
+                    symbol.params.join(", "),
+                    symbol.params.len()
+                ),
+                SymbolKind::Parameter => format!("(parameter) {}", symbol.name),
+                SymbolKind::Variable => format!("var {}", symbol.name),
+            }
+        } else if let Some(name) = document.unresolved_at(position) {
+            match Interpreter::new()
+                .globals
+                .get(name)
+                .and_then(|v| v.as_callable())
+            {
+                Some(native) => format!("native fun {name}() — arity {}", native.arity()),
+                None => return Value::Null,
+            }
+        } else {
+            return Value::Null;
+        };
+
+        json!({ "contents": { "kind": "plaintext", "value": contents } })
+    }
+
+    fn document_symbols(&self, params: &Value) -> Value {
+        let Some(document) = params["textDocument"]["uri"]
+            .as_str()
+            .and_then(|uri| self.documents.get(uri))
+        else {
+            return Value::Null;
+        };
+
+        document
+            .symbols
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.kind == SymbolKind::Function)
+            .map(|symbol| {
+                let range = document.range(symbol.declaration, symbol.name.chars().count());
+                json!({
+                    "name": symbol.name,
+                    "detail": format!("({})", symbol.params.join(", ")),
+                    "kind": 12,
+                    "range": range,
+                    "selectionRange": range,
+                })
+            })
+            .collect()
+    }
+
+    fn completion(&self, params: &Value) -> Value {
+        let Some((_, document, position)) = self.locate(params) else {
+            return Value::Null;
+        };
+
+        let mut keywords: Vec<&str> = Scanner::new("").keywords.into_keys().collect();
+        keywords.sort();
+        let keywords = keywords
+            .into_iter()
+            .map(|keyword| json!({ "label": keyword, "kind": 14 }));
+
+        let mut natives = Interpreter::new().globals.names();
+        natives.sort();
+        let natives = natives
+            .into_iter()
+            .map(|name| json!({ "label": name, "kind": 3 }));
+
+        let symbols = document
+            .visible_symbols(position)
+            .into_iter()
+            .map(|symbol| {
+                let kind = match symbol.kind {
+                    SymbolKind::Function => 3,
+                    _ => 6,
+                };
+                json!({ "label": symbol.name, "kind": kind })
+            });
+
+        symbols.chain(natives).chain(keywords).collect()
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": 1,
+            "definitionProvider": true,
+            "referencesProvider": true,
+            "hoverProvider": true,
+            "documentSymbolProvider": true,
+            "completionProvider": {},
+        },
+        "serverInfo": { "name": "lox_one", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+fn error(id: &Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
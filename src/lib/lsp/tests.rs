@@ -0,0 +1,295 @@
+use std::io::Cursor;
+
+use serde_json::{json, Value};
+
+use super::transport::{read_message, write_message};
+use super::Server;
+
+const URI: &str = "file:///test.lox";
+
+/// Plays `messages` to a server as a client would and returns everything it sent back.
+fn run_session(messages: &[Value]) -> Vec<Value> {
+    let mut input = Vec::new();
+    for message in messages {
+        write_message(&mut input, message).unwrap();
+    }
+
+    run_input(input)
+}
+
+/// Runs a server on raw `input` and returns everything it sent back.
+fn run_input(input: Vec<u8>) -> Vec<Value> {
+    let mut output = Vec::new();
+    Server::new()
+        .run(&mut Cursor::new(input), &mut output)
+        .unwrap();
+
+    let mut output = Cursor::new(output);
+    let mut responses = Vec::new();
+    while let Some(response) = read_message(&mut output).unwrap() {
+        responses.push(response.unwrap());
+    }
+
+    responses
+}
+
+fn open(text: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": {
+            "textDocument": { "uri": URI, "languageId": "lox", "version": 1, "text": text },
+        },
+    })
+}
+
+fn request(id: i64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+fn at(line: usize, character: usize) -> Value {
+    json!({
+        "textDocument": { "uri": URI },
+        "position": { "line": line, "character": character },
+    })
+}
+
+/// Opens `text` then sends a single request and returns its result.
+fn result_of(text: &str, method: &str, params: Value) -> Value {
+    let responses = run_session(&[open(text), request(1, method, params)]);
+
+    responses
+        .into_iter()
+        .find(|response| response["id"] == 1)
+        .unwrap_or_else(|| panic!("No response to {method}"))["result"]
+        .clone()
+}
+
+fn range(line: usize, start: usize, end: usize) -> Value {
+    json!({
+        "start": { "line": line, "character": start },
+        "end": { "line": line, "character": end },
+    })
+}
+
+#[test]
+fn replies_to_malformed_messages_and_keeps_going() {
+    let mut input = b"Content-Length: 9\r\n\r\n{bad json".to_vec();
+    input.extend_from_slice(b"Content-Type: application/vscode-jsonrpc\r\n\r\n");
+    write_message(
+        &mut input,
+        &request(1, "initialize", json!({ "capabilities": {} })),
+    )
+    .unwrap();
+
+    let responses = run_input(input);
+
+    assert_eq!(responses.len(), 3, "{responses:?}");
+    assert_eq!(responses[0]["id"], Value::Null);
+    assert_eq!(responses[0]["error"]["code"], -32700);
+    assert_eq!(responses[1]["error"]["code"], -32700);
+    assert_eq!(responses[2]["id"], 1);
+    assert!(responses[2]["result"]["capabilities"].is_object());
+}
+
+#[test]
+fn initializes_and_shuts_down() {
+    let responses = run_session(&[
+        request(1, "initialize", json!({ "capabilities": {} })),
+        json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+        request(2, "shutdown", Value::Null),
+        request(3, "textDocument/hover", at(0, 0)),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+        request(4, "shutdown", Value::Null),
+    ]);
+
+    assert_eq!(responses.len(), 3);
+    let capabilities = &responses[0]["result"]["capabilities"];
+    assert_eq!(capabilities["definitionProvider"], true);
+    assert_eq!(capabilities["textDocumentSync"], 1);
+    assert_eq!(responses[1]["result"], Value::Null);
+    assert_eq!(responses[2]["error"]["code"], -32600);
+}
+
+#[test]
+fn rejects_unknown_methods() {
+    let responses = run_session(&[request(1, "textDocument/rename", at(0, 0))]);
+
+    assert_eq!(responses[0]["error"]["code"], -32601);
+}
+
+#[test]
+fn publishes_diagnostics() {
+    let responses = run_session(&[open("var a = 1;\nprint a +;\nvar b = \"oops;")]);
+
+    let params = &responses[0]["params"];
+    assert_eq!(responses[0]["method"], "textDocument/publishDiagnostics");
+    assert_eq!(params["uri"], URI);
+
+    let diagnostics = params["diagnostics"].as_array().unwrap();
+    assert!(diagnostics.iter().any(|d| d["message"]
+        .as_str()
+        .unwrap()
+        .contains("Unterminated string")));
+    assert!(diagnostics.iter().any(|d| d["range"]["start"]["line"] == 1));
+}
+
+#[test]
+fn publishes_resolver_diagnostics() {
+    let responses = run_session(&[open("{\n  var a = a;\n}")]);
+
+    let diagnostics = responses[0]["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+}
+
+#[test]
+fn clears_diagnostics_once_fixed() {
+    let change = json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didChange",
+        "params": {
+            "textDocument": { "uri": URI, "version": 2 },
+            "contentChanges": [{ "text": "print 1;" }],
+        },
+    });
+    let responses = run_session(&[open("print 1"), change]);
+
+    assert_eq!(
+        responses[0]["params"]["diagnostics"]
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(responses[1]["params"]["diagnostics"], json!([]));
+}
+
+#[test]
+fn goes_to_definition() {
+    let source = "var count = 0;\nfun add(n) {\n  count = count + n;\n}\nadd(2);";
+
+    assert_eq!(
+        result_of(source, "textDocument/definition", at(2, 18)),
+        json!({ "uri": URI, "range": range(1, 8, 9) })
+    );
+    assert_eq!(
+        result_of(source, "textDocument/definition", at(2, 11)),
+        json!({ "uri": URI, "range": range(0, 4, 9) })
+    );
+    assert_eq!(
+        result_of(source, "textDocument/definition", at(4, 1)),
+        json!({ "uri": URI, "range": range(1, 4, 7) })
+    );
+}
+
+#[test]
+fn finds_references() {
+    let source = "var a = 1;\n{\n  var a = 2;\n  print a;\n}\nprint a + a;";
+    let mut params = at(5, 6);
+    params["context"] = json!({ "includeDeclaration": false });
+
+    let references = result_of(source, "textDocument/references", params);
+    let lines: Vec<&Value> = references
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|location| &location["range"]["start"]["line"])
+        .collect();
+    assert_eq!(lines, [&json!(5), &json!(5)]);
+
+    let mut params = at(3, 8);
+    params["context"] = json!({ "includeDeclaration": true });
+    let references = result_of(source, "textDocument/references", params);
+    assert_eq!(
+        references,
+        json!([
+            { "uri": URI, "range": range(2, 6, 7) },
+            { "uri": URI, "range": range(3, 8, 9) },
+        ])
+    );
+}
+
+#[test]
+fn hovers_with_arity() {
+    let source = "fun add(a, b) { return a + b; }\nprint add(1, 2) + clock();";
+
+    let hover = result_of(source, "textDocument/hover", at(1, 7));
+    assert_eq!(hover["contents"]["value"], "fun add(a, b) — arity 2");
+
+    let hover = result_of(source, "textDocument/hover", at(1, 19));
+    assert_eq!(hover["contents"]["value"], "native fun clock() — arity 0");
+
+    let hover = result_of(source, "textDocument/hover", at(0, 3));
+    assert_eq!(hover, Value::Null);
+}
+
+#[test]
+fn lists_function_symbols() {
+    let source = "var x = 1;\nfun outer() {\n  fun inner(a) {}\n}";
+
+    let symbols = result_of(source, "textDocument/documentSymbol", at(0, 0));
+    let names: Vec<&Value> = symbols
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| &symbol["name"])
+        .collect();
+    assert_eq!(names, [&json!("outer"), &json!("inner")]);
+    assert_eq!(symbols[1]["kind"], 12);
+    assert_eq!(symbols[1]["detail"], "(a)");
+}
+
+#[test]
+fn completes_identifiers_in_scope() {
+    let source = "var top = 1;\nfun f(param) {\n  var local = 2;\n  \n}\n";
+
+    let labels = |line, character| -> Vec<String> {
+        result_of(source, "textDocument/completion", at(line, character))
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["label"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let inside = labels(3, 2);
+    for name in ["top", "f", "param", "local", "clock", "while"] {
+        assert!(inside.contains(&name.to_string()), "{name} in {inside:?}");
+    }
+
+    let outside = labels(5, 0);
+    assert!(outside.contains(&"top".to_string()));
+    assert!(!outside.contains(&"param".to_string()));
+    assert!(!outside.contains(&"local".to_string()));
+}
+
+#[test]
+fn scopes_parameters_of_arrow_functions_to_their_expression() {
+    let source = "var f = (x) => x + 1;\n{ var z = 2; print z; }\nvar g = (y) => y;";
+
+    let labels = |line, character| -> Vec<String> {
+        result_of(source, "textDocument/completion", at(line, character))
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["label"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    assert!(labels(0, 16).contains(&"x".to_string()));
+    let block = labels(1, 13);
+    assert!(block.contains(&"z".to_string()), "{block:?}");
+    assert!(!block.contains(&"x".to_string()), "{block:?}");
+    assert!(labels(2, 15).contains(&"y".to_string()));
+}
+
+#[test]
+fn counts_positions_in_utf16() {
+    let source = "var s = \"😀\"; var é = 1;\nprint é;";
+
+    assert_eq!(
+        result_of(source, "textDocument/definition", at(1, 6)),
+        json!({ "uri": URI, "range": range(0, 18, 19) })
+    );
+}
@@ -0,0 +1,63 @@
+//! The base protocol shared by the language server and the debug adapter:
+//! JSON messages preceded by a `Content-Length` header.
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value;
+use thiserror::Error;
+
+/// A message that was read whole but isn't valid, the messages after it can still be read.
+#[derive(Debug, Error)]
+#[error("{0}")]
+pub struct MalformedMessage(String);
+
+/// Reads the next message, returns `None` once the input is closed. Only failing to
+/// read the input is an `io::Error`.
+pub fn read_message(
+    reader: &mut impl BufRead,
+) -> io::Result<Option<Result<Value, MalformedMessage>>> {
+    let mut content_length: Option<usize> = None;
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse().ok();
+            }
+        }
+    }
+
+    let Some(content_length) = content_length else {
+        return Ok(Some(Err(MalformedMessage(
+            "Missing Content-Length header".to_string(),
+        ))));
+    };
+
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+
+    Ok(Some(
+        serde_json::from_slice(&content).map_err(|e| MalformedMessage(e.to_string())),
+    ))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    writer.flush()
+}
@@ -0,0 +1,160 @@
+//! An optional pass that simplifies a program before it runs.
+//!
+//! Constant arithmetic, comparisons, string concatenation and interpolation
+//! are folded into literals, ternaries, `and` / `or`, `if` and `while` with a
+//! constant condition keep only what can run, and statements after a `return`
+//! in the same block are dropped.
+//!
+//! An expression that would fail when it runs, like `1 / 0`, is left as it is
+//! so that the program still fails there with the same error. Folded code no
+//! longer reports branches to hooks, so coverage shouldn't be recorded for an
+//! optimized program.
+
+#[cfg(test)]
+mod tests;
+
+use crate::ast::{walk_expr_mut, walk_stmt_mut, walk_stmts_mut, Expr, Stmt, VisitorMut};
+use crate::interpreter::Interpreter;
+use crate::lox_value::LoxValue;
+use crate::token_type::TokenType;
+
+/// Optimizes a program in place.
+pub fn optimize(statements: &mut Vec<Stmt>) {
+    Optimizer::default().visit_stmts_mut(statements);
+}
+
+#[derive(Default)]
+struct Optimizer {
+    /// Evaluates the expressions being folded, they only ever contain literals.
+    interpreter: Interpreter,
+}
+
+impl Optimizer {
+    /// Evaluates an expression made up of literals, `None` if it fails.
+    fn fold(&mut self, expr: &Expr) -> Option<Expr> {
+        let value = self.interpreter.evaluate(expr).ok()?;
+
+        Some(Expr::Value {
+            value,
+            position: expr.start_position(),
+        })
+    }
+
+    fn fold_binary(&mut self, expr: &Expr) -> Option<Expr> {
+        let Expr::Binary { lhs, op, rhs, .. } = expr else {
+            return None;
+        };
+        if !is_literal(lhs) {
+            return None;
+        }
+
+        match (&op.token_type, &**lhs) {
+            // The right side only runs when the left side doesn't decide.
+            (TokenType::And | TokenType::Or, lhs) => {
+                let truthy = truthiness(lhs);
+                (truthy == (op.token_type == TokenType::Or)).then(|| Expr::Value {
+                    value: LoxValue::Boolean(truthy),
+                    position: lhs.get_position(),
+                })
+            }
+            // Repeating a string is left for when it runs, it could be huge.
+            (
+                TokenType::Star,
+                Expr::Value {
+                    value: LoxValue::String(_),
+                    ..
+                },
+            ) => None,
+            _ if is_literal(rhs) => self.fold(expr),
+            _ => None,
+        }
+    }
+}
+
+impl VisitorMut for Optimizer {
+    fn visit_stmts_mut(&mut self, stmts: &mut Vec<Stmt>) {
+        walk_stmts_mut(self, stmts);
+
+        if let Some(index) = stmts
+            .iter()
+            .position(|stmt| matches!(stmt, Stmt::ReturnStmt { .. }))
+        {
+            stmts.truncate(index + 1);
+        }
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        use Stmt::*;
+
+        walk_stmt_mut(self, stmt);
+
+        let position = stmt.get_position();
+        *stmt = match std::mem::replace(stmt, Block(Vec::new(), position)) {
+            IfStmt {
+                condition,
+                then_branch,
+                else_branch,
+                position,
+            } if is_literal(&condition) => {
+                if truthiness(&condition) {
+                    *then_branch
+                } else {
+                    else_branch.map_or(Block(Vec::new(), position), |branch| *branch)
+                }
+            }
+            WhileStmt {
+                condition,
+                position,
+                ..
+            } if is_literal(&condition) && !truthiness(&condition) => Block(Vec::new(), position),
+            stmt => stmt,
+        };
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        use Expr::*;
+
+        walk_expr_mut(self, expr);
+
+        let folded = match &*expr {
+            Grouping(inner, _) if is_literal(inner) => Some((**inner).clone()),
+            Unary { rhs, .. } if is_literal(rhs) => self.fold(expr),
+            Binary { .. } => self.fold_binary(expr),
+            Interpolation { parts, .. } if parts.iter().all(is_literal) => self.fold(expr),
+            _ => None,
+        };
+        if let Some(folded) = folded {
+            *expr = folded;
+            return;
+        }
+
+        let placeholder = Value {
+            value: LoxValue::Nil,
+            position: expr.get_position(),
+        };
+        *expr = match std::mem::replace(expr, placeholder) {
+            Ternary {
+                condition,
+                result_1,
+                result_2,
+                ..
+            } if is_literal(&condition) => {
+                if truthiness(&condition) {
+                    *result_1
+                } else {
+                    *result_2
+                }
+            }
+            expr => expr,
+        };
+    }
+}
+
+fn is_literal(expr: &Expr) -> bool {
+    matches!(expr, Expr::Value { .. })
+}
+
+/// Returns whether a literal is truthy.
+fn truthiness(expr: &Expr) -> bool {
+    matches!(expr, Expr::Value { value, .. } if value.is_truthy())
+}
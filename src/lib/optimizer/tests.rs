@@ -0,0 +1,117 @@
+use super::*;
+
+use crate::dump::program_to_sexpr;
+use crate::parser::Parser;
+use crate::utils::{read_file, Output};
+
+fn optimized(source: &str) -> String {
+    let mut statements = Parser::parse_str(source).unwrap();
+    optimize(&mut statements);
+
+    program_to_sexpr(&statements)
+}
+
+/// Runs a program, optimized or not, returning what it printed and the error it stopped with.
+fn run(source: &str, optimized: bool) -> (String, Option<String>) {
+    let mut statements = Parser::parse_str(source).unwrap();
+    if optimized {
+        optimize(&mut statements);
+    }
+
+    let output = Output::default();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(Box::new(output.clone()));
+    let error = interpreter
+        .interpret(&statements, false, false)
+        .err()
+        .map(|e| e.to_string());
+
+    let printed = output.text();
+    (printed, error)
+}
+
+#[test]
+fn folds_constant_expressions() {
+    assert_eq!(
+        optimized("print 2 * 3 + 1;\nprint (1 < 2) == !false;\nprint \"a\" + \"b\" + 1;"),
+        "(print (literal@1:7 7))\n\
+         (print (literal@2:8 true))\n\
+         (print (literal@3:7 \"ab1\"))\n"
+    );
+    assert_eq!(
+        optimized("print \"${1 + 1} and ${\"x\"}\";"),
+        "(print (literal@1:7 \"2 and x\"))\n"
+    );
+}
+
+#[test]
+fn folds_around_variables() {
+    assert_eq!(
+        optimized(
+            "var x = 1;\nprint x + 2 * 3;\nprint true or x;\nprint false and x;\nprint true and x;"
+        ),
+        "(var@1:5 x _ (literal@1:9 1))\n\
+         (print (binary@2:9 + (identifier@2:7 x) (literal@2:11 6)))\n\
+         (print (literal@3:7 true))\n\
+         (print (literal@4:7 false))\n\
+         (print (binary@5:12 and (literal@5:7 true) (identifier@5:16 x)))\n"
+    );
+}
+
+#[test]
+fn keeps_what_fails_when_it_runs() {
+    assert_eq!(
+        optimized("print 1 / 0;\nprint -\"a\";\nprint \"ab\" * 3;"),
+        "(print (binary@1:9 / (literal@1:7 1) (literal@1:11 0)))\n\
+         (print (unary@2:7 - (literal@2:8 \"a\")))\n\
+         (print (binary@3:12 * (literal@3:7 \"ab\") (literal@3:14 3)))\n"
+    );
+
+    let source = "var x = 1;\nif (x > 0) print 10 / (5 - 5);";
+    assert_eq!(run(source, true), run(source, false));
+    assert_eq!(
+        run(source, true).1.unwrap(),
+        "Division by zero error, line 2, column 21."
+    );
+}
+
+#[test]
+fn removes_dead_branches() {
+    assert_eq!(
+        optimized("if (1 > 2) print 1; else print 2;\nif (nil) print 3;\nprint 0 == 0 ? \"a\" : 1 / 0;\nwhile (false) print 4;"),
+        "(print (literal@1:32 2))\n\
+         (block@2:1)\n\
+         (print (literal@3:16 \"a\"))\n\
+         (block@4:1)\n"
+    );
+}
+
+#[test]
+fn removes_statements_after_return() {
+    assert_eq!(
+        optimized("fun f() {\n    return 1;\n    print 2;\n}"),
+        "(fun@1:5 (declaration@1:5 f () _ (block@1:9 (return@2:5 (literal@2:12 1)))))\n"
+    );
+}
+
+#[test]
+fn runs_programs_the_same() {
+    let mut paths: Vec<String> = std::fs::read_dir("tests/lox")
+        .unwrap()
+        .flat_map(|directory| std::fs::read_dir(directory.unwrap().path()).unwrap())
+        .chain(std::fs::read_dir("examples").unwrap())
+        .map(|entry| entry.unwrap().path().display().to_string())
+        .filter(|path| path.ends_with(".lox"))
+        .collect();
+    paths.sort();
+    assert!(paths.len() > 20);
+
+    for path in paths {
+        let source = read_file(&path);
+        if Parser::parse_str(&source).is_err() {
+            continue;
+        }
+
+        assert_eq!(run(&source, true), run(&source, false), "{path}");
+    }
+}
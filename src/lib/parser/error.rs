@@ -1,3 +1,4 @@
+use crate::scanner::error::ScanError;
 use crate::token::{Position, Token};
 use crate::token_type::TokenType;
 
@@ -5,13 +6,14 @@ use crate::token_type::TokenType;
 pub enum ParserError {
     Eof(Position),
     ExpectedClosingBrace(Position),
-    UnexpectedToken(Token, Position),
+    UnexpectedToken(Box<Token>, Position),
     Expected {
         found: TokenType,
         msg: String,
         position: Position,
     },
     ArgumentLimitReached(Position),
+    Scan(ScanError),
     Bundle(Vec<ParserError>),
 }
 
@@ -28,6 +30,7 @@ impl std::fmt::Display for ParserError {
                 position,
             } => write!(f, "{msg}, found '{found}' at {position}"),
             ArgumentLimitReached(p) => write!(f, "Arguments exceeded limit of 250, {p}"),
+            Scan(e) => write!(f, "{e}"),
             Bundle(errs) => {
                 for err in errs {
                     writeln!(f, "{err}")?;
@@ -39,4 +42,20 @@ impl std::fmt::Display for ParserError {
     }
 }
 
+impl ParserError {
+    /// Returns where the error happened, `None` for a bundle of errors.
+    pub fn position(&self) -> Option<Position> {
+        use ParserError::*;
+
+        match self {
+            Eof(p) | ExpectedClosingBrace(p) | UnexpectedToken(_, p) | ArgumentLimitReached(p) => {
+                Some(*p)
+            }
+            Expected { position, .. } => Some(*position),
+            Scan(e) => e.position(),
+            Bundle(_) => None,
+        }
+    }
+}
+
 pub type ParserResult<T> = Result<T, ParserError>;
@@ -7,13 +7,16 @@ pub mod error;
 #[cfg(test)]
 mod tests;
 
+use std::rc::Rc;
+
 use crate::error::{LoxError, LoxResult};
-use crate::function::FunDecl;
-use crate::scanner::Scanner;
+use crate::function::{FunDecl, Parameter};
+use crate::scanner::{error::ScanError, Scanner};
 use crate::token::{Position, Token};
 use crate::token_type::TokenType;
+use crate::types::Type;
 
-use crate::ast::{Expr, Stmt};
+use crate::ast::{Expr, ResolvedSlot, Stmt};
 use crate::lox_value::LoxValue;
 
 use error::ParserError;
@@ -56,9 +59,16 @@ impl Parser {
         Some(self.tokens[self.current - 1].clone())
     }
 
-    /// Gets the line number of the previous token.
+    /// Gets the position of the previous token, or of the first one if nothing has been consumed.
     fn position(&self) -> Position {
-        self.previous().unwrap().position
+        self.tokens[self.current.saturating_sub(1)].position
+    }
+
+    /// Returns the type of the next token, `TokenType::Eof` at the end.
+    fn peek_type(&self) -> TokenType {
+        self.peek()
+            .map(|token| token.token_type)
+            .unwrap_or(TokenType::Eof)
     }
 
     /// Return the token at current and increments self.current.
@@ -80,6 +90,14 @@ impl Parser {
         }
     }
 
+    /// Checks if token_type matches the token type of the token after the next one.
+    fn check_next(&self, token_type: &TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => &token.token_type == token_type,
+            None => false,
+        }
+    }
+
     fn check_prev(&self, token_type: &TokenType) -> bool {
         match self.previous() {
             Some(token) => &token.token_type == token_type,
@@ -153,10 +171,23 @@ impl Parser {
     }
 
     /// primary -> NUMBER | STRING | "true" | "false" | "nil"
-    ///          | "("Expr  ")" | IDENTIFIER                  ;
+    ///          | "("Expr  ")" | IDENTIFIER
+    ///          | lambda | arrowFunction | interpolation     ;
     fn primary(&mut self) -> ParserResult<Expr> {
         // let curr_token = self.peek().unwrap();
 
+        if self.matches(vec![TokenType::Interpolation]) {
+            return self.interpolation();
+        }
+
+        if self.matches(vec![TokenType::Fun]) {
+            return self.lambda();
+        }
+
+        if self.is_arrow_function() {
+            return self.arrow_function();
+        }
+
         if self.matches(vec![
             TokenType::Number,
             TokenType::String,
@@ -171,19 +202,24 @@ impl Parser {
         }
 
         if self.matches(vec![TokenType::LeftParen]) {
+            let position = self.position();
             let expr = self.expression()?;
             self.consume(
                 TokenType::RightParen,
                 "Expected accompanying  closing bracket ')'",
             )?;
 
-            return Ok(Expr::Grouping(Box::new(expr), self.position()));
+            Ok(Expr::Grouping(Box::new(expr), position))
         } else if self.matches(vec![TokenType::Identifier]) {
             let tok = self.previous().unwrap();
             let literal = tok.literal.unwrap();
 
             match literal {
-                LoxValue::Identifier(name) => Ok(Expr::Identifier(name, self.position())),
+                LoxValue::Identifier(name) => Ok(Expr::Identifier(
+                    name,
+                    self.position(),
+                    ResolvedSlot::default(),
+                )),
                 _ => Err(ParserError::Expected {
                     msg: "This should be impossible, check 'primary' parse rule.".to_string(),
                     found: tok.token_type,
@@ -193,12 +229,258 @@ impl Parser {
         } else {
             Err(ParserError::Expected {
                 msg: "Expected a number, string or boolean value ".to_string(),
-                found: self.peek().unwrap().token_type,
+                found: self.peek_type(),
                 position: self.position(),
             })
         }
     }
 
+    /// Checks if the tokens ahead are the parameter list of an arrow function, i.e. a
+    /// parenthesised list followed by "=>", with an optional return type in between.
+    fn is_arrow_function(&self) -> bool {
+        if !self.check(&TokenType::LeftParen) {
+            return false;
+        }
+
+        let Some(close) = self.closing_paren(self.current) else {
+            return false;
+        };
+
+        match self.tokens.get(close + 1).map(|token| &token.token_type) {
+            Some(TokenType::Arrow) => true,
+            Some(TokenType::Colon) => self
+                .skip_type(close + 2)
+                .and_then(|index| self.tokens.get(index))
+                .is_some_and(|token| token.token_type == TokenType::Arrow),
+            _ => false,
+        }
+    }
+
+    /// Finds the index of the ')' matching the '(' at `open`.
+    fn closing_paren(&self, open: usize) -> Option<usize> {
+        let mut depth = 0;
+
+        for (index, token) in self.tokens.iter().enumerate().skip(open) {
+            match token.token_type {
+                TokenType::LeftParen => depth += 1,
+                TokenType::RightParen if depth == 1 => return Some(index),
+                TokenType::RightParen => depth -= 1,
+                _ => (),
+            }
+        }
+
+        None
+    }
+
+    /// Returns the index of the token after the type starting at `index`, if there's one.
+    fn skip_type(&self, index: usize) -> Option<usize> {
+        let mut index = match self.tokens.get(index)?.token_type {
+            TokenType::Identifier | TokenType::Nil => index + 1,
+            TokenType::LeftParen => self.closing_paren(index)? + 1,
+            TokenType::Fun => {
+                let index = self.closing_paren(index + 1)? + 1;
+                match self.tokens.get(index)?.token_type {
+                    TokenType::Colon => self.skip_type(index + 1)?,
+                    _ => index,
+                }
+            }
+            _ => return None,
+        };
+
+        if self.tokens.get(index)?.token_type == TokenType::QuestionMark {
+            index += 1;
+        }
+
+        Some(index)
+    }
+
+    /// typeAnnotation -> ( IDENTIFIER | "nil" | "(" typeAnnotation ")"
+    ///                   | "fun" "(" ( typeAnnotation ( "," typeAnnotation )* )? ")"
+    ///                     ( ":" typeAnnotation )? ) "?"? ;
+    fn type_annotation(&mut self) -> ParserResult<Type> {
+        let ty = if self.matches(vec![TokenType::Nil]) {
+            Type::Nil
+        } else if self.matches(vec![TokenType::LeftParen]) {
+            let ty = self.type_annotation()?;
+            self.consume(TokenType::RightParen, "Expected ')' after type")?;
+            ty
+        } else if self.matches(vec![TokenType::Fun]) {
+            self.consume(TokenType::LeftParen, "Expected '(' after 'fun' in type")?;
+
+            let mut params = Vec::new();
+            if !self.matches(vec![TokenType::RightParen]) {
+                loop {
+                    params.push(self.type_annotation()?);
+                    if !self.matches(vec![TokenType::Comma]) {
+                        break;
+                    }
+                }
+                self.consume(
+                    TokenType::RightParen,
+                    "Expected ')' after parameter types in function type",
+                )?;
+            }
+
+            let ret = if self.matches(vec![TokenType::Colon]) {
+                self.type_annotation()?
+            } else {
+                Type::Any
+            };
+
+            Type::Function {
+                params,
+                ret: Box::new(ret),
+            }
+        } else {
+            let token = self.consume(TokenType::Identifier, "Expected a type")?;
+
+            match token.lexeme.as_str() {
+                "any" => Type::Any,
+                "bool" => Type::Bool,
+                "number" => Type::Number,
+                "string" => Type::String,
+                name => {
+                    return Err(ParserError::Expected {
+                        found: TokenType::Identifier,
+                        msg: format!(
+                            "Unknown type '{name}', expected any, bool, number, string, nil or a function type"
+                        ),
+                        position: token.position,
+                    })
+                }
+            }
+        };
+
+        if self.matches(vec![TokenType::QuestionMark]) {
+            Ok(ty.nilable())
+        } else {
+            Ok(ty)
+        }
+    }
+
+    /// Parses the type after a ':' if there is one.
+    fn optional_annotation(&mut self) -> ParserResult<Option<Type>> {
+        if self.matches(vec![TokenType::Colon]) {
+            self.type_annotation().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// interpolation -> ( INTERPOLATION expression )+ STRING ;
+    fn interpolation(&mut self) -> ParserResult<Expr> {
+        let position = self.position();
+        let mut parts: Vec<Expr> = Vec::new();
+
+        loop {
+            // The previous token is a string segment.
+            parts.push(Expr::Value {
+                value: self.previous().unwrap().literal.unwrap(),
+                position: self.position(),
+            });
+
+            if self.check_prev(&TokenType::String) {
+                break;
+            }
+
+            // With nothing between the braces the segment after them, which starts with '}',
+            // would be parsed as the expression.
+            if let Some(token) = self.peek().filter(|token| {
+                matches!(
+                    token.token_type,
+                    TokenType::String | TokenType::Interpolation
+                ) && token.lexeme.starts_with('}')
+            }) {
+                return Err(ParserError::Expected {
+                    found: token.token_type,
+                    msg: "Expected an expression in '${}'".to_string(),
+                    position: self.position(),
+                });
+            }
+
+            parts.push(self.expression()?);
+
+            if !self.matches(vec![TokenType::Interpolation]) {
+                self.consume(
+                    TokenType::String,
+                    "Expected '}' at the end of an interpolated expression",
+                )?;
+            }
+        }
+
+        Ok(Expr::Interpolation { parts, position })
+    }
+
+    /// lambda -> "fun" "(" parameters? ")" ( ":" typeAnnotation )? block ;
+    fn lambda(&mut self) -> ParserResult<Expr> {
+        let position = self.position();
+
+        self.consume(
+            TokenType::LeftParen,
+            "Expected '(' before parameters in function expression",
+        )?;
+        let params = self.parameters()?;
+        let return_type = self.optional_annotation()?;
+        self.consume(
+            TokenType::LeftBrace,
+            "Expected '{' at the beginning of the body of a function expression",
+        )?;
+        let body = Box::new(self.block()?);
+
+        Ok(Expr::Lambda {
+            fun_declaration: Rc::new(FunDecl {
+                name: "anonymous".into(),
+                params,
+                return_type,
+                body,
+                position,
+            }),
+            position,
+        })
+    }
+
+    /// arrowFunction -> "(" parameters? ")" ( ":" typeAnnotation )? "=>" ( block | expression ) ;
+    fn arrow_function(&mut self) -> ParserResult<Expr> {
+        self.consume(
+            TokenType::LeftParen,
+            "Expected '(' before parameters in arrow function",
+        )?;
+        let position = self.position();
+        let params = self.parameters()?;
+        let return_type = self.optional_annotation()?;
+        self.consume(
+            TokenType::Arrow,
+            "Expected '=>' after parameters in arrow function",
+        )?;
+
+        // An expression body is sugar for a block that returns the expression.
+        let body = if self.matches(vec![TokenType::LeftBrace]) {
+            self.block()?
+        } else {
+            let expr = self.expression()?;
+            let position = expr.get_position();
+
+            Stmt::Block(
+                vec![Stmt::ReturnStmt {
+                    expr: Some(expr),
+                    position,
+                }],
+                position,
+            )
+        };
+
+        Ok(Expr::Lambda {
+            fun_declaration: Rc::new(FunDecl {
+                name: "anonymous".into(),
+                params,
+                return_type,
+                body: Box::new(body),
+                position,
+            }),
+            position,
+        })
+    }
+
     /// arguments -> expression ( "," expression )* ;
     fn arguments(&mut self) -> ParserResult<Vec<Expr>> {
         let mut args: Vec<Expr> = Vec::new();
@@ -251,7 +533,7 @@ impl Parser {
     fn unary(&mut self) -> ParserResult<Expr> {
         if self.matches(vec![TokenType::Bang, TokenType::Minus]) {
             let op = self.previous().unwrap();
-            let position = op.position.clone();
+            let position = op.position;
             let rhs = self.unary()?;
 
             Ok(Expr::Unary {
@@ -270,7 +552,7 @@ impl Parser {
 
         while self.matches(vec![TokenType::Slash, TokenType::Star]) {
             let op = self.previous().unwrap();
-            let position = op.position.clone();
+            let position = op.position;
             let rhs = self.factor()?;
 
             expr = Expr::Binary {
@@ -290,7 +572,7 @@ impl Parser {
 
         while self.matches(vec![TokenType::Minus, TokenType::Plus]) {
             let op = self.previous().unwrap();
-            let position = op.position.clone();
+            let position = op.position;
             let rhs = self.factor()?;
 
             expr = Expr::Binary {
@@ -315,7 +597,7 @@ impl Parser {
             TokenType::GreaterEqual,
         ]) {
             let op = self.previous().unwrap();
-            let position = op.position.clone();
+            let position = op.position;
             let rhs = self.term()?;
 
             expr = Expr::Binary {
@@ -335,7 +617,7 @@ impl Parser {
 
         while self.matches(vec![TokenType::BangEqual, TokenType::EqualEqual]) {
             let op = self.previous().unwrap();
-            let position = op.position.clone();
+            let position = op.position;
             let rhs = self.comparison()?;
 
             expr = Expr::Binary {
@@ -349,7 +631,7 @@ impl Parser {
         Ok(expr)
     }
 
-    ///! Breaks the parser "32 ;\n false ? 32 : 323;\n";
+    // ! Breaks the parser "32 ;\n false ? 32 : 323;\n";
     /// ternary -> expression ( "?" ternary ":" ternary )?
     fn ternary(&mut self) -> ParserResult<Expr> {
         let mut expr = self.equality()?;
@@ -369,7 +651,7 @@ impl Parser {
                 return Ok(expr);
             } else {
                 return Err(ParserError::Expected {
-                    found: self.peek().unwrap().token_type,
+                    found: self.peek_type(),
                     msg: "Expected colon after the second expression in a ternary expression."
                         .to_string(),
                     position: self.position(),
@@ -386,7 +668,7 @@ impl Parser {
 
         while self.matches(vec![TokenType::And]) {
             let op = self.previous().unwrap();
-            let position = op.position.clone();
+            let position = op.position;
 
             expr = Expr::Binary {
                 lhs: Box::new(expr),
@@ -405,7 +687,7 @@ impl Parser {
 
         while self.matches(vec![TokenType::Or]) {
             let op = self.previous().unwrap();
-            let position = op.position.clone();
+            let position = op.position;
 
             expr = Expr::Binary {
                 lhs: Box::new(expr),
@@ -424,14 +706,15 @@ impl Parser {
         let expr = self.logical_or()?;
 
         match &expr {
-            Expr::Identifier(name, _position) => {
+            Expr::Identifier(name, position, _) => {
                 if self.matches(vec![TokenType::Equal]) {
                     let value = Box::new(self.assignment()?);
 
                     Ok(Expr::Assignment {
                         name: name.to_owned(),
                         value,
-                        position: self.position(),
+                        position: *position,
+                        slot: ResolvedSlot::default(),
                     })
                 } else {
                     Ok(expr)
@@ -466,16 +749,18 @@ impl Parser {
 
     /// block -> "{" declaration* "}" ;
     fn block(&mut self) -> ParserResult<Stmt> {
+        let position = self.position();
         let mut declarations: Vec<Stmt> = Vec::new();
 
         while !self.matches(vec![TokenType::RightBrace]) {
             declarations.push(self.declaration()?)
         }
 
-        Ok(Stmt::Block(declarations))
+        Ok(Stmt::Block(declarations, position))
     }
 
     fn if_statement(&mut self) -> ParserResult<Stmt> {
+        let position = self.position();
         self.consume(
             TokenType::LeftParen,
             "Expected '(' before condition in an 'if' statement",
@@ -498,12 +783,13 @@ impl Parser {
             condition,
             then_branch,
             else_branch,
-            position: self.position(),
+            position,
         })
     }
 
     /// whileStmt -> "while" "(" expression ")" statement;
     fn while_statement(&mut self) -> ParserResult<Stmt> {
+        let position = self.position();
         self.consume(
             TokenType::LeftParen,
             "Expected '(' before condition in while loop",
@@ -519,7 +805,7 @@ impl Parser {
         Ok(Stmt::WhileStmt {
             condition,
             body,
-            position: self.position(),
+            position,
         })
     }
 
@@ -527,6 +813,7 @@ impl Parser {
     ///            expression? ";"
     ///            expression? ")" statement;
     fn for_statement(&mut self) -> Result<Stmt, ParserError> {
+        let position = self.position();
         self.consume(
             TokenType::LeftParen,
             "Expected '(' before condition in 'for' loop",
@@ -566,37 +853,15 @@ impl Parser {
             )?;
         }
 
-        let mut block_declarations: Vec<Stmt> = Vec::new();
-
-        if let Some(stmt) = initializer {
-            block_declarations.push(stmt)
-        }
-
-        // Parse for loop
-        let condition = if condition.is_none() {
-            Expr::Value {
-                value: LoxValue::Boolean(true),
-                position: self.position(),
-            }
-        } else {
-            condition.unwrap()
-        };
-
-        let while_body: Stmt = if let Some(increment) = increment {
-            Stmt::Block(vec![self.statement()?, Stmt::ExprStmt(increment)])
-        } else {
-            Stmt::Block(vec![self.statement()?])
-        };
+        let body = Box::new(self.statement()?);
 
-        let while_stmt = Stmt::WhileStmt {
+        Ok(Stmt::ForStmt {
+            initializer: initializer.map(Box::new),
             condition,
-            body: Box::new(while_body),
-            position: self.position(),
-        };
-
-        block_declarations.push(while_stmt);
-
-        Ok(Stmt::Block(block_declarations))
+            increment,
+            body,
+            position,
+        })
     }
 
     /// breakStmt  -> "break" ";" ;
@@ -669,9 +934,13 @@ impl Parser {
         Ok(stmt)
     }
 
-    /// varDeclaration -> "var" IDENTIFIER ("=" expression)?;
+    /// varDeclaration -> "var" IDENTIFIER ( ":" typeAnnotation )? ("=" expression)?;
     fn var_declaration(&mut self) -> ParserResult<Stmt> {
-        let ident_token = self.advance().unwrap();
+        let ident_token = match self.advance() {
+            Some(token) => token,
+            None => return Err(ParserError::Eof(self.position())),
+        };
+        let position = ident_token.position;
 
         let name = if let Some(LoxValue::Identifier(s)) = ident_token.literal {
             s
@@ -683,12 +952,14 @@ impl Parser {
             });
         };
 
+        let annotation = self.optional_annotation()?;
+
         let initializer = if self.matches(vec![TokenType::Equal]) {
             self.expression()?
         } else {
             Expr::Value {
                 value: LoxValue::Nil,
-                position: self.position(),
+                position,
             }
         };
 
@@ -699,13 +970,57 @@ impl Parser {
 
         Ok(Stmt::Var {
             name,
+            annotation,
             initializer,
-            postion: self.position(),
+            position,
         })
     }
 
-    /// IDENTIFIER "(" arguments? ")" block ;
+    /// parameters -> ( parameter ( "," parameter )* )? ")" ;
+    /// parameter  -> IDENTIFIER ( ":" typeAnnotation )? ;
+    fn parameters(&mut self) -> ParserResult<Vec<Parameter>> {
+        let mut params: Vec<Parameter> = Vec::new();
+
+        if self.matches(vec![TokenType::RightParen]) {
+            return Ok(params);
+        }
+
+        loop {
+            let token = self.consume(
+                TokenType::Identifier,
+                "Parameters in function declaration must be identifiers",
+            )?;
+            if let Some(LoxValue::Identifier(name)) = token.literal {
+                params.push(Parameter {
+                    name,
+                    annotation: self.optional_annotation()?,
+                    position: token.position,
+                });
+            }
+
+            if !self.matches(vec![TokenType::Comma]) {
+                break;
+            }
+        }
+
+        if params.len() > 250 {
+            return Err(ParserError::ArgumentLimitReached(self.position()));
+        }
+
+        self.consume(
+            TokenType::RightParen,
+            "Expected ')' after parameters in a function declaration",
+        )?;
+
+        Ok(params)
+    }
+
+    /// IDENTIFIER "(" parameters? ")" ( ":" typeAnnotation )? block ;
     fn function(&mut self) -> ParserResult<Stmt> {
+        let position = self
+            .peek()
+            .map(|token| token.position)
+            .unwrap_or(self.position());
         let name = if let LoxValue::Identifier(ident) = self.advance().unwrap().literal.unwrap() {
             ident
         } else {
@@ -721,26 +1036,8 @@ impl Parser {
             "Expected '(' before parameters in function declaration",
         )?;
 
-        let mut params: Vec<String> = Vec::new();
-        if !self.matches(vec![TokenType::RightParen]) {
-            for arg in self.arguments()? {
-                match arg {
-                    Expr::Identifier(ident, _position) => params.push(ident),
-                    _ => {
-                        return Err(ParserError::Expected {
-                            found: TokenType::Nil,
-                            msg: "Parameters in function declaration must be identifiers"
-                                .to_string(),
-                            position: self.position(),
-                        });
-                    }
-                }
-            }
-            self.consume(
-                TokenType::RightParen,
-                "Expected ')' after parameters in a function declaration",
-            )?;
-        }
+        let params = self.parameters()?;
+        let return_type = self.optional_annotation()?;
 
         self.consume(
             TokenType::LeftBrace,
@@ -749,11 +1046,17 @@ impl Parser {
 
         let body = Box::new(self.block()?);
 
-        let fun_declaration = FunDecl { name, params, body };
+        let fun_declaration = FunDecl {
+            name,
+            params,
+            return_type,
+            body,
+            position,
+        };
 
         Ok(Stmt::FunStmt {
-            fun_declaration,
-            position: self.position(),
+            fun_declaration: Rc::new(fun_declaration),
+            position,
         })
     }
 
@@ -768,7 +1071,9 @@ impl Parser {
     fn declaration(&mut self) -> ParserResult<Stmt> {
         if self.matches(vec![TokenType::Var]) {
             self.var_declaration()
-        } else if self.matches(vec![TokenType::Fun]) {
+        } else if self.check(&TokenType::Fun) && self.check_next(&TokenType::Identifier) {
+            // A 'fun' without a name starts a function expression instead.
+            self.advance();
             self.fun_declaration()
         } else {
             self.statement()
@@ -777,6 +1082,18 @@ impl Parser {
 
     /// program -> declaration* EOF
     pub fn program(&mut self) -> ParserResult<Vec<Stmt>> {
+        let (statements, errors) = self.program_with_errors();
+
+        if errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(ParserError::Bundle(errors))
+        }
+    }
+
+    /// Parses the whole program, recovering from errors so that the statements around them are
+    /// still returned. This is what tooling wants when working on code that's being edited.
+    pub fn program_with_errors(&mut self) -> (Vec<Stmt>, Vec<ParserError>) {
         let mut statements: Vec<Stmt> = Vec::new();
         let mut errors: Vec<ParserError> = Vec::new();
 
@@ -791,17 +1108,26 @@ impl Parser {
             }
         }
 
-        if errors.is_empty() {
-            Ok(statements)
-        } else {
-            Err(ParserError::Bundle(errors))
-        }
+        (statements, errors)
     }
 
+    /// Scans and parses `src`, errors from both stages are reported together in a `ParserError::Bundle`.
     pub fn parse_str(src: &str) -> LoxResult<Vec<Stmt>> {
-        let tokens = Scanner::tokens_from_str(src, false);
-        Parser::new(tokens)
-            .program()
-            .map_err(|e| LoxError::Parser(e))
+        let mut scanner = Scanner::new(src);
+
+        let mut errors: Vec<ParserError> = match scanner.scan_tokens() {
+            Ok(_) => Vec::new(),
+            Err(ScanError::Bundle(errors)) => errors.into_iter().map(ParserError::Scan).collect(),
+            Err(e) => vec![ParserError::Scan(e)],
+        };
+
+        match Parser::new(scanner.tokens).program() {
+            Ok(statements) if errors.is_empty() => return Ok(statements),
+            Ok(_) => (),
+            Err(ParserError::Bundle(parser_errors)) => errors.extend(parser_errors),
+            Err(e) => errors.push(e),
+        }
+
+        Err(LoxError::Parser(ParserError::Bundle(errors)))
     }
 }
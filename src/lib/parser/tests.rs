@@ -2,18 +2,19 @@ use super::*;
 
 use crate::parser::Parser;
 use crate::scanner::Scanner;
-use crate::utils::{log_items, read_file};
+use crate::utils::read_file;
 
 fn assert_can_parse(title: &str, src: &str, verbose: bool) -> Vec<Stmt> {
-    let tokens = Scanner::tokens_from_str(src, verbose);
-
-    let mut parser = Parser::new(tokens);
-    let statements = parser.program().unwrap_or_else(|e| {
-        println!("{e}");
+    let tokens = Scanner::tokens_from_str(src, verbose).unwrap_or_else(|e| {
+        println!("{title}:\n{e}");
         panic!()
     });
 
-    statements
+    let mut parser = Parser::new(tokens);
+    parser.program().unwrap_or_else(|e| {
+        println!("{title}:\n{e}");
+        panic!()
+    })
 }
 
 fn assert_can_parse_file(file_name: &str, verbose: bool) -> Vec<Stmt> {
@@ -102,3 +103,71 @@ fn can_parse_fun_decl_stmt() {
 // fn can_parse_error_stmt() {
 // assert_can_parse_file("errors", false);
 // }
+
+#[test]
+fn can_parse_lambda() {
+    assert_can_parse_file("lambda", false);
+}
+
+#[test]
+fn can_parse_interpolation() {
+    assert_can_parse_file("interpolation", false);
+}
+
+#[test]
+fn can_parse_strings() {
+    assert_can_parse_file("strings", false);
+}
+
+#[test]
+fn bundles_scan_errors_with_parser_errors() {
+    let errors = match Parser::parse_str("var a = @;\nprint ;") {
+        Err(LoxError::Parser(ParserError::Bundle(errors))) => errors,
+        res => panic!("Expected errors, found {res:?}"),
+    };
+
+    assert!(matches!(errors[0], ParserError::Scan(_)));
+    assert!(errors
+        .iter()
+        .any(|e| matches!(e, ParserError::Expected { .. })));
+}
+
+#[test]
+fn parses_type_annotations() {
+    let src = "var a: number? = nil;\nfun f(a: string, b): fun(number, (bool)?): nil { return nil; }\nvar g = (x: any): string? => nil;\nvar h = fun (): number { return 1; };";
+    let statements = Parser::parse_str(src).unwrap_or_else(|e| panic!("{e}"));
+
+    assert!(matches!(
+        &statements[0],
+        Stmt::Var { annotation: Some(ty), .. } if ty.to_string() == "number?"
+    ));
+    match &statements[1] {
+        Stmt::FunStmt {
+            fun_declaration, ..
+        } => {
+            assert_eq!(fun_declaration.params_repr(), "a: string, b");
+            assert_eq!(
+                fun_declaration.return_type.as_ref().unwrap().to_string(),
+                "fun(number, bool?): nil"
+            );
+        }
+        statement => panic!("Expected a function, found {statement}"),
+    }
+    assert_eq!(statements.len(), 4);
+
+    assert!(Parser::parse_str("var a: integer = 1;").is_err());
+}
+
+#[test]
+fn rejects_empty_interpolations() {
+    for src in ["print \"a${}b\" \"c\";", "\"a${}b\";", "print \"${}${1}\";"] {
+        let error = Parser::parse_str(src).unwrap_err().to_string();
+        assert!(
+            error.contains("Expected an expression in '${}'"),
+            "{src}: {error}"
+        );
+    }
+
+    // A string can still be interpolated.
+    assert!(Parser::parse_str("print \"a${\"b\"}${\"\"}\";").is_ok());
+}
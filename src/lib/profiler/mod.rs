@@ -0,0 +1,233 @@
+//! A profiler that times function calls and counts how often lines run.
+//!
+//! `Profiler` is a `Hook` recording into a shared `Profile`, which keeps it
+//! readable once the interpreter is done with the hook. The script itself is
+//! the outermost call, named `<script>`. Inclusive time counts the calls a
+//! function makes, exclusive time doesn't, a recursive function's inclusive
+//! time is only counted for its outermost call.
+
+#[cfg(test)]
+mod tests;
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::ast::Stmt;
+use crate::interpreter::error::RuntimeResult;
+use crate::interpreter::hook::Hook;
+use crate::interpreter::Interpreter;
+use crate::lox_value::LoxValue;
+use crate::token::Position;
+
+/// What the profile knows about one function.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FunctionStats {
+    pub calls: u64,
+    pub inclusive: Duration,
+    pub exclusive: Duration,
+}
+
+/// A call that hasn't returned yet.
+#[derive(Debug)]
+struct Call {
+    name: String,
+    started: Instant,
+    /// The time spent in the calls it made so far.
+    children: Duration,
+}
+
+#[derive(Debug)]
+pub struct Profile {
+    stack: Vec<Call>,
+    functions: HashMap<String, FunctionStats>,
+    /// How many statements ran on each line.
+    lines: BTreeMap<usize, u64>,
+    /// The exclusive time of each call stack, its names joined by ';'.
+    stacks: HashMap<String, Duration>,
+}
+
+impl Profile {
+    /// Creates a profile with the script's call started.
+    pub fn new() -> Profile {
+        let mut profile = Profile {
+            stack: Vec::new(),
+            functions: HashMap::new(),
+            lines: BTreeMap::new(),
+            stacks: HashMap::new(),
+        };
+        profile.enter("<script>");
+
+        profile
+    }
+
+    fn enter(&mut self, name: &str) {
+        self.stack.push(Call {
+            name: name.to_string(),
+            started: Instant::now(),
+            children: Duration::ZERO,
+        });
+    }
+
+    fn exit(&mut self) {
+        let stack: Vec<&str> = self.stack.iter().map(|call| call.name.as_str()).collect();
+        let key = stack.join(";");
+
+        let Some(call) = self.stack.pop() else {
+            return;
+        };
+        let elapsed = call.started.elapsed();
+        let exclusive = elapsed.saturating_sub(call.children);
+        let recursive = self.stack.iter().any(|outer| outer.name == call.name);
+
+        let stats = self.functions.entry(call.name).or_default();
+        stats.calls += 1;
+        stats.exclusive += exclusive;
+        if !recursive {
+            stats.inclusive += elapsed;
+        }
+
+        *self.stacks.entry(key).or_default() += exclusive;
+        if let Some(caller) = self.stack.last_mut() {
+            caller.children += elapsed;
+        }
+    }
+
+    /// Ends the calls that haven't returned, the script's included.
+    pub fn finish(&mut self) {
+        while !self.stack.is_empty() {
+            self.exit();
+        }
+    }
+
+    /// Returns the functions called, the one with the most exclusive time first.
+    pub fn functions(&self) -> Vec<(&str, FunctionStats)> {
+        let mut functions: Vec<(&str, FunctionStats)> = self
+            .functions
+            .iter()
+            .map(|(name, stats)| (name.as_str(), *stats))
+            .collect();
+        functions.sort_by(|(a_name, a), (b_name, b)| {
+            b.exclusive.cmp(&a.exclusive).then(a_name.cmp(b_name))
+        });
+
+        functions
+    }
+
+    /// Returns how often statements ran on each line that ran, the busiest line first.
+    pub fn lines(&self) -> Vec<(usize, u64)> {
+        let mut lines: Vec<(usize, u64)> = self
+            .lines
+            .iter()
+            .map(|(line, hits)| (*line, *hits))
+            .collect();
+        lines.sort_by(|(a_line, a), (b_line, b)| b.cmp(a).then(a_line.cmp(b_line)));
+
+        lines
+    }
+
+    /// Returns a report of the functions and lines for people to read.
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "{:<24} {:>10} {:>14} {:>14}\n",
+            "Function", "Calls", "Inclusive", "Exclusive"
+        );
+        for (name, stats) in self.functions() {
+            writeln!(
+                report,
+                "{:<24} {:>10} {:>14} {:>14}",
+                name,
+                stats.calls,
+                milliseconds(stats.inclusive),
+                milliseconds(stats.exclusive)
+            )
+            .expect("Writing to a String can't fail");
+        }
+
+        writeln!(report, "\n{:<8} {:>10}", "Line", "Hits").expect("Writing to a String can't fail");
+        for (line, hits) in self.lines() {
+            writeln!(report, "{line:<8} {hits:>10}").expect("Writing to a String can't fail");
+        }
+
+        report
+    }
+
+    /// Returns the call stacks in the folded format of flamegraph tools,
+    /// one `<script>;caller;callee <microseconds>` line per stack.
+    pub fn folded(&self) -> String {
+        let mut stacks: Vec<(&String, &Duration)> = self.stacks.iter().collect();
+        stacks.sort();
+
+        stacks
+            .into_iter()
+            .map(|(stack, time)| format!("{stack} {}\n", time.as_micros()))
+            .collect()
+    }
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn milliseconds(duration: Duration) -> String {
+    format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Records a program's calls and lines into a `Profile`.
+pub struct Profiler {
+    profile: Rc<RefCell<Profile>>,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler {
+            profile: Rc::new(RefCell::new(Profile::new())),
+        }
+    }
+
+    /// Returns the profile being recorded, call `Profile::finish` once the program is done.
+    pub fn profile(&self) -> Rc<RefCell<Profile>> {
+        self.profile.clone()
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hook for Profiler {
+    fn statement(&mut self, _interpreter: &mut Interpreter, statement: &Stmt) -> RuntimeResult<()> {
+        // A block's line is only where its statements start.
+        if !matches!(statement, Stmt::Block(..)) {
+            let line = statement.get_position().line;
+            *self.profile.borrow_mut().lines.entry(line).or_default() += 1;
+        }
+
+        Ok(())
+    }
+
+    fn enter(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        name: &str,
+        _arguments: &[LoxValue],
+        _position: Position,
+    ) {
+        self.profile.borrow_mut().enter(name);
+    }
+
+    fn exit(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        _name: &str,
+        _result: &RuntimeResult<LoxValue>,
+    ) {
+        self.profile.borrow_mut().exit();
+    }
+}
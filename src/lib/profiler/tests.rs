@@ -0,0 +1,87 @@
+use super::*;
+use crate::utils::run_with_hook;
+
+const SRC: &str = "\
+fun fib(n) {
+    if (n < 2) return n;
+    return fib(n - 1) + fib(n - 2);
+}
+fun run() {
+    return fib(5);
+}
+print run();
+print fib(1);";
+
+fn profile() -> Rc<RefCell<Profile>> {
+    let profiler = Profiler::new();
+    let profile = profiler.profile();
+
+    run_with_hook(SRC, Box::new(profiler));
+    profile.borrow_mut().finish();
+
+    profile
+}
+
+#[test]
+fn counts_calls_and_lines() {
+    let profile = profile();
+    let profile = profile.borrow();
+
+    let mut calls: Vec<(&str, u64)> = profile
+        .functions()
+        .into_iter()
+        .map(|(name, stats)| (name, stats.calls))
+        .collect();
+    calls.sort();
+    assert_eq!(calls, [("<script>", 1), ("fib", 16), ("run", 1)]);
+
+    // `fib(5)` makes 15 calls, 8 of them return early, and so does `fib(1)`.
+    let lines = profile.lines();
+    assert_eq!(lines[0], (2, 25));
+    assert!(lines.contains(&(3, 7)));
+    assert!(lines.contains(&(8, 1)));
+}
+
+#[test]
+fn times_calls() {
+    let profile = profile();
+    let profile = profile.borrow();
+    let functions: HashMap<&str, FunctionStats> = profile.functions().into_iter().collect();
+
+    let script = functions["<script>"];
+    let run = functions["run"];
+    let fib = functions["fib"];
+    assert!(script.inclusive >= run.inclusive);
+    assert!(run.inclusive >= run.exclusive);
+    // The recursive calls are inside the outermost ones.
+    assert!(fib.inclusive >= fib.exclusive);
+    assert!(fib.inclusive <= script.inclusive);
+
+    let report = profile.report();
+    assert!(report.starts_with("Function"));
+    assert!(report.contains("\nLine "));
+}
+
+#[test]
+fn folds_call_stacks() {
+    let profile = profile();
+    let folded = profile.borrow().folded();
+
+    let stacks: Vec<&str> = folded
+        .lines()
+        .map(|line| line.rsplit_once(' ').unwrap().0)
+        .collect();
+    assert_eq!(
+        stacks,
+        [
+            "<script>",
+            "<script>;fib",
+            "<script>;run",
+            "<script>;run;fib",
+            "<script>;run;fib;fib",
+            "<script>;run;fib;fib;fib",
+            "<script>;run;fib;fib;fib;fib",
+            "<script>;run;fib;fib;fib;fib;fib",
+        ]
+    );
+}
@@ -0,0 +1,43 @@
+use crate::token::Position;
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, Error)]
+pub enum ScanError {
+    #[error("Invalid character '{0}', {1}.")]
+    InvalidCharacter(char, Position),
+    #[error("Unterminated string starting at {0}.")]
+    UnterminatedString(Position),
+    #[error("Unterminated block comment starting at {0}.")]
+    UnterminatedComment(Position),
+    #[error("Malformed number '{0}', {1}.")]
+    MalformedNumber(String, Position),
+    #[error("Unknown escape sequence '\\{0}' in string, {1}.")]
+    InvalidEscape(char, Position),
+    #[error("Invalid unicode escape sequence '\\u{{{0}}}' in string, {1}.")]
+    InvalidUnicodeEscape(String, Position),
+    #[error("Expected '\"' to start the raw string at {0}.")]
+    MissingRawStringQuote(Position),
+    #[error("{}", .0.iter().map(|e| e.to_string()).collect::<Vec<String>>().join("\n"))]
+    Bundle(Vec<ScanError>),
+}
+
+impl ScanError {
+    /// Returns where the error happened, `None` for a bundle of errors.
+    pub fn position(&self) -> Option<Position> {
+        use ScanError::*;
+
+        match self {
+            InvalidCharacter(_, p)
+            | UnterminatedString(p)
+            | UnterminatedComment(p)
+            | MalformedNumber(_, p)
+            | InvalidEscape(_, p)
+            | InvalidUnicodeEscape(_, p)
+            | MissingRawStringQuote(p) => Some(*p),
+            Bundle(_) => None,
+        }
+    }
+}
+
+pub type ScanResult<T> = Result<T, ScanError>;
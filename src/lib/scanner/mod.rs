@@ -0,0 +1,881 @@
+pub mod error;
+
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::lox_value::LoxValue;
+use crate::token::{Comment, Position, Token};
+use crate::token_type::TokenType;
+
+use error::{ScanError, ScanResult};
+
+pub struct Scanner {
+    pub source: Vec<char>,
+    pub tokens: Vec<Token>,
+    /// The comments skipped while scanning, in the order they appear.
+    pub comments: Vec<Comment>,
+    pub start: usize,
+    pub current: usize,
+    pub column: usize,
+    pub line: usize,
+    /// The position of the first character of the token being scanned.
+    start_position: Position,
+    pub keywords: HashMap<&'static str, TokenType>,
+    /// The identifiers scanned so far, so that every use of a name shares one string.
+    identifiers: HashSet<Rc<str>>,
+    /// The number of unclosed '{' in each `${...}` being scanned, innermost last.
+    interpolations: Vec<usize>,
+    /// The line the last token ended on, used to tell trailing comments apart.
+    last_token_line: Option<usize>,
+    pub errors: Vec<ScanError>,
+}
+
+impl Scanner {
+    pub fn new(source: &str) -> Scanner {
+        Scanner {
+            source: source.chars().collect(),
+            tokens: Vec::new(),
+            comments: Vec::new(),
+            start: 0,
+            current: 0,
+            column: 1,
+            line: 1,
+            start_position: Position::new(1, 1),
+            keywords: HashMap::from([
+                ("and", TokenType::And),
+                ("or", TokenType::Or),
+                ("true", TokenType::True),
+                ("false", TokenType::False),
+                ("nil", TokenType::Nil),
+                ("if", TokenType::If),
+                ("else", TokenType::Else),
+                ("while", TokenType::While),
+                ("for", TokenType::For),
+                ("fun", TokenType::Fun),
+                ("class", TokenType::Class),
+                ("return", TokenType::Return),
+                ("print", TokenType::Print),
+                ("super", TokenType::Super),
+                ("this", TokenType::This),
+                ("var", TokenType::Var),
+                ("break", TokenType::Break),
+                ("continue", TokenType::Continue),
+            ]),
+            identifiers: HashSet::new(),
+            interpolations: Vec::new(),
+            last_token_line: None,
+            errors: Vec::new(),
+        }
+    }
+
+    // Checks self.current is at the end of source.
+    fn is_at_end(&self) -> bool {
+        self.current >= self.source.len()
+    }
+
+    fn increment_current(&mut self) {
+        self.current += 1;
+        self.column += 1;
+    }
+
+    // Yields value at self.current and increment self.current.
+    fn advance(&mut self) -> char {
+        let res = self.source[self.current];
+        // self.current += 1;
+        self.increment_current();
+
+        if res == '\n' {
+            self.line += 1;
+            self.column = 1;
+        }
+
+        res
+    }
+
+    // Looks ahead at value at self.current
+    fn peek(&self) -> char {
+        self.source[self.current]
+    }
+
+    // Checks if the source at self.current starts with expected.
+    fn check_str(&self, expected: &str) -> bool {
+        expected
+            .chars()
+            .enumerate()
+            .all(|(offset, c)| self.source.get(self.current + offset) == Some(&c))
+    }
+
+    // Checks if the character at self.current is equal to expected.
+    fn matches_next(&mut self, expected: char) -> bool {
+        if self.is_at_end() {
+            return false;
+        };
+        if self.peek() != expected {
+            return false;
+        };
+
+        // self.current += 1;
+        self.increment_current();
+        true
+    }
+
+    fn position(&self) -> Position {
+        Position::new(self.line, self.column)
+    }
+
+    /// Adds a token positioned at the first character of the lexeme.
+    fn add_token_with_literal(&mut self, token_type: TokenType, literal: Option<LoxValue>) {
+        let lexeme = self.get_curr_string();
+
+        self.last_token_line = Some(self.line);
+        self.tokens
+            .push(Token::new(token_type, lexeme, literal, self.start_position))
+    }
+
+    fn add_token(&mut self, token_type: TokenType) {
+        self.add_token_with_literal(token_type, None)
+    }
+
+    /// Gets the string between self.start and self.current.
+    fn get_curr_string(&self) -> String {
+        self.source[self.start..self.current].iter().collect()
+    }
+
+    fn scan_comment(&mut self) {
+        // While there is a comment keep on calling advance
+        if self.matches_next('/') {
+            while !self.is_at_end() && (self.peek() != '\n') {
+                self.advance();
+            }
+        } else if self.matches_next('*') {
+            while !self.check_str("*/") {
+                if self.is_at_end() {
+                    self.errors
+                        .push(ScanError::UnterminatedComment(self.start_position));
+                    return;
+                }
+
+                self.advance();
+            }
+
+            self.advance();
+            self.advance();
+        } else {
+            return self.add_token(TokenType::Slash);
+        }
+
+        self.comments.push(Comment {
+            text: self.get_curr_string().trim_end().to_string(),
+            position: self.start_position,
+            trailing: self.last_token_line == Some(self.start_position.line),
+        });
+    }
+
+    /// Scans the rest of a string literal up to the closing '"' or the start
+    /// of an interpolated expression, `${`.
+    fn scan_string(&mut self) {
+        let mut content = String::new();
+        let mut escapes = Vec::new();
+        let mut token_type = TokenType::String;
+
+        loop {
+            if self.is_at_end() {
+                self.errors
+                    .push(ScanError::UnterminatedString(self.start_position));
+                break;
+            }
+
+            let position = self.position();
+            match self.advance() {
+                '"' => break,
+                '$' if !self.is_at_end() && self.peek() == '{' => {
+                    self.advance();
+                    self.interpolations.push(0);
+                    token_type = TokenType::Interpolation;
+                    break;
+                }
+                // Keep escapes as they are so an escaped '"' or '$' doesn't end the segment.
+                '\\' if !self.is_at_end() => {
+                    escapes.push(position);
+                    content.push('\\');
+                    content.push(self.advance());
+                }
+                c => content.push(c),
+            }
+        }
+
+        let segment = self.unescape(&content, &escapes);
+        self.add_token_with_literal(token_type, Some(LoxValue::String(segment.into())))
+    }
+
+    /// Scans a string delimited by '"""' which can span multiple lines.
+    ///
+    /// If the opening '"""' is followed by a line break the break is dropped, the same goes for
+    /// a closing '"""' on a line of its own. The indentation shared by all the remaining lines
+    /// is then stripped so the string can be indented along with the code around it.
+    fn scan_multiline_string(&mut self) {
+        let mut content = String::new();
+        let mut escapes = Vec::new();
+
+        while !self.is_at_end() && !self.check_str("\"\"\"") {
+            let position = self.position();
+            match self.advance() {
+                '\\' if !self.is_at_end() => {
+                    escapes.push(position);
+                    content.push('\\');
+                    content.push(self.advance());
+                }
+                c => content.push(c),
+            }
+        }
+        if self.is_at_end() {
+            self.errors
+                .push(ScanError::UnterminatedString(self.start_position));
+        }
+        for _ in 0..3 {
+            if !self.is_at_end() {
+                self.advance();
+            }
+        }
+
+        // Stripping the indentation leaves the escapes, so they still line up with `escapes`.
+        let content = strip_indentation(&content);
+        let literal = self.unescape(&content, &escapes);
+
+        self.add_token_with_literal(TokenType::String, Some(LoxValue::String(literal.into())))
+    }
+
+    /// Scans a raw string, `r"..."` or `r#"..."#`, in which '\\' and '${' have no special meaning.
+    /// Any number of '#' can be used so that the string can contain '"#'.
+    fn scan_raw_string(&mut self) {
+        let mut hashes = 0;
+        while self.matches_next('#') {
+            hashes += 1;
+        }
+        if !self.matches_next('"') {
+            self.errors
+                .push(ScanError::MissingRawStringQuote(self.start_position));
+            return;
+        }
+
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        let mut content = String::new();
+
+        while !self.is_at_end() && !self.check_str(&terminator) {
+            content.push(self.advance());
+        }
+        if self.is_at_end() {
+            self.errors
+                .push(ScanError::UnterminatedString(self.start_position));
+        }
+        for _ in 0..terminator.len() {
+            if !self.is_at_end() {
+                self.advance();
+            }
+        }
+
+        self.add_token_with_literal(TokenType::String, Some(LoxValue::String(content.into())))
+    }
+
+    /// Replaces the escape sequences in a string with the characters they represent,
+    /// unknown escapes are reported at their position in `escapes`, which holds where
+    /// each '\\' in `raw` is in the source, and left as they are.
+    fn unescape(&mut self, raw: &str, escapes: &[Position]) -> String {
+        let mut res = String::with_capacity(raw.len());
+        let mut chars = raw.chars();
+        let mut escapes = escapes.iter();
+
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                res.push(c);
+                continue;
+            }
+
+            let position = escapes.next().copied().unwrap_or(self.start_position);
+            match chars.next() {
+                Some('n') => res.push('\n'),
+                Some('t') => res.push('\t'),
+                Some('r') => res.push('\r'),
+                Some('0') => res.push('\0'),
+                Some('\\') => res.push('\\'),
+                Some('"') => res.push('"'),
+                Some('\'') => res.push('\''),
+                Some('$') => res.push('$'),
+                Some('u') => match parse_unicode_escape(&mut chars) {
+                    Ok(c) => res.push(c),
+                    Err(code) => {
+                        self.errors
+                            .push(ScanError::InvalidUnicodeEscape(code, position));
+                    }
+                },
+                Some(c) => {
+                    self.errors.push(ScanError::InvalidEscape(c, position));
+                    res.push('\\');
+                    res.push(c);
+                }
+                None => res.push('\\'),
+            }
+        }
+
+        res
+    }
+
+    fn scan_number(&mut self) {
+        while !self.is_at_end() && self.peek().is_ascii_digit() {
+            self.advance();
+        }
+        if self.check_str(".")
+            && self
+                .source
+                .get(self.current + 1)
+                .is_some_and(|c| c.is_ascii_digit())
+        {
+            self.advance();
+
+            while !self.is_at_end() && self.peek().is_ascii_digit() {
+                self.advance();
+            }
+        }
+
+        // Letters straight after a number, like in '12abc', make the whole thing malformed.
+        let mut malformed = false;
+        while !self.is_at_end() && is_ident_continue(self.peek()) {
+            malformed = true;
+            self.advance();
+        }
+
+        let lexeme = self.get_curr_string();
+        match lexeme.parse::<f64>() {
+            Ok(number) if !malformed => {
+                self.add_token_with_literal(TokenType::Number, Some(LoxValue::Number(number)))
+            }
+            _ => self
+                .errors
+                .push(ScanError::MalformedNumber(lexeme, self.start_position)),
+        }
+    }
+
+    fn scan_identifier(&mut self) {
+        while !self.is_at_end() && is_ident_continue(self.peek()) {
+            self.advance();
+        }
+
+        let literal = self.get_curr_string();
+
+        if let Some(token_type) = self.keywords.get(literal.as_str()) {
+            let token_type = token_type.clone();
+
+            match token_type {
+                TokenType::True => {
+                    self.add_token_with_literal(token_type, Some(LoxValue::Boolean(true)))
+                }
+                TokenType::False => {
+                    self.add_token_with_literal(token_type, Some(LoxValue::Boolean(false)))
+                }
+                TokenType::Nil => self.add_token_with_literal(token_type, Some(LoxValue::Nil)),
+                _ => self.add_token(token_type),
+            }
+        } else {
+            let name = self.intern(&literal);
+            self.add_token_with_literal(TokenType::Identifier, Some(LoxValue::Identifier(name)))
+        }
+    }
+
+    /// Returns the shared string for an identifier.
+    fn intern(&mut self, name: &str) -> Rc<str> {
+        if let Some(interned) = self.identifiers.get(name) {
+            return interned.clone();
+        }
+
+        let interned: Rc<str> = Rc::from(name);
+        self.identifiers.insert(interned.clone());
+
+        interned
+    }
+
+    // @desc Calls advance and matches to handle the token.
+    fn scan_token(&mut self) {
+        let c = self.advance();
+
+        match c {
+            '(' => self.add_token(TokenType::LeftParen),
+            ')' => self.add_token(TokenType::RightParen),
+            '{' => {
+                if let Some(open_braces) = self.interpolations.last_mut() {
+                    *open_braces += 1;
+                }
+                self.add_token(TokenType::LeftBrace)
+            }
+            '}' => match self.interpolations.last_mut() {
+                // This brace closes an interpolated expression so the string continues.
+                Some(0) => {
+                    self.interpolations.pop();
+                    self.scan_string();
+                }
+                Some(open_braces) => {
+                    *open_braces -= 1;
+                    self.add_token(TokenType::RightBrace)
+                }
+                None => self.add_token(TokenType::RightBrace),
+            },
+            ',' => self.add_token(TokenType::Comma),
+            '.' => self.add_token(TokenType::Dot),
+            '?' => self.add_token(TokenType::QuestionMark),
+            '-' => self.add_token(TokenType::Minus),
+            '+' => self.add_token(TokenType::Plus),
+            '*' => self.add_token(TokenType::Star),
+            ';' => self.add_token(TokenType::Semicolon),
+            ':' => self.add_token(TokenType::Colon),
+
+            '!' => {
+                let token_type = if self.matches_next('=') {
+                    TokenType::BangEqual
+                } else {
+                    TokenType::Bang
+                };
+                self.add_token(token_type);
+            }
+            '=' => {
+                let token_type = if self.matches_next('=') {
+                    TokenType::EqualEqual
+                } else if self.matches_next('>') {
+                    TokenType::Arrow
+                } else {
+                    TokenType::Equal
+                };
+                self.add_token(token_type);
+            }
+            '<' => {
+                let token_type = if self.matches_next('=') {
+                    TokenType::LessEqual
+                } else {
+                    TokenType::Less
+                };
+                self.add_token(token_type);
+            }
+            '>' => {
+                let token_type = if self.matches_next('=') {
+                    TokenType::GreaterEqual
+                } else {
+                    TokenType::Greater
+                };
+                self.add_token(token_type);
+            }
+            // TODO
+            '/' => self.scan_comment(),
+            '"' if self.check_str("\"\"") => {
+                self.advance();
+                self.advance();
+                self.scan_multiline_string()
+            }
+            '"' => self.scan_string(),
+            'r' if !self.is_at_end() && matches!(self.peek(), '"' | '#') => self.scan_raw_string(),
+            c if c.is_ascii_digit() => self.scan_number(),
+            c if is_ident_start(c) => self.scan_identifier(),
+
+            ' ' | '\r' | '\t' | '\n' => (),
+            // ' ' | '\r' | '\t' => (),
+            c => self
+                .errors
+                .push(ScanError::InvalidCharacter(c, self.start_position)),
+        }
+    }
+
+    // @desc Call scan_token till it's done with self.source.
+    /// Scans the whole source, tokens are kept in `self.tokens` even if some of the source
+    /// couldn't be scanned, in which case all the errors are returned in a `ScanError::Bundle`.
+    pub fn scan_tokens(&mut self) -> ScanResult<Vec<Token>> {
+        while !self.is_at_end() {
+            self.start = self.current;
+            self.start_position = self.position();
+            self.scan_token();
+        }
+
+        if !self.interpolations.is_empty() {
+            self.errors
+                .push(ScanError::UnterminatedString(self.position()));
+        }
+
+        self.start = self.current;
+        self.start_position = self.position();
+        self.add_token(TokenType::Eof);
+
+        if self.errors.is_empty() {
+            Ok(self.tokens.clone())
+        } else {
+            Err(ScanError::Bundle(self.errors.clone()))
+        }
+    }
+
+    pub fn print_tokens(&self) {
+        println!("\nScanned Tokens: ");
+        println!("============================================================================================");
+        let mut line_no = 0;
+
+        for (index, token) in self.tokens.iter().enumerate() {
+            if token.position.line != line_no {
+                line_no = token.position.line;
+                println!("\nLine {}", line_no);
+                println!("==================");
+            }
+            println!("{} ->  {:?}", index, token);
+        }
+        println!("\n============================================================================================\n");
+    }
+
+    pub fn tokens_from_str(source: &str, display: bool) -> ScanResult<Vec<Token>> {
+        let mut scanner = Scanner::new(source);
+        let res = scanner.scan_tokens();
+
+        if display {
+            scanner.print_tokens();
+        }
+
+        res
+    }
+
+    pub fn tokens_from_bytes(source: &[u8]) -> ScanResult<Vec<Token>> {
+        Scanner::new(&String::from_utf8_lossy(source)).scan_tokens()
+    }
+
+    /// Checks if the source needs more lines to be finished, i.e. it ends inside a string
+    /// or a block comment or has brackets or braces that haven't been closed yet.
+    pub fn is_incomplete(source: &str) -> bool {
+        let mut scanner = Scanner::new(source);
+
+        if let Err(ScanError::Bundle(errors)) = scanner.scan_tokens() {
+            let unterminated = errors.iter().any(|error| {
+                matches!(
+                    error,
+                    ScanError::UnterminatedString(_) | ScanError::UnterminatedComment(_)
+                )
+            });
+            if unterminated {
+                return true;
+            }
+        }
+
+        let depth = scanner
+            .tokens
+            .iter()
+            .fold(0, |depth, token| match token.token_type {
+                TokenType::LeftParen | TokenType::LeftBrace => depth + 1,
+                TokenType::RightParen | TokenType::RightBrace => depth - 1,
+                _ => depth,
+            });
+
+        depth > 0
+    }
+}
+
+/// Parses the `{XXXX}` part of a `\u{XXXX}` escape, returning the text
+/// between the braces if it isn't a valid code point.
+fn parse_unicode_escape(chars: &mut std::str::Chars) -> Result<char, String> {
+    if chars.next() != Some('{') {
+        return Err(String::new());
+    }
+
+    let code: String = chars.by_ref().take_while(|c| *c != '}').collect();
+
+    u32::from_str_radix(&code, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or(code)
+}
+
+/// Strips the line breaks next to the delimiters of a multi-line string and
+/// the indentation common to all of its lines.
+fn strip_indentation(content: &str) -> String {
+    let content = content.strip_prefix('\n').unwrap_or(content);
+    let mut lines: Vec<&str> = content.split('\n').collect();
+
+    // A closing delimiter on its own line only contributes its indentation.
+    let closing_indentation = match lines.last() {
+        Some(last) if last.trim().is_empty() && lines.len() > 1 => lines.pop(),
+        _ => None,
+    };
+
+    let indentation = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .chain(closing_indentation.iter())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|line| line.get(indentation..).unwrap_or("").trim_end_matches('\r'))
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+/// Identifiers start with a character with the Unicode XID_Start property or an underscore.
+fn is_ident_start(c: char) -> bool {
+    c == '_' || unicode_ident::is_xid_start(c)
+}
+
+/// The rest of an identifier is made of characters with the Unicode XID_Continue property.
+fn is_ident_continue(c: char) -> bool {
+    unicode_ident::is_xid_continue(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_left_paren() {
+        let tokens = Scanner::tokens_from_str("if (true)", true).unwrap();
+        assert_eq!(tokens[1].token_type, TokenType::LeftParen)
+    }
+
+    #[test]
+    fn scans_string_interpolation() {
+        let tokens = Scanner::tokens_from_str(r#""a ${b} c ${ {} } d""#, false).unwrap();
+        let token_types: Vec<TokenType> = tokens.iter().map(|t| t.token_type.clone()).collect();
+
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::Interpolation,
+                TokenType::Identifier,
+                TokenType::Interpolation,
+                TokenType::LeftBrace,
+                TokenType::RightBrace,
+                TokenType::String,
+                TokenType::Eof,
+            ]
+        );
+        assert!(matches!(&tokens[0].literal, Some(LoxValue::String(s)) if &**s == "a "));
+        assert!(matches!(&tokens[5].literal, Some(LoxValue::String(s)) if &**s == " d"));
+    }
+
+    fn string_literal(source: &str) -> String {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+
+        match &tokens[0].literal {
+            Some(LoxValue::String(s)) => s.to_string(),
+            literal => panic!("Expected a string literal, found {literal:?}"),
+        }
+    }
+
+    #[test]
+    fn scans_escape_sequences() {
+        assert_eq!(
+            string_literal(r#""a\tb\n\"c\" \\ \$ \u{1F600}""#),
+            "a\tb\n\"c\" \\ $ \u{1F600}"
+        );
+    }
+
+    #[test]
+    fn reports_unknown_escape_sequences() {
+        let errors = match Scanner::tokens_from_bytes(br#""\q \u{110000}""#) {
+            Err(ScanError::Bundle(errors)) => errors,
+            res => panic!("Expected scan errors, found {res:?}"),
+        };
+
+        assert!(matches!(
+            errors.as_slice(),
+            [
+                ScanError::InvalidEscape('q', _),
+                ScanError::InvalidUnicodeEscape(_, _)
+            ]
+        ));
+    }
+
+    #[test]
+    fn reports_unknown_escape_sequences_where_they_are() {
+        let errors = scan_errors(
+            "var a = \"ok \\q\";\nvar b = \"\"\"\n    fine\n      \\u{110000}\n    \"\"\";",
+        );
+
+        assert!(matches!(
+            errors.as_slice(),
+            [
+                ScanError::InvalidEscape(
+                    'q',
+                    Position {
+                        line: 1,
+                        column: 13
+                    }
+                ),
+                ScanError::InvalidUnicodeEscape(_, Position { line: 4, column: 7 })
+            ]
+        ));
+    }
+
+    #[test]
+    fn strips_indentation_from_multiline_strings() {
+        let src = "\"\"\"\n    first\n      second\n    \"\"\"";
+
+        assert_eq!(string_literal(src), "first\n  second");
+    }
+
+    #[test]
+    fn tracks_lines_across_multiline_strings() {
+        let tokens = Scanner::tokens_from_str("\"\"\"\na\nb\"\"\" x", false).unwrap();
+
+        assert_eq!(tokens[1].position.line, 3);
+    }
+
+    #[test]
+    fn scans_raw_strings() {
+        assert_eq!(string_literal(r#"r"\n ${x}""#), r"\n ${x}");
+        assert_eq!(string_literal(r##"r#"say "hi""#"##), r#"say "hi""#);
+    }
+
+    fn scan_errors(source: &str) -> Vec<ScanError> {
+        match Scanner::tokens_from_str(source, false) {
+            Err(ScanError::Bundle(errors)) => errors,
+            res => panic!("Expected scan errors, found {res:?}"),
+        }
+    }
+
+    #[test]
+    fn scans_decimal_numbers() {
+        let tokens = Scanner::tokens_from_str("3.25", false).unwrap();
+
+        assert!(matches!(tokens[0].literal, Some(LoxValue::Number(n)) if n == 3.25));
+    }
+
+    #[test]
+    fn reports_invalid_characters() {
+        let errors = scan_errors("var a = 1;\nvar b = #;");
+
+        assert!(matches!(
+            errors.as_slice(),
+            [ScanError::InvalidCharacter('#', Position { line: 2, .. })]
+        ));
+    }
+
+    #[test]
+    fn reports_unterminated_strings_and_comments() {
+        assert!(matches!(
+            scan_errors("\"abc").as_slice(),
+            [ScanError::UnterminatedString(_)]
+        ));
+        assert!(matches!(
+            scan_errors("\"a ${b").as_slice(),
+            [ScanError::UnterminatedString(_)]
+        ));
+        assert!(matches!(
+            scan_errors("/* abc *").as_slice(),
+            [ScanError::UnterminatedComment(_)]
+        ));
+    }
+
+    #[test]
+    fn reports_raw_strings_without_a_quote() {
+        assert!(matches!(
+            scan_errors("var a = r#abc;\nprint a;").as_slice(),
+            [ScanError::MissingRawStringQuote(Position {
+                line: 1,
+                column: 9
+            })]
+        ));
+    }
+
+    #[test]
+    fn reports_malformed_numbers() {
+        assert!(matches!(
+            scan_errors("12abc;").as_slice(),
+            [ScanError::MalformedNumber(lexeme, _)] if lexeme == "12abc"
+        ));
+    }
+
+    #[test]
+    fn positions_tokens_at_their_first_character() {
+        let tokens = Scanner::tokens_from_str("var answer = 42;", false).unwrap();
+        let columns: Vec<usize> = tokens.iter().map(|t| t.position.column).collect();
+
+        assert_eq!(columns, vec![1, 5, 12, 14, 16, 17]);
+    }
+
+    #[test]
+    fn scans_unicode_identifiers() {
+        let tokens = Scanner::tokens_from_str("var café = 1; print 变量 + _x1;", false).unwrap();
+
+        assert!(matches!(&tokens[1].literal, Some(LoxValue::Identifier(s)) if &**s == "café"));
+        assert!(matches!(&tokens[6].literal, Some(LoxValue::Identifier(s)) if &**s == "变量"));
+        assert!(matches!(&tokens[8].literal, Some(LoxValue::Identifier(s)) if &**s == "_x1"));
+    }
+
+    #[test]
+    fn counts_columns_in_characters() {
+        let tokens = Scanner::tokens_from_str("\"ünïcödé\" /* ☃☃ */ x", false).unwrap();
+
+        assert_eq!(tokens[1].position, Position::new(1, 20));
+    }
+
+    #[test]
+    fn keeps_lexemes() {
+        let tokens = Scanner::tokens_from_str(r#"x >= 1.50 + "a\n${b}""#, false).unwrap();
+        let lexemes: Vec<&str> = tokens.iter().map(|t| t.lexeme.as_str()).collect();
+
+        assert_eq!(
+            lexemes,
+            vec!["x", ">=", "1.50", "+", r#""a\n${"#, "b", r#"}""#, ""]
+        );
+    }
+
+    #[test]
+    fn keeps_comments_as_trivia() {
+        let mut scanner = Scanner::new("// first\nvar a; /* second */\n/* third\n */ a;");
+        scanner.scan_tokens().unwrap();
+
+        let comments: Vec<(&str, bool)> = scanner
+            .comments
+            .iter()
+            .map(|c| (c.text.as_str(), c.trailing))
+            .collect();
+        assert_eq!(
+            comments,
+            vec![
+                ("// first", false),
+                ("/* second */", true),
+                ("/* third\n */", false)
+            ]
+        );
+        assert_eq!(scanner.comments[2].position, Position::new(3, 1));
+    }
+
+    #[test]
+    fn detects_incomplete_input() {
+        for src in [
+            "fun f() {",
+            "print (1 +",
+            "{ if (a) {} ",
+            "print \"abc",
+            "print \"\"\"\nmulti",
+            "print \"${1 + ",
+            "/* comment",
+        ] {
+            assert!(Scanner::is_incomplete(src), "{src}");
+        }
+
+        for src in [
+            "print 1;",
+            "fun f() {}",
+            "print 1 +",
+            "}",
+            "print \"${1}\";",
+            "@",
+        ] {
+            assert!(!Scanner::is_incomplete(src), "{src}");
+        }
+    }
+
+    #[test]
+    fn interns_identifiers() {
+        let tokens = Scanner::tokens_from_str("a = b + a;", false).unwrap();
+
+        let name = |index: usize| match &tokens[index].literal {
+            Some(LoxValue::Identifier(name)) => name.clone(),
+            literal => panic!("Expected an identifier, found {literal:?}"),
+        };
+        assert!(Rc::ptr_eq(&name(0), &name(4)));
+        assert!(!Rc::ptr_eq(&name(0), &name(2)));
+    }
+}
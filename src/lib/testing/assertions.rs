@@ -0,0 +1,99 @@
+use crate::interpreter::error::{RuntimeError, RuntimeResult};
+use crate::interpreter::Interpreter;
+use crate::lox_value::LoxValue;
+use crate::token::Position;
+use crate::utils::line_diff;
+
+/// Defines `assert`, `assert_eq` and `assert_throws` in an interpreter.
+pub fn define_assertions(interpreter: &mut Interpreter) {
+    interpreter.define_native("assert", 1, assert);
+    interpreter.define_native("assert_eq", 2, assert_eq);
+    interpreter.define_native("assert_throws", 1, assert_throws);
+}
+
+/// `assert(condition)` fails unless the condition is truthy.
+fn assert(
+    _interpreter: &mut Interpreter,
+    args: &[LoxValue],
+    position: Position,
+) -> RuntimeResult<LoxValue> {
+    if args[0].is_truthy() {
+        return Ok(LoxValue::Nil);
+    }
+
+    Err(RuntimeError::AssertionFailed {
+        message: format!("expected a truthy value, got {}", repr(&args[0])),
+        position,
+    })
+}
+
+/// `assert_eq(actual, expected)` fails unless the values are equal.
+fn assert_eq(
+    _interpreter: &mut Interpreter,
+    args: &[LoxValue],
+    position: Position,
+) -> RuntimeResult<LoxValue> {
+    let (actual, expected) = (&args[0], &args[1]);
+    if actual.is_equal(expected) {
+        return Ok(LoxValue::Nil);
+    }
+
+    Err(RuntimeError::AssertionFailed {
+        message: difference(actual, expected),
+        position,
+    })
+}
+
+/// `assert_throws(function)` calls a function without parameters and fails unless it
+/// fails with an error, returning the error's message.
+fn assert_throws(
+    interpreter: &mut Interpreter,
+    args: &[LoxValue],
+    position: Position,
+) -> RuntimeResult<LoxValue> {
+    let callable = match args[0].as_callable() {
+        Some(callable) if callable.arity() == 0 => callable,
+        _ => {
+            return Err(RuntimeError::AssertionFailed {
+                message: format!(
+                    "expected a function without parameters, got {}",
+                    repr(&args[0])
+                ),
+                position,
+            })
+        }
+    };
+
+    match callable.call(interpreter, &[], position) {
+        Ok(value) => Err(RuntimeError::AssertionFailed {
+            message: format!("expected an error, the function returned {}", repr(&value)),
+            position,
+        }),
+        Err(e) => Ok(LoxValue::String(e.to_string().into())),
+    }
+}
+
+/// Shows a value in a failed assertion, strings are quoted so `"1"` isn't mistaken for `1`.
+fn repr(value: &LoxValue) -> String {
+    match value {
+        LoxValue::String(string) => format!("\"{string}\""),
+        value => value.to_string(),
+    }
+}
+
+/// Describes how two values differ, strings with several lines are compared line by line.
+fn difference(actual: &LoxValue, expected: &LoxValue) -> String {
+    let (LoxValue::String(actual), LoxValue::String(expected)) = (actual, expected) else {
+        return format!("expected {}, got {}", repr(expected), repr(actual));
+    };
+    if !actual.contains('\n') && !expected.contains('\n') {
+        return format!("expected \"{expected}\", got \"{actual}\"");
+    }
+
+    let actual: Vec<&str> = actual.lines().collect();
+    let expected: Vec<&str> = expected.lines().collect();
+    format!(
+        "the strings differ (- expected, + actual):\n{}",
+        line_diff(&expected, &actual)
+    )
+}
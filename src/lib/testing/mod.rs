@@ -0,0 +1,109 @@
+//! A test runner for Lox code, used by `lox_one test`.
+//!
+//! Tests are the top level functions of `*_test.lox` files whose names start
+//! with `test_` and that take no parameters. Each test runs in its own
+//! interpreter, which runs the whole file first and then calls the test, so
+//! tests can't see what other tests did. The natives `assert`, `assert_eq`
+//! and `assert_throws` are defined for them.
+
+mod assertions;
+
+#[cfg(test)]
+mod tests;
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::ast::Stmt;
+use crate::error::{LoxError, LoxResult};
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::utils::Output;
+
+pub use self::assertions::define_assertions;
+
+/// How a test went.
+#[derive(Debug)]
+pub struct TestResult {
+    pub name: String,
+    /// Why the test failed, `None` if it passed.
+    pub error: Option<LoxError>,
+    /// What the test printed.
+    pub output: String,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Returns the `*_test.lox` files in a directory and its subdirectories sorted
+/// by path, or the path itself if it's a file.
+pub fn discover(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(path)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            files.extend(discover(&path)?);
+        } else if path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.ends_with("_test.lox"))
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+/// Returns the names of the tests in a program, in the order they're declared.
+pub fn test_names(statements: &[Stmt]) -> Vec<String> {
+    statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Stmt::FunStmt {
+                fun_declaration, ..
+            } if fun_declaration.name.starts_with("test_") && fun_declaration.params.is_empty() => {
+                Some(fun_declaration.name.to_string())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Runs each test of a program, an error if the program doesn't parse.
+pub fn run_tests(source: &str) -> LoxResult<Vec<TestResult>> {
+    let statements = Parser::parse_str(source)?;
+
+    Ok(test_names(&statements)
+        .into_iter()
+        .map(|name| run_test(&statements, name))
+        .collect())
+}
+
+fn run_test(statements: &[Stmt], name: String) -> TestResult {
+    let output = Output::default();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(Box::new(output.clone()));
+    define_assertions(&mut interpreter);
+
+    let result = interpreter
+        .interpret(statements, false, false)
+        .map_err(LoxError::Runtime)
+        .and_then(|_| interpreter.evaluate_str(&format!("{name}()")));
+    let output = output.text();
+
+    TestResult {
+        name,
+        error: result.err(),
+        output,
+    }
+}
@@ -0,0 +1,108 @@
+use super::*;
+
+use crate::interpreter::error::RuntimeError;
+
+/// Runs a program's tests, returning each test's name and error message.
+fn run(source: &str) -> Vec<(String, Option<String>)> {
+    run_tests(source)
+        .unwrap()
+        .into_iter()
+        .map(|result| {
+            let error = result.error.map(|e| match e {
+                LoxError::Runtime(e) => e.to_string(),
+                e => panic!("Unexpected error {e}"),
+            });
+            (result.name, error)
+        })
+        .collect()
+}
+
+#[test]
+fn runs_test_functions() {
+    let src = "
+var count = 0;
+fun add(a, b) { return a + b; }
+fun helper() { assert(false); }
+fun test_add() { assert_eq(add(1, 2), 3); count = count + 1; }
+fun test_isolated() { assert_eq(count, 0); }
+fun test_failing() {
+    assert_eq(add(1, 2), \"3\");
+}
+fun test_with_parameters(x) { assert(false); }
+";
+    assert_eq!(
+        run(src),
+        [
+            ("test_add".to_string(), None),
+            ("test_isolated".to_string(), None),
+            (
+                "test_failing".to_string(),
+                Some("Assertion failed, line 8, column 26: expected \"3\", got 3".to_string())
+            ),
+        ]
+    );
+}
+
+#[test]
+fn asserts() {
+    let src = r#"
+fun test_truthy() { assert(1 < 2); assert(nil); }
+fun test_lines() { assert_eq("a
+b
+c", "a
+x
+c
+d"); }
+fun test_throws() {
+    var message = assert_throws(fun () { return 1 / 0; });
+    assert(message != nil);
+    assert_throws(fun () { return 1; });
+}
+fun test_not_a_function() { assert_throws(1); }
+"#;
+    let errors: Vec<String> = run(src)
+        .into_iter()
+        .map(|(_, error)| error.unwrap())
+        .collect();
+
+    assert_eq!(
+        errors,
+        [
+            "Assertion failed, line 2, column 43: expected a truthy value, got nil",
+            "Assertion failed, line 5, column 5: the strings differ (- expected, + actual):\n  a\n- x\n+ b\n  c\n- d",
+            "Assertion failed, line 12, column 38: expected an error, the function returned 1",
+            "Assertion failed, line 14, column 43: expected a function without parameters, got 1",
+        ]
+    );
+}
+
+#[test]
+fn keeps_what_tests_print() {
+    let results = run_tests("print \"setup\"; fun test_print() { print 1 + 1; }").unwrap();
+
+    assert_eq!(results[0].output, "setup\n2\n");
+    assert!(results[0].passed());
+}
+
+#[test]
+fn reports_errors_outside_assertions() {
+    let results = run_tests("fun test_error() { return nope; }").unwrap();
+
+    assert!(matches!(
+        results[0].error,
+        Some(LoxError::Runtime(RuntimeError::VarDoesNotExist { .. }))
+    ));
+}
+
+#[test]
+fn discovers_test_files() {
+    let files = discover(Path::new("examples/tests")).unwrap();
+
+    assert_eq!(
+        files,
+        [
+            PathBuf::from("examples/tests/math_test.lox"),
+            PathBuf::from("examples/tests/strings/strings_test.lox")
+        ]
+    );
+}
@@ -3,7 +3,7 @@ use crate::token_type::TokenType;
 
 use parse_display::Display;
 
-#[derive(Debug, Display, Hash, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Display, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 #[display("line {line}, column {column}")]
 pub struct Position {
     pub line: usize,
@@ -20,16 +20,34 @@ impl Position {
 #[display("<type: {token_type}, literal: {literal:?}, at {position}>")]
 pub struct Token {
     pub token_type: TokenType,
+    /// The source text of the token.
+    pub lexeme: String,
     pub literal: Option<LoxValue>,
     pub position: Position,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, literal: Option<LoxValue>, position: Position) -> Token {
+    pub fn new(
+        token_type: TokenType,
+        lexeme: String,
+        literal: Option<LoxValue>,
+        position: Position,
+    ) -> Token {
         Token {
             token_type,
+            lexeme,
             literal,
             position,
         }
     }
 }
+
+/// A comment, kept by the scanner as trivia for tools like the formatter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// The whole comment including the '//' or '/* */'.
+    pub text: String,
+    pub position: Position,
+    /// Whether the comment follows code on the line it starts on.
+    pub trailing: bool,
+}
@@ -47,12 +47,17 @@ pub enum TokenType {
     Less,
     #[display("<=")]
     LessEqual,
+    #[display("=>")]
+    Arrow,
 
     // Literals
     #[display("IDENTIFIER")]
     Identifier,
     #[display("STRING")]
     String,
+    /// A string segment that is followed by an interpolated `${...}` expression.
+    #[display("INTERPOLATION")]
+    Interpolation,
     #[display("NUMBER")]
     Number,
 
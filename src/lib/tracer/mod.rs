@@ -0,0 +1,201 @@
+//! A tracer that logs what a program does as JSON lines.
+//!
+//! Every statement, function call, return and assignment is written as one
+//! JSON object per line with an `event` field, a `depth` (0 for the script,
+//! 1 inside a function it calls and so on) and where it happened. Blocks
+//! aren't logged, the statements in them are.
+
+#[cfg(test)]
+mod tests;
+
+use std::io::{self, Write};
+
+use serde_json::{json, Value};
+
+use crate::ast::Stmt;
+use crate::interpreter::error::{RuntimeError, RuntimeResult};
+use crate::interpreter::hook::Hook;
+use crate::interpreter::Interpreter;
+use crate::lox_value::LoxValue;
+use crate::token::Position;
+
+/// Limits what a `Tracer` logs.
+#[derive(Debug, Clone, Default)]
+pub struct TraceOptions {
+    /// Only logs what happens while one of these functions runs, everything if empty.
+    pub functions: Vec<String>,
+    /// Only logs events up to this depth.
+    pub max_depth: Option<usize>,
+}
+
+pub struct Tracer<W> {
+    output: W,
+    options: TraceOptions,
+    /// Whether each function on the call stack is one of `options.functions`.
+    calls: Vec<bool>,
+    /// A write that failed outside of a statement, reported at the next one.
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(output: W, options: TraceOptions) -> Tracer<W> {
+        Tracer {
+            output,
+            options,
+            calls: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Returns whether events at `depth` pass the filters.
+    fn traced(&self, depth: usize) -> bool {
+        let in_function =
+            self.options.functions.is_empty() || self.calls.iter().any(|selected| *selected);
+
+        in_function && self.options.max_depth.is_none_or(|max| depth <= max)
+    }
+
+    fn log(&mut self, event: Value) -> io::Result<()> {
+        writeln!(self.output, "{event}")
+    }
+
+    /// Logs an event from a hook that can't fail, the error is kept for the next statement.
+    fn log_later(&mut self, event: Value) {
+        if let Err(e) = self.log(event) {
+            self.error.get_or_insert(e);
+        }
+    }
+}
+
+impl<W: Write> Hook for Tracer<W> {
+    fn statement(&mut self, _interpreter: &mut Interpreter, statement: &Stmt) -> RuntimeResult<()> {
+        let position = statement.get_position();
+        let fail = |e: io::Error| {
+            RuntimeError::Generic(format!("Couldn't write the trace: {e}"), position)
+        };
+
+        if let Some(e) = self.error.take() {
+            return Err(fail(e));
+        }
+        if matches!(statement, Stmt::Block(..)) || !self.traced(self.calls.len()) {
+            return Ok(());
+        }
+
+        self.log(json!({
+            "event": "statement",
+            "kind": kind(statement),
+            "line": position.line,
+            "column": position.column,
+            "depth": self.calls.len(),
+        }))
+        .map_err(fail)
+    }
+
+    fn enter(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        name: &str,
+        arguments: &[LoxValue],
+        position: Position,
+    ) {
+        let selected = self
+            .options
+            .functions
+            .iter()
+            .any(|function| function == name);
+        let depth = self.calls.len();
+        self.calls.push(selected);
+
+        if self.traced(depth) {
+            let arguments: Vec<Value> = arguments.iter().map(to_json).collect();
+            self.log_later(json!({
+                "event": "call",
+                "function": name,
+                "arguments": arguments,
+                "line": position.line,
+                "column": position.column,
+                "depth": depth,
+            }));
+        }
+    }
+
+    fn exit(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        name: &str,
+        result: &RuntimeResult<LoxValue>,
+    ) {
+        let depth = self.calls.len().saturating_sub(1);
+
+        if self.traced(depth) {
+            let event = match result {
+                Ok(value) => json!({
+                    "event": "return",
+                    "function": name,
+                    "value": to_json(value),
+                    "depth": depth,
+                }),
+                Err(e) => json!({
+                    "event": "error",
+                    "function": name,
+                    "message": e.to_string(),
+                    "depth": depth,
+                }),
+            };
+            self.log_later(event);
+        }
+
+        self.calls.pop();
+    }
+
+    fn assign(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        name: &str,
+        value: &LoxValue,
+        position: Position,
+    ) {
+        let depth = self.calls.len();
+
+        if self.traced(depth) {
+            self.log_later(json!({
+                "event": "assign",
+                "name": name,
+                "value": to_json(value),
+                "line": position.line,
+                "column": position.column,
+                "depth": depth,
+            }));
+        }
+    }
+}
+
+/// Names the kind of a statement in a trace.
+fn kind(statement: &Stmt) -> &'static str {
+    use Stmt::*;
+
+    match statement {
+        PrintStmt(_) => "print",
+        ExprStmt(_) => "expression",
+        Var { .. } => "var",
+        Block(..) => "block",
+        IfStmt { .. } => "if",
+        WhileStmt { .. } => "while",
+        ForStmt { .. } => "for",
+        BreakStmt(_) => "break",
+        ContinueStmt(_) => "continue",
+        FunStmt { .. } => "fun",
+        ReturnStmt { .. } => "return",
+    }
+}
+
+/// Converts a value to JSON, functions become the string they print as.
+fn to_json(value: &LoxValue) -> Value {
+    match value {
+        LoxValue::Boolean(boolean) => json!(boolean),
+        LoxValue::Number(number) => json!(number),
+        LoxValue::String(string) => json!(&**string),
+        LoxValue::Nil => Value::Null,
+        value => json!(value.to_string()),
+    }
+}
@@ -0,0 +1,135 @@
+use serde_json::{json, Value};
+
+use super::*;
+use crate::utils::{run_with_hook, Output};
+
+const SRC: &str = "\
+fun add(a, b) {
+    var sum = a + b;
+    return sum;
+}
+fun twice(n) {
+    return add(n, n);
+}
+var x = 1;
+x = twice(x);
+print add(x, 1);";
+
+/// Traces `SRC`, returning the logged events.
+fn trace(options: TraceOptions) -> Vec<Value> {
+    let output = Output::default();
+    run_with_hook(SRC, Box::new(Tracer::new(output.clone(), options)));
+
+    output
+        .text()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+/// Summarises events as their kind and the function or variable they're about.
+fn summary(events: &[Value]) -> Vec<String> {
+    events
+        .iter()
+        .map(|event| match event["event"].as_str().unwrap() {
+            "statement" => format!("{} {}", event["kind"], event["line"]),
+            "call" | "return" => format!("{} {}", event["event"], event["function"]),
+            "assign" => format!("assign {} = {}", event["name"], event["value"]),
+            other => other.to_string(),
+        })
+        .map(|summary| summary.replace('"', ""))
+        .collect()
+}
+
+#[test]
+fn traces_everything() {
+    let events = trace(TraceOptions::default());
+
+    assert_eq!(
+        summary(&events),
+        [
+            "fun 1",
+            "fun 5",
+            "var 8",
+            "assign x = 1.0",
+            "expression 9",
+            "call twice",
+            "return 6",
+            "call add",
+            "var 2",
+            "assign sum = 2.0",
+            "return 3",
+            "return add",
+            "return twice",
+            "assign x = 2.0",
+            "print 10",
+            "call add",
+            "var 2",
+            "assign sum = 3.0",
+            "return 3",
+            "return add",
+        ]
+    );
+
+    assert_eq!(
+        events[5],
+        json!({
+            "event": "call",
+            "function": "twice",
+            "arguments": [1.0],
+            "line": 9,
+            "column": 11,
+            "depth": 0,
+        })
+    );
+    assert_eq!(events[8]["depth"], 2);
+    assert_eq!(events[12]["value"], 2.0);
+}
+
+#[test]
+fn filters_by_function() {
+    let events = trace(TraceOptions {
+        functions: vec!["twice".to_string()],
+        max_depth: None,
+    });
+
+    assert_eq!(
+        summary(&events),
+        [
+            "call twice",
+            "return 6",
+            "call add",
+            "var 2",
+            "assign sum = 2.0",
+            "return 3",
+            "return add",
+            "return twice",
+        ]
+    );
+}
+
+#[test]
+fn filters_by_depth() {
+    let events = trace(TraceOptions {
+        functions: Vec::new(),
+        max_depth: Some(0),
+    });
+
+    assert!(events.iter().all(|event| event["depth"] == 0));
+    assert_eq!(
+        summary(&events),
+        [
+            "fun 1",
+            "fun 5",
+            "var 8",
+            "assign x = 1.0",
+            "expression 9",
+            "call twice",
+            "return twice",
+            "assign x = 2.0",
+            "print 10",
+            "call add",
+            "return add",
+        ]
+    );
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+
+/// A type that can be written in an annotation, e.g. `var x: number? = nil;`.
+///
+/// Annotations are only read by the type checker, the interpreter ignores them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    /// The type of anything that isn't annotated and can't be inferred.
+    Any,
+    Nil,
+    Bool,
+    Number,
+    String,
+    Function {
+        params: Vec<Type>,
+        ret: Box<Type>,
+    },
+    /// A type that can also be `nil`, written with a trailing '?'.
+    Nilable(Box<Type>),
+}
+
+impl Type {
+    /// Returns the type that also allows `nil`.
+    pub fn nilable(self) -> Type {
+        match self {
+            Type::Any | Type::Nil | Type::Nilable(_) => self,
+            ty => Type::Nilable(Box::new(ty)),
+        }
+    }
+
+    /// Returns the type without `nil`, what a value is known to be after checking it isn't nil.
+    pub fn non_nil(&self) -> Type {
+        match self {
+            Type::Nilable(ty) => *ty.clone(),
+            ty => ty.clone(),
+        }
+    }
+
+    /// Checks if a value of this type can be used where `other` is expected.
+    pub fn is_assignable_to(&self, other: &Type) -> bool {
+        use Type::*;
+
+        match (self, other) {
+            (Any, _) | (_, Any) => true,
+            (Nil, Nilable(_)) => true,
+            (Nilable(from), Nilable(to)) => from.is_assignable_to(to),
+            (from, Nilable(to)) => from.is_assignable_to(to),
+            (
+                Function { params, ret },
+                Function {
+                    params: other_params,
+                    ret: other_ret,
+                },
+            ) => {
+                params.len() == other_params.len()
+                    // Parameters go the other way, the function is given what `other` would be given.
+                    && params
+                        .iter()
+                        .zip(other_params)
+                        .all(|(param, other)| other.is_assignable_to(param))
+                    && ret.is_assignable_to(other_ret)
+            }
+            (from, to) => from == to,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Type::*;
+
+        match self {
+            Any => write!(f, "any"),
+            Nil => write!(f, "nil"),
+            Bool => write!(f, "bool"),
+            Number => write!(f, "number"),
+            String => write!(f, "string"),
+            Function { params, ret } => {
+                let params: Vec<std::string::String> =
+                    params.iter().map(|param| param.to_string()).collect();
+                write!(f, "fun({}): {}", params.join(", "), ret)
+            }
+            // Without the brackets the '?' would apply to the return type.
+            Nilable(ty) if matches!(**ty, Function { .. }) => write!(f, "({ty})?"),
+            Nilable(ty) => write!(f, "{ty}?"),
+        }
+    }
+}
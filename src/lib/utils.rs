@@ -1,31 +1,79 @@
-use std::fs::File;
-use std::io::Read;
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
 
-use std::fmt::Display;
+#[cfg(test)]
+use crate::interpreter::{hook::Hook, Interpreter};
 
+#[cfg(test)]
 pub fn read_file(path: &str) -> String {
-    let mut file = File::open(path).expect(&format!("\n\nError opening: {}\n", path));
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut file = File::open(path).unwrap_or_else(|_| panic!("\n\nError opening: {}\n", path));
 
     let mut src = String::new();
     file.read_to_string(&mut src)
-        .expect(&format!("\n\nError reading: {}\n", path));
+        .unwrap_or_else(|_| panic!("\n\nError reading: {}\n", path));
 
     src
 }
 
-pub fn log_items<T: Display>(log_title: &str, items: &Vec<T>) {
-    if items.len() != 0 {
-        let sep = "======================================================================================";
+/// Output that can still be read once whatever writes to it, like an interpreter, is gone.
+#[derive(Clone, Default)]
+pub(crate) struct Output(Rc<RefCell<Vec<u8>>>);
 
-        println!("\n{}", sep);
-        println!("{}:\n{}\n", log_title, sep);
+impl Output {
+    #[cfg(test)]
+    pub fn bytes(&self) -> Vec<u8> {
+        self.0.borrow().clone()
+    }
 
-        for (i, item) in items.iter().enumerate() {
-            println!("{} -> Error: {}", i, item);
-        }
+    /// What was written, with invalid UTF-8 replaced.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).to_string()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
 
-        // println!("\nFinished reporting errors.");
-        println!("\n{}\n", sep);
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
-    panic!()
+}
+
+/// Runs `source` with `hook` watching it, throwing away what it prints.
+#[cfg(test)]
+pub fn run_with_hook(source: &str, hook: Box<dyn Hook>) {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(Box::new(io::sink()));
+    interpreter.set_hook(hook);
+    interpreter.interpret_str(source).unwrap();
+}
+
+/// Compares two texts line by line, marking lines only `expected` has with `-` and
+/// lines only `actual` has with `+`.
+pub(crate) fn line_diff(expected: &[&str], actual: &[&str]) -> String {
+    let mut diff = Vec::new();
+
+    for line in 0..expected.len().max(actual.len()) {
+        match (expected.get(line), actual.get(line)) {
+            (Some(expected), Some(actual)) if expected == actual => {
+                diff.push(format!("  {expected}"))
+            }
+            (expected, actual) => {
+                if let Some(expected) = expected {
+                    diff.push(format!("- {expected}"));
+                }
+                if let Some(actual) = actual {
+                    diff.push(format!("+ {actual}"));
+                }
+            }
+        }
+    }
+
+    diff.join("\n")
 }
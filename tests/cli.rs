@@ -0,0 +1,35 @@
+//! Runs the `lox_one` binary.
+
+use std::process::Command;
+
+#[test]
+fn traces_only_json_lines_to_stderr() {
+    let directory = std::env::temp_dir().join(format!("lox_one_cli_{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    // The unused local makes the linter warn.
+    let path = directory.join("trace.lox");
+    std::fs::write(
+        &path,
+        "fun f(a) { var unused = 1; return a; }\nprint f(2);\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_lox_one"))
+        .args(["run", "--trace"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "2\n");
+
+    let trace = String::from_utf8(output.stderr).unwrap();
+    assert!(trace.lines().count() > 3, "{trace}");
+    for line in trace.lines() {
+        assert!(
+            serde_json::from_str::<serde_json::Value>(line).is_ok(),
+            "Not JSON: {line}"
+        );
+    }
+}